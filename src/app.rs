@@ -1,7 +1,18 @@
-use crate::circuit::Gate;
-use crate::dag::CircuitDAG;
-use crate::menu::is_parameterized_gate;
-use crate::params::{format_param, parse_params};
+use crate::circuit::{Gate, has_distinct_inverse, repeated_qubit};
+use crate::config::{DEFAULT_MAX_SIM_QUBITS, Settings};
+use crate::dag::{CircuitDAG, complete_gate_token, parse_command};
+use crate::diff::{DiffLine, diff_counts, line_diff};
+use crate::matrix::compute_circuit_unitary;
+use crate::menu::{
+    GATE_MENU, MenuItem, find_menu_item, gate_name, is_parameterized_gate, is_single_qubit_gate,
+    menu_item_matches, validate_param_count,
+};
+use crate::params::{format_param, parse_param_expr, parse_params};
+use crate::quantum::{
+    DEFAULT_NOISE_PARAM, IncrementalSimulator, NOISE_TYPES, StateVector, simulate_density,
+};
+use std::ops::RangeInclusive;
+use std::time::SystemTime;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Focus {
@@ -15,6 +26,151 @@ pub enum Focus {
     EditParam,
     EditTarget,
     EditControl,
+    Observables,
+    SelectSwap,
+    Stats,
+    InputPath,
+    Command,
+    InputState,
+    InputLabel,
+    SelectRange,
+    SelectRegion,
+    Help,
+    Histogram,
+    Diff,
+}
+
+/// Probability below which basis states are hidden from the state panel.
+pub const DEFAULT_AMP_THRESHOLD: f64 = 1e-6;
+/// Range `[` / `]` move the threshold through, one decade per press.
+pub const MIN_AMP_THRESHOLD: f64 = 1e-12;
+pub const MAX_AMP_THRESHOLD: f64 = 1e-1;
+
+/// Steps in a circuit generated by `G` or a bare `random` command.
+pub const DEFAULT_RANDOM_DEPTH: usize = 12;
+
+/// Gate set the `T` key rewrites the circuit into.
+pub const TRANSPILE_BASIS: &[&str] = &["RZ", "SX", "CX"];
+
+/// Shots drawn by the Enter measure-and-sample shortcut.
+pub const SAMPLE_SHOTS: usize = 1024;
+
+/// Length of the Add Gate menu's recently-used row.
+pub const MAX_RECENT_GATES: usize = 5;
+
+/// Where Ctrl+S writes the circuit.
+pub const SAVE_PATH: &str = "circuit.qasm";
+
+/// Periodic backup of the circuit, removed on a clean quit.
+pub const AUTOSAVE_PATH: &str = ".q-deck-autosave.qasm";
+
+/// Where the `csv` command writes the statevector.
+pub const STATE_CSV_PATH: &str = "state.csv";
+
+const RECOVER_PROMPT: &str = "Unsaved work from a previous session: R Recover  Esc Discard";
+
+/// Offer recovery when an autosave exists and is newer than the last explicit save.
+pub fn should_recover(autosave: Option<SystemTime>, saved: Option<SystemTime>) -> bool {
+    match (autosave, saved) {
+        (Some(auto), Some(saved)) => auto > saved,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+/// Placeholder for the state panels when `num_qubits` exceeds the live
+/// simulation limit `max_qubits`; None when the register can be simulated.
+pub fn sim_limit_message(num_qubits: usize, max_qubits: usize) -> Option<String> {
+    (num_qubits > max_qubits).then(|| format!("State too large to simulate ({num_qubits} qubits)"))
+}
+
+/// Whether measure-and-sample has to add measurements first: true when no
+/// qubit in `dag` is measured.
+pub fn needs_measurements(dag: &CircuitDAG) -> bool {
+    !dag.nodes
+        .values()
+        .any(|n| n.type_name == "MEASURE" || n.measure_source >= 0)
+}
+
+/// Qubit drawn on display row `row`, counted from the top. With `reversed`
+/// the highest qubit is on top. The mapping is its own inverse, so it also
+/// turns a qubit into its row.
+pub fn display_row_qubit(row: usize, num_qubits: usize, reversed: bool) -> usize {
+    if reversed {
+        num_qubits.saturating_sub(row + 1)
+    } else {
+        row
+    }
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum WireStyle {
+    #[default]
+    Single,
+    Double,
+}
+
+impl WireStyle {
+    pub fn toggled(self) -> Self {
+        match self {
+            WireStyle::Single => WireStyle::Double,
+            WireStyle::Double => WireStyle::Single,
+        }
+    }
+}
+
+/// How the statevector view writes each amplitude.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum AmpFormat {
+    /// `a+bi`
+    #[default]
+    Rectangular,
+    /// `r∠θ°`
+    Polar,
+    /// `|α|` only
+    Magnitude,
+}
+
+impl AmpFormat {
+    pub fn cycled(self) -> Self {
+        match self {
+            AmpFormat::Rectangular => AmpFormat::Polar,
+            AmpFormat::Polar => AmpFormat::Magnitude,
+            AmpFormat::Magnitude => AmpFormat::Rectangular,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AmpFormat::Rectangular => "a+bi",
+            AmpFormat::Polar => "polar",
+            AmpFormat::Magnitude => "magnitude",
+        }
+    }
+
+    /// The format whose `label` is `label`.
+    pub fn from_label(label: &str) -> Option<Self> {
+        [
+            AmpFormat::Rectangular,
+            AmpFormat::Polar,
+            AmpFormat::Magnitude,
+        ]
+        .into_iter()
+        .find(|f| f.label() == label)
+    }
+}
+
+/// Step-by-step simulation playback.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Playback {
+    #[default]
+    Off,
+    Playing,
+    Paused,
 }
 
 #[derive(Clone, Debug)]
@@ -24,15 +180,43 @@ pub struct EditOption {
     pub ctrl_idx: isize, // -1 for the single Control field
 }
 
+/// Shot counts shown by the histogram overlay.
+#[derive(Clone, Debug)]
+pub struct SampleHistogram {
+    /// Measured qubits; bit k of an outcome is `qubits[k]`
+    pub qubits: Vec<usize>,
+    /// (outcome, count), ascending by outcome
+    pub counts: Vec<(usize, usize)>,
+    pub shots: usize,
+    /// The circuit before measurements were added for the run, restored
+    /// unless they are kept
+    pub undo: Option<CircuitDAG>,
+}
+
+/// The state panels' contents as of the last refresh while live
+/// simulation is off.
+#[derive(Clone, Debug)]
+pub struct FrozenSimulation {
+    pub state: StateVector,
+    pub noisy: Option<Vec<f64>>,
+    /// QASM, cursor step and input state the snapshot was simulated for
+    key: (String, isize, usize),
+}
+
 pub struct App {
     pub dag: CircuitDAG,
     pub cursor_qubit: usize,
     pub cursor_step: isize,
     pub qubit_scroll: usize,
+    // Draw the highest qubit on top; display only, the cursor keeps true
+    // qubit indices
+    pub reverse_qubits: bool,
     pub width: u16,
     pub height: u16,
     pub focus: Focus,
     pub status_msg: String,
+    /// Draw `status_msg` in red
+    pub status_is_error: bool,
 
     // QASM editor state
     pub qasm_text: String,
@@ -40,17 +224,35 @@ pub struct App {
     pub qasm_cursor: usize, // byte offset into qasm_text
     pub qasm_scroll: u16,   // vertical scroll offset (lines)
     pub qasm_errors: Vec<(usize, String)>,
+    // Tab completion in progress: typed prefix and index of the match shown
+    pub qasm_completion: Option<(String, usize)>,
+
+    // First line shown in the `?` help overlay
+    pub help_scroll: usize,
+
+    // Circuit before the last transpile, and the QASM it was turned into;
+    // `T` restores it while the circuit is unchanged
+    pub transpile_undo: Option<(CircuitDAG, String)>,
 
     // Menu state
     pub menu_cat: usize,
     pub menu_item: usize,
+    pub menu_filter: String,
+    // Up/Down and Left/Right in the menu wrap past the ends (`:wrap` toggles)
+    pub menu_wrap: bool,
+    // Place the next single-qubit gate on every qubit at the cursor step
+    pub apply_all_qubits: bool,
 
     // Gate placement pending state
     pub pending_gate: String,
     pub target_qubit: usize,
     pub param_input: String,
+    pub path_input: String,
     pub control_qubits: Vec<usize>,
 
+    // Corner (step, qubit) of a region selection; the cursor is the other
+    pub region_anchor: (isize, usize),
+
     // Edit gate state
     pub edit_gate: Option<Gate>,
     pub edit_menu_idx: usize,
@@ -59,10 +261,73 @@ pub struct App {
 
     // State panel view toggle
     pub show_statevector: bool,
+    pub amp_format: AmpFormat,
 
     // Matrix view toggle
     pub show_matrix: bool,
     pub matrix_scroll: usize,
+
+    // Shade the column after the cursor, where simulation stops
+    pub show_sim_frontier: bool,
+
+    // Marginal P(|1⟩) at the end of each wire
+    pub show_qubit_probs: bool,
+
+    // Quantum wire glyphs in the circuit view
+    pub wire_style: WireStyle,
+
+    // Apply noise channels (density-matrix simulation) in the probability panel
+    pub noise_enabled: bool,
+
+    // Saved QASM gets a barrier between steps (`:layers` toggles)
+    pub layer_barriers: bool,
+
+    // Dominant basis state, entropy and gate count in the controls row
+    // (`:summary` toggles)
+    pub show_summary: bool,
+
+    // Statevector view rotated so the first nonzero amplitude is real and
+    // positive (`:phase` toggles)
+    pub canonical_phase: bool,
+
+    // Registers wider than this are not simulated live (`--max-sim-qubits`)
+    pub max_sim_qubits: usize,
+
+    // Running simulation backing the state panels
+    pub simulator: IncrementalSimulator,
+
+    // Resimulate on every change; when off (`F`) the state panels keep
+    // `frozen_sim` until `.` refreshes it
+    pub sim_auto: bool,
+    pub frozen_sim: Option<FrozenSimulation>,
+
+    // Shot counts from Enter (measure and sample), shown as an overlay
+    pub histogram: Option<SampleHistogram>,
+
+    // Basis state the simulation starts from; the circuit is left untouched
+    pub input_state: usize,
+
+    // Text of circuit.qasm as last saved or found at startup, and the diff
+    // of the current circuit against it shown by `D`
+    pub saved_qasm: Option<String>,
+    pub diff: Option<Vec<DiffLine>>,
+    pub diff_scroll: usize,
+
+    // Autosave: last text written, and whether a recovery offer is open
+    pub last_autosave_qasm: String,
+    pub recover_pending: bool,
+
+    // Circuit listing panel (gates as readable sentences)
+    pub show_listing: bool,
+    pub wrap_circuit: bool,
+    pub amp_threshold: f64,
+    pub playback: Playback,
+    /// Recently placed gate types, most recent first
+    pub recent_gates: Vec<String>,
+
+    // Observables panel (one Pauli string per line)
+    pub show_observables: bool,
+    pub observables_input: String,
 }
 
 impl App {
@@ -75,39 +340,81 @@ impl App {
             cursor_qubit: 0,
             cursor_step: 0,
             qubit_scroll: 0,
+            reverse_qubits: false,
             width: 80,
             height: 24,
             focus: Focus::Circuit,
             status_msg: String::new(),
+            status_is_error: false,
             qasm_text: String::new(),
             last_qasm: String::new(),
             qasm_cursor: 0,
             qasm_scroll: 0,
             qasm_errors: vec![],
+            qasm_completion: None,
+            help_scroll: 0,
+            transpile_undo: None,
             menu_cat: 0,
             menu_item: 0,
+            menu_filter: String::new(),
+            menu_wrap: true,
+            apply_all_qubits: false,
             pending_gate: String::new(),
             target_qubit: 0,
             param_input: String::new(),
+            path_input: String::new(),
             control_qubits: vec![],
+            region_anchor: (0, 0),
             edit_gate: None,
             edit_menu_idx: 0,
             edit_orig_step: 0,
             edit_control_idx: -1,
             show_statevector: false,
+            amp_format: AmpFormat::Rectangular,
             show_matrix: false,
             matrix_scroll: 0,
+            show_sim_frontier: true,
+            show_qubit_probs: false,
+            wire_style: WireStyle::Single,
+            noise_enabled: false,
+            layer_barriers: false,
+            show_summary: false,
+            canonical_phase: false,
+            max_sim_qubits: DEFAULT_MAX_SIM_QUBITS,
+            simulator: IncrementalSimulator::new(),
+            sim_auto: true,
+            frozen_sim: None,
+            histogram: None,
+            input_state: 0,
+            saved_qasm: None,
+            diff: None,
+            diff_scroll: 0,
+            last_autosave_qasm: String::new(),
+            recover_pending: false,
+            show_listing: false,
+            wrap_circuit: false,
+            amp_threshold: DEFAULT_AMP_THRESHOLD,
+            playback: Playback::Off,
+            recent_gates: Vec::new(),
+            show_observables: false,
+            observables_input: String::new(),
         };
+        app.apply_settings(&Settings::load());
         app.sync_from_dag();
+        app.last_autosave_qasm = app.qasm_text.clone();
         app
     }
 
+    /// Regenerate the editor text from the DAG. The cursor keeps its row and
+    /// column, clamped to the nearest position in the new text.
     pub fn sync_from_dag(&mut self) {
+        let (row, col) = self.qasm_cursor_row_col();
         let qasm = self.dag.to_qasm();
         self.qasm_text = qasm.clone();
         self.last_qasm = qasm;
-        self.qasm_cursor = self.qasm_text.len();
-        self.qasm_scroll = 0;
+        self.qasm_cursor = self.qasm_offset(row, col);
+        let (row, _) = self.qasm_cursor_row_col();
+        self.qasm_scroll = self.qasm_scroll.min(row as u16);
         self.qasm_errors.clear();
     }
 
@@ -115,18 +422,229 @@ impl App {
         if self.qasm_text != self.last_qasm {
             let mut new_dag = CircuitDAG::new();
             self.qasm_errors = new_dag.parse_qasm(&self.qasm_text);
+            if let Some(w) = new_dag.warnings.first() {
+                let more = new_dag.warnings.len() - 1;
+                self.status_msg = if more > 0 {
+                    format!("Warning: line {}: {} (+{more} more)", w.line + 1, w.message)
+                } else {
+                    format!("Warning: line {}: {}", w.line + 1, w.message)
+                };
+            }
             self.dag = new_dag;
             self.last_qasm = self.qasm_text.clone();
         }
     }
 
+    /// Bring the cursor back inside the circuit after the QASM changed under
+    /// it: `cursor_qubit` below `num_qubits` and `cursor_step` at most one
+    /// past the last step. A cursor that had to move and lands on an empty
+    /// cell snaps to the nearest gate.
+    pub fn clamp_cursor(&mut self) {
+        let qubit = self.cursor_qubit.min(self.dag.num_qubits.saturating_sub(1));
+        let step = self.cursor_step.clamp(0, self.dag.max_step() + 1);
+        let moved = (step, qubit) != (self.cursor_step, self.cursor_qubit);
+        self.cursor_step = step;
+        self.cursor_qubit = qubit;
+        if !moved || self.dag.get_node_at(step, qubit).is_some() {
+            return;
+        }
+        let nearest = self
+            .dag
+            .nodes
+            .values()
+            .flat_map(|n| {
+                (0..self.dag.num_qubits)
+                    .filter(|&q| n.references(q))
+                    .map(move |q| (n.step, q))
+            })
+            .min_by_key(|&(s, q)| ((s - step).abs() + q.abs_diff(qubit) as isize, s, q));
+        if let Some((s, q)) = nearest {
+            self.cursor_step = s;
+            self.cursor_qubit = q;
+        }
+    }
+
+    /// Replace the editor text with canonical QASM if it parses cleanly,
+    /// keeping the cursor on the same line where possible.
+    pub fn reformat_qasm(&mut self) -> bool {
+        let mut new_dag = CircuitDAG::new();
+        let errors = new_dag.parse_qasm(&self.qasm_text);
+        if !errors.is_empty() {
+            self.qasm_errors = errors;
+            return false;
+        }
+        let (row, _) = self.qasm_cursor_row_col();
+        self.dag = new_dag;
+        self.qasm_text = self.dag.to_qasm();
+        self.last_qasm = self.qasm_text.clone();
+        self.qasm_errors.clear();
+        self.qasm_cursor = self.qasm_offset(row, 0);
+        true
+    }
+
     pub fn circuit(&self) -> crate::circuit::Circuit {
         self.dag.to_circuit()
     }
 
+    /// Items listed in the Add Gate menu: the current category, or every
+    /// matching item across categories while a filter is typed.
+    pub fn menu_items(&self) -> Vec<&'static MenuItem> {
+        if self.menu_filter.is_empty() {
+            GATE_MENU[self.menu_cat].items.iter().collect()
+        } else {
+            GATE_MENU
+                .iter()
+                .flat_map(|cat| cat.items.iter())
+                .filter(|item| menu_item_matches(item, &self.menu_filter))
+                .collect()
+        }
+    }
+
+    pub fn selected_menu_item(&self) -> Option<&'static MenuItem> {
+        self.menu_items().get(self.menu_item).copied()
+    }
+
+    /// State after all gates up to and including `up_to_step`, served from
+    /// the per-step simulation cache.
+    pub fn cached_state(&mut self, up_to_step: isize) -> &StateVector {
+        let circuit = self.circuit();
+        self.simulator.simulate(&circuit, up_to_step)
+    }
+
+    /// Message shown in place of the state panels when the register is too
+    /// wide to simulate live, or None when it can be simulated.
+    pub fn simulation_limit_message(&self) -> Option<String> {
+        sim_limit_message(self.dag.num_qubits, self.max_sim_qubits)
+    }
+
+    /// State after all gates up to and including the cursor column, or the
+    /// frozen snapshot while live simulation is off.
+    pub fn simulated_state(&mut self) -> StateVector {
+        if self.sim_auto {
+            return self.cached_state(self.cursor_step).clone();
+        }
+        if self.frozen_sim.is_none() {
+            self.refresh_simulation();
+        }
+        self.frozen_sim
+            .as_ref()
+            .map(|frozen| frozen.state.clone())
+            .unwrap_or_else(|| StateVector::new(1))
+    }
+
+    /// Basis-state probabilities with noise channels applied, up to the cursor column.
+    /// None when the register is too large for density-matrix simulation.
+    pub fn noisy_probabilities(&self) -> Option<Vec<f64>> {
+        if !self.sim_auto
+            && let Some(frozen) = &self.frozen_sim
+        {
+            return frozen.noisy.clone();
+        }
+        simulate_density(&self.circuit(), self.input_state, self.cursor_step)
+            .map(|rho| rho.probabilities())
+    }
+
+    /// Resimulate the current circuit into the frozen snapshot.
+    pub fn refresh_simulation(&mut self) {
+        let state = self.cached_state(self.cursor_step).clone();
+        let noisy = simulate_density(&self.circuit(), self.input_state, self.cursor_step)
+            .map(|rho| rho.probabilities());
+        self.frozen_sim = Some(FrozenSimulation {
+            state,
+            noisy,
+            key: (self.last_qasm.clone(), self.cursor_step, self.input_state),
+        });
+    }
+
+    /// Whether the frozen snapshot no longer matches the circuit, cursor or
+    /// input state. Always false while simulating live.
+    pub fn needs_recompute(&self) -> bool {
+        !self.sim_auto
+            && self.frozen_sim.as_ref().is_none_or(|frozen| {
+                let (qasm, step, input) = &frozen.key;
+                *qasm != self.last_qasm || *step != self.cursor_step || *input != self.input_state
+            })
+    }
+
+    /// Switch between live simulation and a snapshot refreshed on demand.
+    /// Freezing keeps the state as it is now.
+    pub fn toggle_sim_auto(&mut self) {
+        self.sim_auto = !self.sim_auto;
+        if self.sim_auto {
+            self.frozen_sim = None;
+        } else if self.simulation_limit_message().is_none() {
+            self.refresh_simulation();
+        }
+    }
+
+    /// Measure every qubit if nothing is measured yet, draw `SAMPLE_SHOTS`
+    /// shots of the measured qubits from the final state and open the
+    /// histogram overlay. Added measurements are undone by `close_histogram`
+    /// unless kept.
+    pub fn measure_and_sample(&mut self) {
+        if let Some(msg) = self.simulation_limit_message() {
+            self.status_msg = msg;
+            self.status_is_error = true;
+            return;
+        }
+        let undo = needs_measurements(&self.dag).then(|| {
+            let before = self.dag.clone();
+            self.dag.measure_all();
+            self.sync_from_dag();
+            before
+        });
+        let circuit = self.circuit();
+        let mut qubits: Vec<usize> = circuit
+            .gates
+            .iter()
+            .filter_map(|g| match g.type_name.as_str() {
+                "MEASURE" => Some(g.target),
+                "MCX" => Some(g.measure_source as usize),
+                _ => None,
+            })
+            .collect();
+        qubits.sort_unstable();
+        qubits.dedup();
+        let seed = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        let counts =
+            self.cached_state(self.dag.max_step())
+                .sample_measurements(&qubits, SAMPLE_SHOTS, seed);
+        self.histogram = Some(SampleHistogram {
+            qubits,
+            counts,
+            shots: SAMPLE_SHOTS,
+            undo,
+        });
+        self.focus = Focus::Histogram;
+    }
+
+    /// Close the histogram overlay, keeping or removing the measurements
+    /// `measure_and_sample` added.
+    pub fn close_histogram(&mut self, keep: bool) {
+        let Some(histogram) = self.histogram.take() else {
+            return;
+        };
+        self.focus = Focus::Circuit;
+        if let Some(before) = histogram.undo {
+            if keep {
+                self.status_msg = "Kept the added measurements".to_string();
+            } else {
+                self.dag = before;
+                self.cursor_step = self.cursor_step.min(self.dag.max_step() + 1);
+                self.sync_from_dag();
+                self.status_msg = "Removed the added measurements".to_string();
+            }
+        }
+    }
+
     pub fn place_gate(&mut self, gate_type: &str, target_q: isize) -> bool {
+        if self.apply_all_qubits && target_q < 0 && is_single_qubit_gate(gate_type) {
+            return self.place_gate_on_all_qubits(gate_type);
+        }
         let qubits_needed: Option<Vec<usize>> = match gate_type {
-            "CX" | "CZ" | "SWAP" | "CH" | "CRX" | "CRY" | "CRZ" | "CU1" => {
+            "CX" | "CZ" | "SWAP" | "CH" | "CRX" | "CRY" | "CRZ" | "CU1" | "CU3" | "CP" | "MS" => {
                 if target_q < 0 {
                     return false;
                 }
@@ -150,15 +668,34 @@ impl App {
             _ => Some(vec![self.cursor_qubit]),
         };
 
-        if let Some(ref qs) = qubits_needed {
-            if !self.dag.can_place_gate_at(self.cursor_step, qs) {
-                self.status_msg =
-                    "Cannot place: qubit already used by another gate at this step".to_string();
-                self.param_input.clear();
-                self.control_qubits.clear();
-                self.pending_gate.clear();
-                return false;
-            }
+        // A leftover parameter buffer never attaches angles to a fixed gate
+        // such as SWAP or CX
+        let params: Vec<f64> = if !self.param_input.is_empty() && is_parameterized_gate(gate_type) {
+            parse_params(&self.param_input).unwrap_or_default()
+        } else {
+            vec![]
+        };
+        if let Err(e) = validate_param_count(gate_type, params.len()) {
+            self.status_msg = e;
+            return false;
+        }
+
+        if let Some(ref qs) = qubits_needed
+            && let Some(q) = repeated_qubit(qs)
+        {
+            self.status_msg = format!("Cannot place {gate_type}: q[{q}] is used twice");
+            return false;
+        }
+
+        if let Some(ref qs) = qubits_needed
+            && !self.dag.can_place_gate_at(self.cursor_step, qs)
+        {
+            self.status_msg =
+                "Cannot place: qubit already used by another gate at this step".to_string();
+            self.param_input.clear();
+            self.control_qubits.clear();
+            self.pending_gate.clear();
+            return false;
         }
 
         // Remove existing gates
@@ -168,14 +705,16 @@ impl App {
             }
         }
 
-        let params: Vec<f64> = if !self.param_input.is_empty() {
-            parse_params(&self.param_input).unwrap_or_default()
-        } else {
-            vec![]
-        };
-
         match gate_type {
-            "CX" | "CZ" | "SWAP" | "CH" | "CRX" | "CRY" | "CRZ" | "CU1" => {
+            "CX" | "CZ" | "SWAP" | "CH" => {
+                self.dag.add_gate(
+                    gate_type,
+                    target_q as usize,
+                    self.cursor_step,
+                    Some(self.cursor_qubit),
+                );
+            }
+            "CRX" | "CRY" | "CRZ" | "CU1" | "CP" => {
                 let tq = target_q as usize;
                 if !params.is_empty() {
                     self.dag.add_parameterized_gate(
@@ -190,6 +729,33 @@ impl App {
                         .add_gate(gate_type, tq, self.cursor_step, Some(self.cursor_qubit));
                 }
             }
+            "CU3" => {
+                let mut p = params;
+                while p.len() < 3 {
+                    p.push(0.0);
+                }
+                self.dag.add_parameterized_gate(
+                    "CU3",
+                    target_q as usize,
+                    self.cursor_step,
+                    p[..3].to_vec(),
+                    Some(self.cursor_qubit),
+                );
+            }
+            "MS" => {
+                let p = if !params.is_empty() {
+                    params
+                } else {
+                    vec![std::f64::consts::FRAC_PI_2]
+                };
+                self.dag.add_parameterized_gate(
+                    "MS",
+                    target_q as usize,
+                    self.cursor_step,
+                    p[..1].to_vec(),
+                    Some(self.cursor_qubit),
+                );
+            }
             "CCX" => {
                 let tq = target_q as usize;
                 let mut controls = vec![self.cursor_qubit];
@@ -211,8 +777,24 @@ impl App {
                 self.dag
                     .add_gate("MEASURE", self.cursor_qubit, self.cursor_step, None);
             }
+            "MEASURE_X" | "MEASURE_Y" => {
+                // Basis change in the cursor step, then measure, in freshly
+                // inserted steps so later gates move right
+                let (q, step) = (self.cursor_qubit, self.cursor_step);
+                let extra = if gate_type == "MEASURE_Y" { 2 } else { 1 };
+                self.dag.insert_step(step + 1, extra);
+                if gate_type == "MEASURE_Y" {
+                    self.dag.add_dagger_gate("S", q, step);
+                }
+                self.dag.add_gate("H", q, step + extra - 1, None);
+                self.dag.add_gate("MEASURE", q, step + extra, None);
+                self.dag.rebuild_dependencies();
+            }
             "BARRIER" => {
                 self.dag.add_barrier(self.cursor_step);
+                // Typed at the barrier label prompt
+                self.dag
+                    .set_barrier_label(self.cursor_step, &self.param_input);
             }
             "RESET" => {
                 self.dag.add_reset(self.cursor_qubit, self.cursor_step);
@@ -282,6 +864,7 @@ impl App {
             }
         }
 
+        self.remember_gate(gate_type);
         self.param_input.clear();
         self.control_qubits.clear();
         self.pending_gate.clear();
@@ -290,6 +873,17 @@ impl App {
         true
     }
 
+    /// Allocate a fresh ancilla qubit and place the pending gate with it as target.
+    pub fn place_gate_on_ancilla(&mut self, gate_type: &str) -> bool {
+        let ancilla = self.dag.allocate_ancilla();
+        if self.place_gate(gate_type, ancilla as isize) {
+            self.status_msg = format!("Allocated ancilla q[{ancilla}]");
+            return true;
+        }
+        self.dag.release_ancilla(ancilla);
+        false
+    }
+
     pub fn get_edit_options(&self) -> Vec<EditOption> {
         let gate = match &self.edit_gate {
             Some(g) => g,
@@ -320,17 +914,27 @@ impl App {
             });
         }
 
+        if has_distinct_inverse(&gate.type_name) {
+            opts.push(EditOption {
+                label: "Invert (dagger)".to_string(),
+                action: "invert",
+                ctrl_idx: -1,
+            });
+        }
+
         opts.push(EditOption {
             label: format!("Target: q[{}]", gate.target),
             action: "edit_target",
             ctrl_idx: -1,
         });
 
-        opts.push(EditOption {
-            label: "Add control qubit".to_string(),
-            action: "add_control",
-            ctrl_idx: -1,
-        });
+        if gate.controlled_type().is_some() {
+            opts.push(EditOption {
+                label: "Add control qubit".to_string(),
+                action: "add_control",
+                ctrl_idx: -1,
+            });
+        }
 
         if gate.control >= 0 {
             opts.push(EditOption {
@@ -365,6 +969,43 @@ impl App {
         }
     }
 
+    /// Complete the gate keyword being typed at the start of the cursor
+    /// line; repeated calls cycle through the matches. Returns false when
+    /// there is no partial keyword before the cursor.
+    pub fn qasm_complete(&mut self) -> bool {
+        let cursor = self.qasm_cursor.min(self.qasm_text.len());
+        let line_start = self.qasm_text[..cursor].rfind('\n').map_or(0, |p| p + 1);
+        let before = &self.qasm_text[line_start..cursor];
+        let token_len = before
+            .chars()
+            .rev()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .count();
+        let (lead, token) = before.split_at(before.len() - token_len);
+        if token.is_empty() || !lead.trim().is_empty() {
+            self.qasm_completion = None;
+            return false;
+        }
+
+        let (prefix, index) = match self.qasm_completion.take() {
+            Some((prefix, i)) if complete_gate_token(&prefix).get(i) == Some(&token) => {
+                (prefix, i + 1)
+            }
+            _ => (token.to_string(), 0),
+        };
+        let matches = complete_gate_token(&prefix);
+        if matches.is_empty() {
+            self.status_msg = format!("No gate starts with '{prefix}'");
+            return true;
+        }
+        let index = index % matches.len();
+        let start = cursor - token_len;
+        self.qasm_text.replace_range(start..cursor, matches[index]);
+        self.qasm_cursor = start + matches[index].len();
+        self.qasm_completion = Some((prefix, index));
+        true
+    }
+
     pub fn qasm_insert_char(&mut self, ch: char) {
         self.qasm_text.insert(self.qasm_cursor, ch);
         self.qasm_cursor += ch.len_utf8();
@@ -400,6 +1041,19 @@ impl App {
         (row, col)
     }
 
+    /// Byte offset of (`row`, `col`) in the editor text, moved to the last
+    /// line or the end of the line when either is past the text.
+    pub fn qasm_offset(&self, row: usize, col: usize) -> usize {
+        let lines: Vec<&str> = self.qasm_text.split('\n').collect();
+        let row = row.min(lines.len() - 1);
+        let line = lines[row];
+        let mut col = col.min(line.len());
+        while !line.is_char_boundary(col) {
+            col -= 1;
+        }
+        lines[..row].iter().map(|l| l.len() + 1).sum::<usize>() + col
+    }
+
     pub fn qasm_move_left(&mut self) {
         if self.qasm_cursor == 0 {
             return;
@@ -426,10 +1080,7 @@ impl App {
         }
         let lines: Vec<&str> = self.qasm_text.split('\n').collect();
         let target_col = col.min(lines[row - 1].len());
-        let mut off = 0usize;
-        for r in 0..(row - 1) {
-            off += lines[r].len() + 1;
-        }
+        let mut off: usize = lines[..row - 1].iter().map(|l| l.len() + 1).sum();
         off += target_col;
         self.qasm_cursor = off;
     }
@@ -441,10 +1092,7 @@ impl App {
             return;
         }
         let target_col = col.min(lines[row + 1].len());
-        let mut off = 0usize;
-        for r in 0..=row {
-            off += lines[r].len() + 1;
-        }
+        let mut off: usize = lines[..=row].iter().map(|l| l.len() + 1).sum();
         off += target_col;
         self.qasm_cursor = off;
     }
@@ -466,19 +1114,629 @@ impl App {
             .unwrap_or(self.qasm_text.len());
     }
 
+    /// Apply saved preferences to this session.
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.show_statevector = settings.show_statevector;
+        self.show_sim_frontier = settings.show_sim_frontier;
+        self.show_qubit_probs = settings.show_qubit_probs;
+        self.wire_style = if settings.double_wires {
+            WireStyle::Double
+        } else {
+            WireStyle::Single
+        };
+        self.noise_enabled = settings.noise_enabled;
+        self.layer_barriers = settings.layer_barriers;
+        self.menu_wrap = settings.menu_wrap;
+        self.show_summary = settings.show_summary;
+        self.canonical_phase = settings.canonical_phase;
+        self.reverse_qubits = settings.reverse_qubits;
+        self.wrap_circuit = settings.wrap_circuit;
+        self.amp_format = settings.amp_format;
+        self.amp_threshold = settings.amp_threshold;
+        self.recent_gates = settings
+            .recent_gates
+            .iter()
+            .filter(|g| find_menu_item(g).is_some())
+            .take(MAX_RECENT_GATES)
+            .cloned()
+            .collect();
+    }
+
+    /// Place `gate_type` on every qubit at the cursor step, as one layer.
+    fn place_gate_on_all_qubits(&mut self, gate_type: &str) -> bool {
+        self.apply_all_qubits = false;
+        let n = self.dag.num_qubits;
+        if !self.place_gate_on_qubits(gate_type, 0..n) {
+            return false;
+        }
+        let name = gate_name(gate_type).unwrap_or(gate_type);
+        self.status_msg = format!("Placed {name} on all {n} qubits");
+        true
+    }
+
+    /// Steps and qubits of the region selection, spanned by its anchor and
+    /// the cursor.
+    pub fn region(&self) -> (RangeInclusive<isize>, RangeInclusive<usize>) {
+        let (step, qubit) = self.region_anchor;
+        (
+            step.min(self.cursor_step)..=step.max(self.cursor_step),
+            qubit.min(self.cursor_qubit)..=qubit.max(self.cursor_qubit),
+        )
+    }
+
+    /// Delete every gate wholly inside the region selection; a multi-qubit
+    /// gate reaching outside it is kept.
+    pub fn delete_region(&mut self) {
+        let (steps, qubits) = self.region();
+        let removed = self.dag.remove_region(steps, qubits);
+        self.sync_from_dag();
+        self.status_msg = format!("Deleted {removed} gate(s)");
+    }
+
+    /// Place a single-qubit gate on every qubit between the cursor and
+    /// `target_qubit` inclusive, as chosen in range mode.
+    pub fn place_gate_on_range(&mut self, gate_type: &str) -> bool {
+        let lo = self.cursor_qubit.min(self.target_qubit);
+        let hi = self.cursor_qubit.max(self.target_qubit);
+        if !self.place_gate_on_qubits(gate_type, lo..hi + 1) {
+            return false;
+        }
+        let name = gate_name(gate_type).unwrap_or(gate_type);
+        self.status_msg = format!("Placed {name} on q[{lo}]–q[{hi}]");
+        true
+    }
+
+    /// Place `gate_type` on each of `qubits` at the cursor step with the same
+    /// parameters, or on none of them if any is blocked, the typed params
+    /// are invalid or any single placement fails.
+    fn place_gate_on_qubits(&mut self, gate_type: &str, qubits: std::ops::Range<usize>) -> bool {
+        let (step, qubit) = (self.cursor_step, self.cursor_qubit);
+        let params = self.param_input.clone();
+        let checked = if params.trim().is_empty() || !is_parameterized_gate(gate_type) {
+            Ok(())
+        } else {
+            match parse_params(&params) {
+                Some(values) => validate_param_count(gate_type, values.len()),
+                None => Err("Invalid parameter — use numbers or pi expressions".to_string()),
+            }
+        };
+        let refusal = match checked {
+            Err(e) => Some(e),
+            Ok(())
+                if !qubits
+                    .clone()
+                    .all(|q| self.dag.can_place_gate_at(step, &[q])) =>
+            {
+                Some("Cannot place: a multi-qubit gate already occupies this step".to_string())
+            }
+            Ok(()) => None,
+        };
+        if let Some(msg) = refusal {
+            self.status_msg = msg;
+            self.param_input.clear();
+            self.pending_gate.clear();
+            return false;
+        }
+
+        let before = self.dag.clone();
+        for q in qubits {
+            self.cursor_qubit = q;
+            self.cursor_step = step;
+            self.param_input = params.clone();
+            if !self.place_gate(gate_type, -1) {
+                // Keep the failing placement's message, drop the others
+                self.dag = before;
+                self.sync_from_dag();
+                self.cursor_step = step;
+                self.cursor_qubit = qubit;
+                return false;
+            }
+        }
+        self.cursor_qubit = qubit;
+        true
+    }
+
+    /// Move `gate_type` to the front of the recently-used list.
+    pub fn remember_gate(&mut self, gate_type: &str) {
+        self.recent_gates.retain(|g| g != gate_type);
+        self.recent_gates.insert(0, gate_type.to_string());
+        self.recent_gates.truncate(MAX_RECENT_GATES);
+    }
+
+    /// Current preferences, for saving on exit.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            show_statevector: self.show_statevector,
+            show_sim_frontier: self.show_sim_frontier,
+            show_qubit_probs: self.show_qubit_probs,
+            double_wires: self.wire_style == WireStyle::Double,
+            noise_enabled: self.noise_enabled,
+            layer_barriers: self.layer_barriers,
+            menu_wrap: self.menu_wrap,
+            show_summary: self.show_summary,
+            canonical_phase: self.canonical_phase,
+            reverse_qubits: self.reverse_qubits,
+            wrap_circuit: self.wrap_circuit,
+            amp_format: self.amp_format,
+            amp_threshold: self.amp_threshold,
+            recent_gates: self.recent_gates.clone(),
+        }
+    }
+
     pub fn save_circuit(&mut self) -> Result<(), std::io::Error> {
+        let qasm = self.dag.to_qasm_with(self.layer_barriers);
+        std::fs::write(SAVE_PATH, &qasm)?;
+        self.saved_qasm = Some(qasm);
+        Ok(())
+    }
+
+    /// Remember the existing circuit.qasm, if any, as the last save.
+    pub fn load_saved_qasm(&mut self) {
+        self.saved_qasm = std::fs::read_to_string(SAVE_PATH).ok();
+    }
+
+    /// Open the diff overlay: what saving now would change in circuit.qasm.
+    pub fn show_diff(&mut self) {
+        let Some(saved) = &self.saved_qasm else {
+            self.status_msg = format!("No {SAVE_PATH} to compare with");
+            return;
+        };
+        let diff = line_diff(saved, &self.dag.to_qasm_with(self.layer_barriers));
+        if diff_counts(&diff) == (0, 0) {
+            self.status_msg = "No changes since the last save".to_string();
+            return;
+        }
+        self.diff = Some(diff);
+        self.diff_scroll = 0;
+        self.focus = Focus::Diff;
+    }
+
+    pub fn close_diff(&mut self) {
+        self.diff = None;
+        self.focus = Focus::Circuit;
+    }
+
+    /// Start playback from step 0, or stop it.
+    pub fn toggle_playback(&mut self) {
+        if self.playback == Playback::Off {
+            self.cursor_step = 0;
+            self.playback = Playback::Playing;
+        } else {
+            self.playback = Playback::Off;
+        }
+    }
+
+    /// Pause or resume; resuming at the last step starts over.
+    pub fn pause_playback(&mut self) {
+        self.playback = match self.playback {
+            Playback::Playing => Playback::Paused,
+            Playback::Paused => {
+                if self.cursor_step >= self.dag.max_step() {
+                    self.cursor_step = 0;
+                }
+                Playback::Playing
+            }
+            Playback::Off => Playback::Off,
+        };
+    }
+
+    /// Advance one step while playing, pausing on the last step. Returns
+    /// whether the cursor moved.
+    pub fn playback_tick(&mut self) -> bool {
+        if self.playback != Playback::Playing {
+            return false;
+        }
+        let last = self.dag.max_step();
+        if self.cursor_step >= last {
+            self.playback = Playback::Paused;
+            return false;
+        }
+        self.cursor_step += 1;
+        if self.cursor_step >= last {
+            self.playback = Playback::Paused;
+        }
+        true
+    }
+
+    /// Report ∑|aᵢ|² of the state at the cursor, flagged when it is not 1.
+    pub fn check_normalization(&mut self) {
+        if let Some(msg) = self.simulation_limit_message() {
+            self.status_msg = msg;
+            self.status_is_error = true;
+            return;
+        }
+        let total = self.simulated_state().total_probability();
+        self.status_is_error = (total - 1.0).abs() > 1e-9;
+        self.status_msg = if self.status_is_error {
+            format!("Total probability {total:.12} (not normalized)")
+        } else {
+            format!("Total probability {total:.12}")
+        };
+    }
+
+    /// Seed the simulation from the basis state typed in `param_input`, given
+    /// as a bitstring (leftmost bit is the highest qubit) or as `0x..` hex.
+    /// An empty input goes back to |0…0⟩.
+    pub fn set_input_state(&mut self) -> Result<(), String> {
+        let text = self.param_input.trim();
+        let parsed = if text.is_empty() {
+            Ok(0)
+        } else if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            usize::from_str_radix(hex, 16)
+        } else {
+            usize::from_str_radix(text, 2)
+        };
+        let index = parsed.map_err(|_| format!("Invalid input state: {text}"))?;
+        let n = self.dag.num_qubits;
+        if n < usize::BITS as usize && index >> n != 0 {
+            return Err(format!("Input state {text} does not fit in {n} qubits"));
+        }
+        self.input_state = index;
+        self.simulator.set_input(index);
+        Ok(())
+    }
+
+    /// Place the gate typed in command mode (held in `param_input`) at the
+    /// cursor step, replacing whatever single-qubit gates it covers. The
+    /// `qft`, `diffusion`, `group`, `ungroup`, `noise`, `simplify`, `wrap`,
+    /// `summary`, `phase` and `csv` commands are handled here too.
+    pub fn run_command(&mut self) -> Result<(), String> {
+        let input = self.param_input.trim().to_owned();
+        let (word, arg) = input.split_once(' ').unwrap_or((&input, ""));
+        match word {
+            "qft" => return self.run_qft_command(arg),
+            "diffusion" => return self.run_diffusion_command(arg),
+            "group" => return self.run_group_command(arg),
+            "random" => return self.run_random_command(arg),
+            "noise" => return self.run_noise_command(arg),
+            "simplify" => {
+                self.simplify_circuit();
+                return Ok(());
+            }
+            "csv" => return self.export_state_csv(),
+            "name" => {
+                self.dag.set_qubit_label(self.cursor_qubit, arg);
+                self.sync_from_dag();
+                self.status_msg = match self.dag.qubit_labels.get(&self.cursor_qubit) {
+                    Some(label) => format!("Named q[{}] {label}", self.cursor_qubit),
+                    None => format!("q[{}] is unnamed", self.cursor_qubit),
+                };
+                return Ok(());
+            }
+            "layers" => {
+                self.layer_barriers = !self.layer_barriers;
+                self.status_msg = if self.layer_barriers {
+                    "Saving with a barrier between every step".to_string()
+                } else {
+                    "Saving without layer barriers".to_string()
+                };
+                return Ok(());
+            }
+            "summary" => {
+                self.show_summary = !self.show_summary;
+                self.status_msg = if self.show_summary {
+                    "Showing the state summary".to_string()
+                } else {
+                    "Hiding the state summary".to_string()
+                };
+                return Ok(());
+            }
+            "phase" => {
+                self.canonical_phase = !self.canonical_phase;
+                self.status_msg = if self.canonical_phase {
+                    "Amplitudes shown up to global phase".to_string()
+                } else {
+                    "Amplitudes shown as simulated".to_string()
+                };
+                return Ok(());
+            }
+            "wrap" => {
+                self.menu_wrap = !self.menu_wrap;
+                self.status_msg = if self.menu_wrap {
+                    "Menu navigation wraps around".to_string()
+                } else {
+                    "Menu navigation stops at the ends".to_string()
+                };
+                return Ok(());
+            }
+            "ungroup" => {
+                let label = self
+                    .dag
+                    .remove_group_at(self.cursor_step)
+                    .ok_or_else(|| format!("No group covers step {}", self.cursor_step))?;
+                self.sync_from_dag();
+                self.status_msg = format!("Removed group {label}");
+                return Ok(());
+            }
+            _ => {}
+        }
+        let mut node = parse_command(&self.param_input)?;
+        let qubits = node.qubits();
+        if let Some(&q) = qubits.iter().find(|&&q| q >= self.dag.num_qubits) {
+            return Err(format!("q[{q}] is out of range"));
+        }
+        if !self.dag.can_place_gate_at(self.cursor_step, &qubits) {
+            return Err("Cannot place: qubit already used by another gate at this step".into());
+        }
+        for &q in &qubits {
+            self.dag.remove_node_at(self.cursor_step, q);
+        }
+        node.step = self.cursor_step;
+        self.dag.add_node(node);
+        self.dag.rebuild_dependencies();
+        self.sync_from_dag();
+        Ok(())
+    }
+
+    /// `qft N`: insert an N-qubit QFT on the qubits from the cursor down,
+    /// in new steps at the cursor step.
+    fn run_qft_command(&mut self, arg: &str) -> Result<(), String> {
+        let n: usize = arg
+            .trim()
+            .parse()
+            .map_err(|_| "Usage: qft N (qubit count)".to_string())?;
+        if n == 0 || self.cursor_qubit + n > self.dag.num_qubits {
+            return Err(format!(
+                "qft {n} needs qubits q[{}]..q[{}]",
+                self.cursor_qubit,
+                self.cursor_qubit + n.max(1) - 1
+            ));
+        }
+        self.dag.add_qft(self.cursor_qubit, n, self.cursor_step);
+        self.sync_from_dag();
+        Ok(())
+    }
+
+    /// `diffusion N`: insert the Grover diffusion operator on the N qubits
+    /// from the cursor down, in new steps at the cursor step.
+    fn run_diffusion_command(&mut self, arg: &str) -> Result<(), String> {
+        let n: usize = arg
+            .trim()
+            .parse()
+            .map_err(|_| "Usage: diffusion N (qubit count)".to_string())?;
+        if n == 0 || self.cursor_qubit + n > self.dag.num_qubits {
+            return Err(format!(
+                "diffusion {n} needs qubits q[{}]..q[{}]",
+                self.cursor_qubit,
+                self.cursor_qubit + n.max(1) - 1
+            ));
+        }
+        let qubits: Vec<usize> = (self.cursor_qubit..self.cursor_qubit + n).collect();
+        self.dag.add_diffusion(&qubits, self.cursor_step);
+        self.sync_from_dag();
+        Ok(())
+    }
+
+    /// `random [DEPTH [SEED]]`: see `random_circuit`.
+    fn run_random_command(&mut self, arg: &str) -> Result<(), String> {
+        let usage = || "Usage: random [DEPTH [SEED]]".to_string();
+        let mut parts = arg.split_whitespace();
+        let depth = match parts.next() {
+            Some(d) => d.parse().map_err(|_| usage())?,
+            None => DEFAULT_RANDOM_DEPTH,
+        };
+        let seed = match parts.next() {
+            Some(s) => Some(s.parse().map_err(|_| usage())?),
+            None => None,
+        };
+        self.random_circuit(depth, seed);
+        Ok(())
+    }
+
+    /// Replace the circuit with a random one on the current qubits. Without
+    /// a seed one is taken from the clock; either way it is shown so the
+    /// circuit can be rebuilt.
+    pub fn random_circuit(&mut self, depth: usize, seed: Option<u64>) {
+        let seed = seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+        self.dag = CircuitDAG::random(self.dag.num_qubits, depth, seed);
+        self.cursor_step = 0;
+        self.sync_from_dag();
+        self.status_msg = format!("Random circuit: depth {depth}, seed {seed}");
+    }
+
+    /// Remove adjacent gate pairs that cancel, checking the unitary is
+    /// unchanged up to global phase when it is small enough to compute.
+    pub fn simplify_circuit(&mut self) {
+        let before = compute_circuit_unitary(&self.dag.to_circuit(), -1);
+        let removed = self.dag.simplify_cancellations();
+        if removed == 0 {
+            self.status_msg = "Nothing to simplify".to_string();
+            return;
+        }
+        let after = compute_circuit_unitary(&self.dag.to_circuit(), -1);
+        let (check, changed) = match (before, after) {
+            (Some(a), Some(b)) if a.equal_up_to_phase(&b) => (", same unitary", false),
+            (Some(_), Some(_)) => (", but the unitary changed", true),
+            _ => ("", false),
+        };
+        self.sync_from_dag();
+        self.status_is_error = changed;
+        self.status_msg = format!("Removed {removed} cancelling pair(s){check}");
+    }
+
+    /// Rewrite the circuit into `TRANSPILE_BASIS`, checking the unitary is
+    /// unchanged up to global phase when it is small enough to compute. Run
+    /// again before any other edit to restore the original.
+    pub fn transpile_circuit(&mut self) {
+        if let Some((original, transpiled)) = self.transpile_undo.take()
+            && transpiled == self.last_qasm
+        {
+            self.dag = original;
+            self.sync_from_dag();
+            self.status_msg = "Restored the circuit from before transpiling".to_string();
+            return;
+        }
+
+        let transpiled = self.dag.transpile_to_basis(TRANSPILE_BASIS);
+        let before = compute_circuit_unitary(&self.dag.to_circuit(), -1);
+        let after = compute_circuit_unitary(&transpiled.to_circuit(), -1);
+        let (check, changed) = match (before, after) {
+            (Some(a), Some(b)) if a.equal_up_to_phase(&b) => {
+                (", same unitary up to global phase", false)
+            }
+            (Some(_), Some(_)) => (", but the unitary changed", true),
+            _ => ("", false),
+        };
+        let (n_before, n_after) = (self.dag.nodes.len(), transpiled.nodes.len());
+        let original = std::mem::replace(&mut self.dag, transpiled);
+        self.sync_from_dag();
+        self.transpile_undo = Some((original, self.last_qasm.clone()));
+        self.status_is_error = changed;
+        self.status_msg = format!(
+            "Transpiled to {}: {n_before} → {n_after} gates{check} (T again to undo)",
+            TRANSPILE_BASIS.join(", ")
+        );
+    }
+
+    /// `noise TYPE [P]`: attach a noise channel to the gate under the cursor,
+    /// applied right after it in noisy simulation. A bare `noise` detaches it.
+    fn run_noise_command(&mut self, arg: &str) -> Result<(), String> {
+        let mut words = arg.split_whitespace();
+        let noise = match words.next() {
+            None => None,
+            Some(kind) => {
+                if !NOISE_TYPES.contains(&kind) {
+                    return Err(format!(
+                        "Unknown noise type '{kind}' (use {})",
+                        NOISE_TYPES.join(", ")
+                    ));
+                }
+                let p = match words.next() {
+                    Some(text) => parse_param_expr(text)
+                        .filter(|p| (0.0..=1.0).contains(p))
+                        .ok_or_else(|| format!("Noise strength must be 0-1, got {text}"))?,
+                    None => DEFAULT_NOISE_PARAM,
+                };
+                Some((kind.to_string(), p))
+            }
+        };
+        if !self
+            .dag
+            .set_node_noise(self.cursor_step, self.cursor_qubit, noise.clone())
+        {
+            return Err(format!(
+                "No gate at step {} on q[{}] to carry noise",
+                self.cursor_step, self.cursor_qubit
+            ));
+        }
+        self.sync_from_dag();
+        self.status_msg = match noise {
+            Some((kind, p)) => format!("Attached {kind} noise (p={}) to the gate", format_param(p)),
+            None => "Removed the gate's noise".to_string(),
+        };
+        Ok(())
+    }
+
+    /// `group START END LABEL`: box steps START..=END under LABEL.
+    fn run_group_command(&mut self, arg: &str) -> Result<(), String> {
+        let usage = || "Usage: group START END LABEL".to_string();
+        let mut parts = arg.trim().splitn(3, ' ');
+        let start: isize = parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(usage)?;
+        let end: isize = parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(usage)?;
+        let label = parts
+            .next()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .ok_or_else(usage)?;
+        self.dag.add_group(start, end, label);
+        self.sync_from_dag();
+        self.status_msg = format!(
+            "Grouped steps {}..{} as {label}",
+            start.min(end),
+            start.max(end)
+        );
+        Ok(())
+    }
+
+    /// Append the QASM file at `path_input` after the current circuit.
+    pub fn append_file(&mut self) -> Result<(), String> {
+        let qasm = std::fs::read_to_string(&self.path_input).map_err(|e| e.to_string())?;
+        self.dag.append_qasm(&qasm)?;
+        self.sync_from_dag();
+        Ok(())
+    }
+
+    pub fn export_svg(&self) -> Result<(), std::io::Error> {
+        std::fs::write("circuit.svg", self.circuit().to_svg())
+    }
+
+    /// Write the state the panel shows (up to the cursor column) to
+    /// `STATE_CSV_PATH`, one row per nonzero amplitude.
+    fn export_state_csv(&mut self) -> Result<(), String> {
+        if let Some(msg) = self.simulation_limit_message() {
+            return Err(msg);
+        }
+        let state = self.simulated_state();
+        std::fs::write(STATE_CSV_PATH, state.to_csv())
+            .map_err(|e| format!("Could not write {STATE_CSV_PATH}: {e}"))?;
+        self.status_msg = format!("Wrote {STATE_CSV_PATH}");
+        Ok(())
+    }
+
+    /// Write the circuit to the autosave file if it changed since the last
+    /// autosave. Paused while a recovery offer is open so the old work survives.
+    pub fn autosave(&mut self) -> Result<(), std::io::Error> {
+        if self.recover_pending {
+            return Ok(());
+        }
         let qasm = self.dag.to_qasm();
-        std::fs::write("circuit.qasm", &qasm)?;
+        if qasm != self.last_autosave_qasm {
+            std::fs::write(AUTOSAVE_PATH, &qasm)?;
+            self.last_autosave_qasm = qasm;
+        }
+        Ok(())
+    }
+
+    /// On startup, offer to recover an autosave left by a session that did not quit cleanly.
+    pub fn check_autosave(&mut self) {
+        if should_recover(modified_time(AUTOSAVE_PATH), modified_time(SAVE_PATH)) {
+            self.recover_pending = true;
+            self.status_msg = RECOVER_PROMPT.to_string();
+        }
+    }
+
+    /// Keep the recovery prompt on screen until it is answered.
+    pub fn refresh_recover_prompt(&mut self) {
+        if self.recover_pending && self.status_msg.is_empty() {
+            self.status_msg = RECOVER_PROMPT.to_string();
+        }
+    }
+
+    pub fn recover_autosave(&mut self) -> Result<(), std::io::Error> {
+        let qasm = std::fs::read_to_string(AUTOSAVE_PATH)?;
+        self.recover_pending = false;
+        self.qasm_text = qasm;
+        self.parse_qasm_input();
+        self.sync_from_dag();
+        self.cursor_step = 0;
+        self.cursor_qubit = 0;
         Ok(())
     }
 
+    pub fn remove_autosave(&self) {
+        let _ = std::fs::remove_file(AUTOSAVE_PATH);
+    }
+
+    /// The nearest qubit not in `excluded`, moving `rows` display rows
+    /// (down when positive) from `from`.
     pub fn next_available_target(
         &self,
         from: usize,
-        direction: isize,
+        rows: isize,
         excluded: &[usize],
     ) -> Option<usize> {
         let nq = self.dag.num_qubits;
+        let direction = if self.reverse_qubits { -rows } else { rows };
         if direction > 0 {
             for q in (from + 1)..nq {
                 if !excluded.contains(&q) {
@@ -494,6 +1752,15 @@ impl App {
         }
         None
     }
+
+    /// The qubit `rows` display rows below `qubit` (above when negative),
+    /// stopping at the first and last rows.
+    pub fn qubit_after_rows(&self, qubit: usize, rows: isize) -> usize {
+        let n = self.dag.num_qubits;
+        let row = display_row_qubit(qubit, n, self.reverse_qubits) as isize;
+        let row = (row + rows).clamp(0, n.saturating_sub(1) as isize) as usize;
+        display_row_qubit(row, n, self.reverse_qubits)
+    }
 }
 
 impl Default for App {
@@ -1,4 +1,6 @@
-#[derive(Clone, Debug, Default)]
+use crate::params::format_param;
+
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Gate {
     pub step: isize,
     pub type_name: String,
@@ -12,16 +14,94 @@ pub struct Gate {
     pub is_noise: bool,
     pub noise_type: String,
     pub classical_control: isize,
+    /// Scheduling duration; None uses the per-type default
+    pub duration: Option<f64>,
+    /// Noise channel (type, strength) applied right after the gate
+    pub noise: Option<(String, f64)>,
+    /// Caption drawn above a barrier
+    pub label: Option<String>,
 }
 
 impl Gate {
     pub fn references(&self, qubit: usize) -> bool {
+        // A barrier with no qubit list spans every wire
+        if self.type_name == "BARRIER" {
+            return self.controls.is_empty() || self.controls.contains(&qubit);
+        }
         let q = qubit as isize;
         self.target == qubit
             || self.control == q
             || self.measure_source == q
             || self.controls.contains(&qubit)
     }
+
+    /// Duration used by `Circuit::schedule`: the gate's own, otherwise
+    /// `default_duration` for its type.
+    pub fn duration(&self) -> f64 {
+        self.duration.unwrap_or_else(|| default_duration(self))
+    }
+
+    /// Why the gate's qubits clash: a control on the target qubit, or the
+    /// same control listed twice. None when every qubit is distinct.
+    pub fn qubit_conflict(&self) -> Option<String> {
+        if self.type_name == "BARRIER" {
+            return None;
+        }
+        let mut qubits: Vec<usize> = [self.measure_source, self.control]
+            .into_iter()
+            .filter(|&q| q >= 0)
+            .map(|q| q as usize)
+            .chain(self.controls.iter().copied())
+            .collect();
+        qubits.push(self.target);
+        let q = repeated_qubit(&qubits)?;
+        Some(if q == self.target {
+            format!(
+                "{}: q[{q}] cannot be both control and target",
+                self.type_name
+            )
+        } else {
+            format!("{}: control q[{q}] is listed twice", self.type_name)
+        })
+    }
+
+    /// Two-qubit interactions with no control/target distinction, drawn as a
+    /// box on both qubits.
+    pub fn is_symmetric_interaction(&self) -> bool {
+        matches!(self.type_name.as_str(), "MS" | "RXX" | "RZZ")
+    }
+
+    /// Gate type after adding one more control, or None when the result
+    /// could not be saved or simulated: a single control goes on X, Z, H and
+    /// the rotations, further controls only on X.
+    pub fn controlled_type(&self) -> Option<&'static str> {
+        if self.is_dagger || self.is_reset || self.is_noise || self.measure_source >= 0 {
+            return None;
+        }
+        let controlled = self.control >= 0 || !self.controls.is_empty();
+        Some(match (self.type_name.as_str(), controlled) {
+            ("X", false) => "CX",
+            ("Z", false) => "CZ",
+            ("H", false) => "CH",
+            ("RX", false) => "CRX",
+            ("RY", false) => "CRY",
+            ("RZ", false) => "CRZ",
+            ("P", false) => "CP",
+            ("U1", false) => "CU1",
+            ("U3", false) => "CU3",
+            ("CX" | "CCX", true) => "CCX",
+            _ => return None,
+        })
+    }
+}
+
+/// The first qubit that appears twice in `qubits`.
+pub fn repeated_qubit(qubits: &[usize]) -> Option<usize> {
+    qubits
+        .iter()
+        .enumerate()
+        .find(|&(i, q)| qubits[..i].contains(q))
+        .map(|(_, &q)| q)
 }
 
 #[derive(Clone, Debug, Default)]
@@ -29,11 +109,21 @@ pub struct Circuit {
     pub num_qubits: usize,
     pub gates: Vec<Gate>,
     pub max_steps: usize,
+    /// Classical bits declared for the circuit, possibly more than it uses
+    pub declared_cbits: usize,
 }
 
 impl Circuit {
+    /// Gates in the circuit, not counting barriers.
+    pub fn gate_count(&self) -> usize {
+        self.gates
+            .iter()
+            .filter(|g| g.type_name != "BARRIER")
+            .count()
+    }
+
     pub fn num_cbits(&self) -> usize {
-        let mut max = -1isize;
+        let mut max = self.declared_cbits as isize - 1;
         for g in &self.gates {
             if g.type_name == "MEASURE" {
                 max = max.max(g.target as isize);
@@ -42,11 +132,63 @@ impl Circuit {
                 max = max.max(g.measure_source);
             }
         }
-        if max < 0 {
-            0
-        } else {
-            max as usize + 1
+        if max < 0 { 0 } else { max as usize + 1 }
+    }
+
+    /// Distinct unordered qubit pairs (lower index first, sorted) coupled by
+    /// a multi-qubit gate. A gate with several controls couples every pair of
+    /// its qubits, so CCX contributes three pairs. Measurement feed-forward
+    /// is classical and adds no pair.
+    pub fn coupling_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs: Vec<(usize, usize)> = Vec::new();
+        for g in &self.gates {
+            let mut qubits: Vec<usize> = g.controls.clone();
+            if g.control >= 0 {
+                qubits.push(g.control as usize);
+            }
+            qubits.push(g.target);
+            for (i, &a) in qubits.iter().enumerate() {
+                for &b in &qubits[i + 1..] {
+                    if a != b {
+                        pairs.push((a.min(b), a.max(b)));
+                    }
+                }
+            }
         }
+        pairs.sort();
+        pairs.dedup();
+        pairs
+    }
+
+    /// Time at which each qubit finishes its last gate when every gate starts
+    /// as soon as its qubits (and, for a classically controlled gate, its
+    /// measured bit) are free. Barriers hold back the qubits they span until
+    /// all of them are free. The maximum is the circuit's critical path.
+    pub fn schedule(&self) -> Vec<f64> {
+        let mut gates: Vec<&Gate> = self.gates.iter().collect();
+        gates.sort_by_key(|g| (g.step, g.target));
+        let mut busy = vec![0.0; self.num_qubits];
+        let mut cbit_ready = vec![0.0; self.num_cbits()];
+        for g in gates {
+            let qubits: Vec<usize> = (0..self.num_qubits).filter(|&q| g.references(q)).collect();
+            let mut start = qubits.iter().map(|&q| busy[q]).fold(0.0, f64::max);
+            if let Some(&ready) = usize::try_from(g.classical_control)
+                .ok()
+                .and_then(|c| cbit_ready.get(c))
+            {
+                start = f64::max(start, ready);
+            }
+            let end = start + g.duration();
+            for q in qubits {
+                busy[q] = end;
+            }
+            if g.type_name == "MEASURE"
+                && let Some(ready) = cbit_ready.get_mut(g.target)
+            {
+                *ready = end;
+            }
+        }
+        busy
     }
 
     pub fn get_measure_at_step(&self, step: isize) -> isize {
@@ -70,28 +212,44 @@ impl Circuit {
             .find(|g| g.step == step && g.references(qubit))
     }
 
+    /// The nearest step after (`forward`) or before `from` with a gate on
+    /// `qubit`, without wrapping.
+    pub fn next_gate_step(&self, qubit: usize, from: isize, forward: bool) -> Option<isize> {
+        if forward {
+            ((from + 1)..=self.max_steps as isize).find(|&s| self.get_gate_at(s, qubit).is_some())
+        } else {
+            (0..from)
+                .rev()
+                .find(|&s| self.get_gate_at(s, qubit).is_some())
+        }
+    }
+
     pub fn remove_gate_at(&mut self, step: isize, qubit: usize) {
-        self.gates.retain(|g| {
-            if g.step == step && g.type_name == "BARRIER" {
-                return false;
-            }
-            !(g.step == step && g.references(qubit))
-        });
+        self.gates
+            .retain(|g| !(g.step == step && g.references(qubit)));
     }
 
+    /// How the cell at (`step`, `qubit`) is drawn. Control dots come from the
+    /// gate's actual control set, so scattered controls (e.g. {0, 3} with
+    /// target 1) get ● on their own rows and every other row inside the
+    /// span is a pass-through.
     pub fn get_cell_info(&self, step: isize, qubit: usize) -> CellInfo {
         let mut info = CellInfo::default();
 
         if let Some(gate) = self.get_gate_at(step, qubit) {
             info.gate = Some(gate.clone());
-            info.is_control = gate.control == qubit as isize || gate.controls.contains(&qubit);
-            info.is_target =
-                gate.target == qubit && (gate.control >= 0 || !gate.controls.is_empty());
+            if gate.is_symmetric_interaction() {
+                info.is_target = true;
+            } else {
+                info.is_control = gate.control == qubit as isize || gate.controls.contains(&qubit);
+                info.is_target =
+                    gate.target == qubit && (gate.control >= 0 || !gate.controls.is_empty());
+            }
         }
 
         // Check for barrier
         for g in &self.gates {
-            if g.step == step && g.type_name == "BARRIER" {
+            if g.step == step && g.type_name == "BARRIER" && g.references(qubit) {
                 info.is_barrier = true;
                 if info.gate.is_none() {
                     info.gate = Some(g.clone());
@@ -102,7 +260,7 @@ impl Circuit {
 
         // Vertical connections
         for g in &self.gates {
-            if g.step != step {
+            if g.step != step || g.type_name == "BARRIER" {
                 continue;
             }
             let (min_q, max_q) = if !g.controls.is_empty() {
@@ -137,26 +295,105 @@ impl Circuit {
         }
 
         // Measurement connections down to classical wire
-        for g in &self.gates {
-            if g.step != step {
-                continue;
-            }
-            let mq = if g.type_name == "MEASURE" {
-                Some(g.target)
-            } else if g.measure_source >= 0 {
-                Some(g.measure_source as usize)
-            } else {
-                None
-            };
-            if let Some(measured) = mq {
-                if qubit > measured {
-                    info.measure_below = true;
-                }
-            }
-        }
+        info.measure_below = self
+            .gates
+            .iter()
+            .filter(|g| g.step == step)
+            .filter_map(measured_qubit)
+            .any(|measured| qubit > measured);
 
         info
     }
+
+    /// `get_cell_info` for a diagram drawn with the highest qubit on top:
+    /// connectors flip, and a measurement's line to the classical wire
+    /// passes the lower-numbered qubits instead.
+    pub fn get_cell_info_reversed(&self, step: isize, qubit: usize) -> CellInfo {
+        let mut info = self.get_cell_info(step, qubit);
+        std::mem::swap(&mut info.vert_above, &mut info.vert_below);
+        info.measure_below = self
+            .gates
+            .iter()
+            .filter(|g| g.step == step)
+            .filter_map(measured_qubit)
+            .any(|measured| qubit < measured);
+        info
+    }
+}
+
+/// The qubit a measurement (or measure-controlled X) reads.
+fn measured_qubit(gate: &Gate) -> Option<usize> {
+    if gate.type_name == "MEASURE" {
+        Some(gate.target)
+    } else if gate.measure_source >= 0 {
+        Some(gate.measure_source as usize)
+    } else {
+        None
+    }
+}
+
+/// Gates whose inverse is a different operation: dagger forms and rotations.
+pub fn has_distinct_inverse(gate_type: &str) -> bool {
+    matches!(
+        gate_type,
+        "S" | "T"
+            | "SX"
+            | "SY"
+            | "RX"
+            | "RY"
+            | "RZ"
+            | "P"
+            | "U1"
+            | "U3"
+            | "U"
+            | "CRX"
+            | "CRY"
+            | "CRZ"
+            | "CP"
+            | "CU1"
+            | "CU3"
+            | "MS"
+            | "RXX"
+            | "RZZ"
+    )
+}
+
+/// Per-type scheduling duration in arbitrary units: measurements 10,
+/// multi-qubit gates 3, single-qubit gates 1, and a `u0(n)` delay n.
+/// Barriers and noise channels take no time.
+pub fn default_duration(gate: &Gate) -> f64 {
+    if gate.type_name == "BARRIER" || gate.is_noise {
+        0.0
+    } else if gate.type_name == "U0" {
+        gate.params.first().copied().unwrap_or(0.0).max(0.0)
+    } else if gate.type_name == "MEASURE" || gate.type_name == "MCX" {
+        10.0
+    } else if gate.control >= 0 || !gate.controls.is_empty() {
+        3.0
+    } else {
+        1.0
+    }
+}
+
+/// Replace a gate by its inverse: dagger forms flip `is_dagger`, rotations
+/// negate their angles and self-inverse gates are left as they are. Returns
+/// false when the gate has no inverse that can be written this way.
+pub fn invert_gate(gate_type: &str, params: &mut [f64], is_dagger: &mut bool) -> bool {
+    match gate_type {
+        "S" | "T" | "SX" | "SY" => *is_dagger = !*is_dagger,
+        "U3" | "U" | "CU3" if params.len() == 3 => {
+            // U3(θ, φ, λ)† = U3(-θ, -λ, -φ)
+            let (phi, lambda) = (params[1], params[2]);
+            params[0] = -params[0];
+            params[1] = -lambda;
+            params[2] = -phi;
+        }
+        "U3" | "U" | "CU3" => return false,
+        t if has_distinct_inverse(t) => params.iter_mut().for_each(|p| *p = -*p),
+        "I" | "H" | "X" | "Y" | "Z" | "CX" | "CZ" | "CH" | "CCX" | "SWAP" | "BARRIER" => {}
+        _ => return false,
+    }
+    true
 }
 
 #[derive(Clone, Debug, Default)]
@@ -170,3 +407,206 @@ pub struct CellInfo {
     pub measure_below: bool,
     pub is_barrier: bool,
 }
+
+// ── SVG export ────────────────────────────────────────────────────────────────
+
+const SVG_CELL_W: f64 = 72.0;
+const SVG_ROW_H: f64 = 48.0;
+const SVG_LABEL_W: f64 = 48.0;
+const SVG_BOX: f64 = 34.0;
+
+/// Short gate name as drawn inside a box, dropping the "C" of controlled
+/// variants since the control dot already says so.
+pub fn gate_display_name(gate_type: &str) -> String {
+    match gate_type {
+        "MEASURE" => "M".to_string(),
+        "ID" => "I".to_string(),
+        "U0" => "DELAY".to_string(),
+        "CX" | "CCX" | "MCX" => "X".to_string(),
+        "CZ" => "Z".to_string(),
+        "CH" => "H".to_string(),
+        "CU1" => "U1".to_string(),
+        "CU3" => "U3".to_string(),
+        "CP" => "P".to_string(),
+        "CRX" => "RX".to_string(),
+        "CRY" => "RY".to_string(),
+        "CRZ" => "RZ".to_string(),
+        other => {
+            if other.starts_with('C') && other.len() > 1 && other != "CONTROL" {
+                other[1..].to_string()
+            } else {
+                other.to_string()
+            }
+        }
+    }
+}
+
+fn svg_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Label for a gate box: display name, dagger mark and parameters.
+fn svg_gate_label(gate: &Gate) -> String {
+    let mut label = match gate.type_name.as_str() {
+        "RESET" => "|0⟩".to_string(),
+        "NOISE" => "N".to_string(),
+        other => gate_display_name(other),
+    };
+    if gate.is_dagger {
+        label.push('†');
+    }
+    if !gate.is_noise && !gate.params.is_empty() {
+        let params: Vec<String> = gate
+            .params
+            .iter()
+            .map(|&p| format_param(p).replace('*', "").replace("pi", "π"))
+            .collect();
+        label.push_str(&format!("({})", params.join(",")));
+    }
+    label
+}
+
+impl Circuit {
+    /// Draw the circuit as a standalone SVG document: one wire per qubit,
+    /// a `<rect>` box per boxed gate, filled dots for controls, ⊕ for X
+    /// targets, × for swaps and a vertical line joining multi-qubit gates.
+    pub fn to_svg(&self) -> String {
+        let steps = if self.gates.is_empty() {
+            1
+        } else {
+            self.max_steps + 1
+        };
+        let width = SVG_LABEL_W + steps as f64 * SVG_CELL_W + 16.0;
+        let height = self.num_qubits.max(1) as f64 * SVG_ROW_H;
+        let x_of = |step: isize| SVG_LABEL_W + (step as f64 + 0.5) * SVG_CELL_W;
+        let y_of = |q: usize| (q as f64 + 0.5) * SVG_ROW_H;
+
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+             viewBox=\"0 0 {width} {height}\" font-family=\"monospace\" font-size=\"13\">\n"
+        );
+
+        // Wires
+        for q in 0..self.num_qubits {
+            let y = y_of(q);
+            out.push_str(&format!(
+                "  <text x=\"4\" y=\"{}\">q[{q}]</text>\n  <line x1=\"{SVG_LABEL_W}\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\" stroke=\"black\"/>\n",
+                y + 4.0,
+                width - 8.0
+            ));
+        }
+
+        let mut gates: Vec<&Gate> = self.gates.iter().collect();
+        gates.sort_by_key(|g| (g.step, g.target));
+        for gate in gates {
+            let x = x_of(gate.step);
+
+            if gate.type_name == "BARRIER" {
+                let (y1, y2) = match (gate.controls.first(), gate.controls.last()) {
+                    (Some(&lo), Some(&hi)) => {
+                        (y_of(lo) - SVG_ROW_H / 2.0, y_of(hi) + SVG_ROW_H / 2.0)
+                    }
+                    _ => (0.0, height),
+                };
+                out.push_str(&format!(
+                    "  <line x1=\"{x}\" y1=\"{y1}\" x2=\"{x}\" y2=\"{y2}\" stroke=\"gray\" stroke-dasharray=\"4 3\"/>\n"
+                ));
+                continue;
+            }
+
+            let mut others: Vec<usize> = gate.controls.clone();
+            if gate.control >= 0 {
+                others.push(gate.control as usize);
+            }
+            if gate.measure_source >= 0 {
+                others.push(gate.measure_source as usize);
+            }
+
+            // Connector spanning every qubit the gate touches
+            if let (Some(&lo), Some(&hi)) = (others.iter().min(), others.iter().max()) {
+                let (lo, hi) = (lo.min(gate.target), hi.max(gate.target));
+                let dash = if gate.measure_source >= 0 {
+                    " stroke-dasharray=\"3 2\""
+                } else {
+                    ""
+                };
+                out.push_str(&format!(
+                    "  <line x1=\"{x}\" y1=\"{}\" x2=\"{x}\" y2=\"{}\" stroke=\"black\"{dash}/>\n",
+                    y_of(lo),
+                    y_of(hi)
+                ));
+            }
+
+            let boxed = |q: usize, label: &str| {
+                let y = y_of(q);
+                // Widen the box for long labels such as "RX(π/2)"
+                let w = SVG_BOX.max(label.chars().count() as f64 * 8.0 + 8.0);
+                format!(
+                    "  <rect x=\"{}\" y=\"{}\" width=\"{w}\" height=\"{SVG_BOX}\" fill=\"white\" stroke=\"black\"/>\n  <text x=\"{x}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+                    x - w / 2.0,
+                    y - SVG_BOX / 2.0,
+                    y + 4.0,
+                    svg_escape(label)
+                )
+            };
+            let dot = |q: usize| {
+                format!(
+                    "  <circle cx=\"{x}\" cy=\"{}\" r=\"5\" fill=\"black\"/>\n",
+                    y_of(q)
+                )
+            };
+            let oplus = |q: usize| {
+                let y = y_of(q);
+                format!(
+                    "  <circle cx=\"{x}\" cy=\"{y}\" r=\"10\" fill=\"white\" stroke=\"black\"/>\n  <line x1=\"{}\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\" stroke=\"black\"/>\n  <line x1=\"{x}\" y1=\"{}\" x2=\"{x}\" y2=\"{}\" stroke=\"black\"/>\n",
+                    x - 10.0,
+                    x + 10.0,
+                    y - 10.0,
+                    y + 10.0
+                )
+            };
+            let cross = |q: usize| {
+                let y = y_of(q);
+                format!(
+                    "  <path d=\"M{} {} L{} {} M{} {} L{} {}\" stroke=\"black\"/>\n",
+                    x - 6.0,
+                    y - 6.0,
+                    x + 6.0,
+                    y + 6.0,
+                    x - 6.0,
+                    y + 6.0,
+                    x + 6.0,
+                    y - 6.0
+                )
+            };
+
+            let label = svg_gate_label(gate);
+            if gate.is_symmetric_interaction() && gate.control >= 0 {
+                out.push_str(&boxed(gate.control as usize, &label));
+                out.push_str(&boxed(gate.target, &label));
+            } else if gate.type_name == "SWAP" && gate.control >= 0 {
+                out.push_str(&cross(gate.control as usize));
+                out.push_str(&cross(gate.target));
+            } else if gate.measure_source >= 0 {
+                out.push_str(&boxed(gate.measure_source as usize, "M"));
+                out.push_str(&oplus(gate.target));
+            } else if !others.is_empty() {
+                for &c in &others {
+                    out.push_str(&dot(c));
+                }
+                match gate.type_name.as_str() {
+                    "CX" | "CCX" => out.push_str(&oplus(gate.target)),
+                    "CZ" => out.push_str(&dot(gate.target)),
+                    _ => out.push_str(&boxed(gate.target, &label)),
+                }
+            } else {
+                out.push_str(&boxed(gate.target, &label));
+            }
+        }
+
+        out.push_str("</svg>\n");
+        out
+    }
+}
@@ -0,0 +1,296 @@
+use std::path::PathBuf;
+
+use crate::app::{AmpFormat, DEFAULT_AMP_THRESHOLD, MAX_AMP_THRESHOLD, MIN_AMP_THRESHOLD};
+use std::time::Duration;
+
+pub const DEFAULT_FPS: u32 = 30;
+pub const MIN_FPS: u32 = 5;
+pub const MAX_FPS: u32 = 120;
+/// Largest register simulated live; bigger circuits can still be built and exported
+pub const DEFAULT_MAX_SIM_QUBITS: usize = 20;
+
+/// Runtime settings taken from the command line or environment.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub fps: u32,
+    pub max_sim_qubits: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            fps: DEFAULT_FPS,
+            max_sim_qubits: DEFAULT_MAX_SIM_QUBITS,
+        }
+    }
+}
+
+impl Config {
+    /// Read `--fps <n>` / `--fps=<n>` and `--max-sim-qubits <n>` /
+    /// `--max-sim-qubits=<n>` from the arguments, falling back to the
+    /// `QDECK_FPS` and `QDECK_MAX_SIM_QUBITS` environment variables.
+    pub fn from_env() -> Self {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let env_fps = std::env::var("QDECK_FPS").ok();
+        let env_max_sim = std::env::var("QDECK_MAX_SIM_QUBITS").ok();
+        Self::from_sources(&args, env_fps.as_deref(), env_max_sim.as_deref())
+    }
+
+    pub fn from_sources(args: &[String], env_fps: Option<&str>, env_max_sim: Option<&str>) -> Self {
+        let mut fps = env_fps.and_then(parse_fps);
+        let mut max_sim = env_max_sim.and_then(parse_max_sim_qubits);
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--fps" {
+                fps = iter.next().and_then(|v| parse_fps(v)).or(fps);
+            } else if let Some(v) = arg.strip_prefix("--fps=") {
+                fps = parse_fps(v).or(fps);
+            } else if arg == "--max-sim-qubits" {
+                max_sim = iter
+                    .next()
+                    .and_then(|v| parse_max_sim_qubits(v))
+                    .or(max_sim);
+            } else if let Some(v) = arg.strip_prefix("--max-sim-qubits=") {
+                max_sim = parse_max_sim_qubits(v).or(max_sim);
+            }
+        }
+        Config {
+            fps: fps.unwrap_or(DEFAULT_FPS),
+            max_sim_qubits: max_sim.unwrap_or(DEFAULT_MAX_SIM_QUBITS),
+        }
+    }
+
+    /// Upper bound on how often the screen is redrawn.
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_millis(1000 / self.fps.max(1) as u64)
+    }
+}
+
+/// Parse a frame rate, clamping it to MIN_FPS..=MAX_FPS.
+pub fn parse_fps(s: &str) -> Option<u32> {
+    let fps = s.trim().parse::<u32>().ok()?;
+    Some(fps.clamp(MIN_FPS, MAX_FPS))
+}
+
+/// Parse the live-simulation qubit limit; at least one qubit.
+pub fn parse_max_sim_qubits(s: &str) -> Option<usize> {
+    s.trim().parse::<usize>().ok().filter(|&n| n > 0)
+}
+
+/// Display and simulation preferences remembered between sessions.
+/// Circuit contents are never stored here.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub show_statevector: bool,
+    pub show_sim_frontier: bool,
+    pub show_qubit_probs: bool,
+    pub double_wires: bool,
+    pub noise_enabled: bool,
+    /// Gate types in the Add Gate menu's recent row, most recent first
+    pub recent_gates: Vec<String>,
+    /// Write a barrier between steps when saving
+    pub layer_barriers: bool,
+    /// Menu navigation wraps past the first and last entries
+    pub menu_wrap: bool,
+    /// State summary at the start of the controls row
+    pub show_summary: bool,
+    /// Statevector view drawn with the global phase removed
+    pub canonical_phase: bool,
+    /// Highest qubit drawn on top
+    pub reverse_qubits: bool,
+    /// Circuit drawn in rows that fit the terminal width
+    pub wrap_circuit: bool,
+    pub amp_format: AmpFormat,
+    /// Probability at or below which the state panel hides basis states
+    pub amp_threshold: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            show_statevector: false,
+            show_sim_frontier: true,
+            show_qubit_probs: false,
+            double_wires: false,
+            noise_enabled: false,
+            recent_gates: Vec::new(),
+            layer_barriers: false,
+            menu_wrap: true,
+            show_summary: false,
+            canonical_phase: false,
+            reverse_qubits: false,
+            wrap_circuit: false,
+            amp_format: AmpFormat::default(),
+            amp_threshold: DEFAULT_AMP_THRESHOLD,
+        }
+    }
+}
+
+impl Settings {
+    /// `$XDG_CONFIG_HOME/q-deck/settings`, else `~/.config/q-deck/settings`.
+    pub fn path() -> Option<PathBuf> {
+        let base = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("q-deck").join("settings"))
+    }
+
+    /// Load saved settings; a missing or unreadable file gives the defaults.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.serialize())
+    }
+
+    /// One `key = value` line per setting.
+    pub fn serialize(&self) -> String {
+        format!(
+            "show_statevector = {}\nshow_sim_frontier = {}\nshow_qubit_probs = {}\ndouble_wires = {}\nnoise_enabled = {}\nrecent_gates = {}\nlayer_barriers = {}\nmenu_wrap = {}\nshow_summary = {}\ncanonical_phase = {}\nreverse_qubits = {}\nwrap_circuit = {}\namp_format = {}\namp_threshold = {:e}\n",
+            self.show_statevector,
+            self.show_sim_frontier,
+            self.show_qubit_probs,
+            self.double_wires,
+            self.noise_enabled,
+            self.recent_gates.join(","),
+            self.layer_barriers,
+            self.menu_wrap,
+            self.show_summary,
+            self.canonical_phase,
+            self.reverse_qubits,
+            self.wrap_circuit,
+            self.amp_format.label(),
+            self.amp_threshold
+        )
+    }
+
+    /// Parse `key = value` lines. Unknown keys and malformed lines are
+    /// skipped, leaving those settings at their defaults.
+    pub fn parse(text: &str) -> Self {
+        let mut settings = Settings::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if key.trim() == "recent_gates" {
+                settings.recent_gates = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|g| !g.is_empty())
+                    .map(String::from)
+                    .collect();
+                continue;
+            }
+            match key.trim() {
+                "amp_format" => {
+                    if let Some(format) = AmpFormat::from_label(value.trim()) {
+                        settings.amp_format = format;
+                    }
+                    continue;
+                }
+                "amp_threshold" => {
+                    if let Ok(t) = value.trim().parse::<f64>()
+                        && t.is_finite()
+                    {
+                        settings.amp_threshold = t.clamp(MIN_AMP_THRESHOLD, MAX_AMP_THRESHOLD);
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let Ok(value) = value.trim().parse::<bool>() else {
+                continue;
+            };
+            match key.trim() {
+                "show_statevector" => settings.show_statevector = value,
+                "show_sim_frontier" => settings.show_sim_frontier = value,
+                "show_qubit_probs" => settings.show_qubit_probs = value,
+                "double_wires" => settings.double_wires = value,
+                "noise_enabled" => settings.noise_enabled = value,
+                "layer_barriers" => settings.layer_barriers = value,
+                "menu_wrap" => settings.menu_wrap = value,
+                "show_summary" => settings.show_summary = value,
+                "canonical_phase" => settings.canonical_phase = value,
+                "reverse_qubits" => settings.reverse_qubits = value,
+                "wrap_circuit" => settings.wrap_circuit = value,
+                _ => {}
+            }
+        }
+        settings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip() {
+        let settings = Settings {
+            show_statevector: true,
+            show_sim_frontier: false,
+            show_qubit_probs: true,
+            double_wires: true,
+            noise_enabled: true,
+            recent_gates: vec!["H".to_string(), "CX".to_string()],
+            layer_barriers: true,
+            menu_wrap: false,
+            show_summary: true,
+            canonical_phase: true,
+            reverse_qubits: true,
+            wrap_circuit: true,
+            amp_format: AmpFormat::Polar,
+            amp_threshold: 1e-3,
+        };
+        assert_eq!(Settings::parse(&settings.serialize()), settings);
+        assert_eq!(
+            Settings::parse(&Settings::default().serialize()),
+            Settings::default()
+        );
+    }
+
+    #[test]
+    fn settings_skip_bad_values() {
+        let settings =
+            Settings::parse("menu_wrap = maybe\namp_format = sideways\namp_threshold = 5\n");
+        assert!(settings.menu_wrap);
+        assert_eq!(settings.amp_format, AmpFormat::Rectangular);
+        assert_eq!(settings.amp_threshold, MAX_AMP_THRESHOLD);
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn fps_is_parsed_and_clamped() {
+        assert_eq!(parse_fps(" 60 "), Some(60));
+        assert_eq!(parse_fps("1"), Some(MIN_FPS));
+        assert_eq!(parse_fps("1000"), Some(MAX_FPS));
+        assert_eq!(parse_fps("fast"), None);
+
+        assert_eq!(Config::from_sources(&[], None, None).fps, DEFAULT_FPS);
+        assert_eq!(Config::from_sources(&[], Some("50"), None).fps, 50);
+        // Arguments win over the environment; a bad value keeps the earlier one
+        let config = Config::from_sources(&args(&["--fps", "20"]), Some("50"), None);
+        assert_eq!(config.fps, 20);
+        let config = Config::from_sources(&args(&["--fps=x"]), Some("50"), None);
+        assert_eq!(config.fps, 50);
+        let config = Config::from_sources(&args(&["--fps=500"]), None, None);
+        assert_eq!(
+            config.frame_interval(),
+            Duration::from_millis(1000 / MAX_FPS as u64)
+        );
+    }
+}
@@ -1,7 +1,10 @@
-use crate::circuit::{Circuit, Gate};
+use crate::circuit::{Circuit, Gate, invert_gate};
+use crate::menu::{GATE_MENU, find_menu_item, gate_name};
 use crate::params::{format_param, parse_param_expr};
+use crate::quantum::DEFAULT_NOISE_PARAM;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
 use std::sync::OnceLock;
 
 // ── Lazy-compiled regex patterns ──────────────────────────────────────────────
@@ -34,7 +37,7 @@ fn two_qubit_param_re() -> &'static Regex {
     R.get_or_init(|| {
         let p = PARAM_PAT;
         Regex::new(&format!(
-            r"^(\w+)\s*\(\s*({p})\s*\)\s+q\[(\d+)\],\s*q\[(\d+)\];?$"
+            r"^(\w+)\s*\(\s*({p}(?:\s*,\s*{p})*)\s*\)\s+q\[(\d+)\],\s*q\[(\d+)\];?$"
         ))
         .unwrap()
     })
@@ -45,6 +48,29 @@ fn three_qubit_re() -> &'static Regex {
     R.get_or_init(|| Regex::new(r"^(\w+)\s+q\[(\d+)\],\s*q\[(\d+)\],\s*q\[(\d+)\];?$").unwrap())
 }
 
+/// OpenQASM 3 control modifiers: `ctrl @ ctrl @ rx(pi/2) q[0], q[1], q[2];`
+fn ctrl_modifier_re() -> &'static Regex {
+    static R: OnceLock<Regex> = OnceLock::new();
+    R.get_or_init(|| {
+        let p = PARAM_PAT;
+        Regex::new(&format!(
+            r"^((?:(?:neg)?ctrl\s*@\s*)+)(\w+)(?:\s*\(\s*({p}(?:\s*,\s*{p})*)\s*\))?\s+(q\[\d+\](?:\s*,\s*q\[\d+\])*)\s*;?$"
+        ))
+        .unwrap()
+    })
+}
+
+fn qubit_operand_re() -> &'static Regex {
+    static R: OnceLock<Regex> = OnceLock::new();
+    R.get_or_init(|| Regex::new(r"q\[(\d+)\]").unwrap())
+}
+
+/// Command-mode gate entry with plain qubit indices: `rx(pi/2) 0`, `cx 1 0`
+fn command_re() -> &'static Regex {
+    static R: OnceLock<Regex> = OnceLock::new();
+    R.get_or_init(|| Regex::new(r"^(\w+(?:\s*\([^)]*\))?)\s+(\d+(?:[\s,]+\d+)*)$").unwrap())
+}
+
 fn measure_re() -> &'static Regex {
     static R: OnceLock<Regex> = OnceLock::new();
     R.get_or_init(|| Regex::new(r"^measure\s+q\[(\d+)\]\s*->\s*(\w+)\[(\d+)\];?$").unwrap())
@@ -55,6 +81,11 @@ fn reset_re() -> &'static Regex {
     R.get_or_init(|| Regex::new(r"^reset\s+q\[(\d+)\];?$").unwrap())
 }
 
+fn reset_all_re() -> &'static Regex {
+    static R: OnceLock<Regex> = OnceLock::new();
+    R.get_or_init(|| Regex::new(r"^reset\s+(\w+)\s*;?$").unwrap())
+}
+
 fn if_re() -> &'static Regex {
     static R: OnceLock<Regex> = OnceLock::new();
     R.get_or_init(|| {
@@ -63,14 +94,12 @@ fn if_re() -> &'static Regex {
     })
 }
 
-fn if_param_re() -> &'static Regex {
+/// A classical condition in front of any gate statement:
+/// `if (c[0]==1) cz q[1], q[2];`
+fn if_prefix_re() -> &'static Regex {
     static R: OnceLock<Regex> = OnceLock::new();
     R.get_or_init(|| {
-        let p = PARAM_PAT;
-        Regex::new(&format!(
-            r"^if\s*\(\s*(\w+)(?:\[(\d+)\])?\s*==\s*(\d+)\s*\)\s+(\w+)\s*\(\s*({p})\s*\)\s+q\[(\d+)\];?$"
-        ))
-        .unwrap()
+        Regex::new(r"^if\s*\(\s*(\w+)(?:\[(\d+)\])?\s*==\s*(\d+)\s*\)\s*(.+)$").unwrap()
     })
 }
 
@@ -95,9 +124,70 @@ fn noise_re() -> &'static Regex {
     })
 }
 
+/// A noise channel attached to the gate on the same line:
+/// `h q[0]; // noise depolarizing 0.01`
+fn inline_noise_re() -> &'static Regex {
+    static R: OnceLock<Regex> = OnceLock::new();
+    R.get_or_init(|| {
+        let p = PARAM_PAT;
+        Regex::new(&format!(r"^(.*;)\s*//\s*noise\s+(\w+)(?:\s+({p}))?\s*$")).unwrap()
+    })
+}
+
+/// A caption on a barrier: `barrier q[0], q[1]; // label: pre-measure`
+fn barrier_label_re() -> &'static Regex {
+    static R: OnceLock<Regex> = OnceLock::new();
+    R.get_or_init(|| Regex::new(r"^(barrier\b[^;]*;)\s*//\s*label:\s*(.*?)\s*$").unwrap())
+}
+
+fn ancilla_re() -> &'static Regex {
+    static R: OnceLock<Regex> = OnceLock::new();
+    R.get_or_init(|| Regex::new(r"^//\s*ancilla\s+q\[(\d+)\]$").unwrap())
+}
+
+fn qubit_label_re() -> &'static Regex {
+    static R: OnceLock<Regex> = OnceLock::new();
+    R.get_or_init(|| Regex::new(r"^//\s*qubit\s+(\d+)\s+(.+)$").unwrap())
+}
+
+fn group_re() -> &'static Regex {
+    static R: OnceLock<Regex> = OnceLock::new();
+    R.get_or_init(|| Regex::new(r"^//\s*group\s+(\d+)\s+(\d+)\s+(.+)$").unwrap())
+}
+
 fn barrier_re() -> &'static Regex {
     static R: OnceLock<Regex> = OnceLock::new();
-    R.get_or_init(|| Regex::new(r"^barrier\s+").unwrap())
+    R.get_or_init(|| Regex::new(r"^barrier(?:\s+(.*?))?\s*;?$").unwrap())
+}
+
+/// qelib1.inc gates spelled differently from (or missing from) the gate
+/// menu, mapped to the native gate type they import as. A `dg` suffix is
+/// split off into the dagger flag afterwards.
+const QELIB1_GATES: &[(&str, &str)] = &[
+    ("u", "U3"),
+    ("u0", "U0"),
+    ("id", "I"),
+    ("cnot", "CX"),
+    ("cphase", "CP"),
+    ("cu3", "CU3"),
+    ("rxx", "RXX"),
+    ("rzz", "RZZ"),
+    ("sxdg", "SXDG"),
+    ("sydg", "SYDG"),
+];
+
+/// Native gate type for a QASM gate keyword: the menu's own types as they
+/// are, otherwise the qelib1 definition table.
+fn native_gate_type(keyword: &str) -> String {
+    let upper = keyword.to_uppercase();
+    if find_menu_item(&upper).is_some() {
+        return upper;
+    }
+    let lower = keyword.to_lowercase();
+    QELIB1_GATES
+        .iter()
+        .find(|(name, _)| *name == lower)
+        .map_or(upper, |(_, native)| native.to_string())
 }
 
 // ── Data structures ───────────────────────────────────────────────────────────
@@ -117,9 +207,64 @@ pub struct DAGNode {
     pub classical_control: isize,
     pub is_noise: bool,
     pub noise_type: String,
+    /// `//` comment lines written directly above this node in the QASM
+    pub comment: Option<String>,
+    /// Scheduling duration; None uses the per-type default
+    pub duration: Option<f64>,
+    /// Noise channel (type, strength) applied right after the gate
+    pub noise: Option<(String, f64)>,
+    /// Caption drawn above a barrier
+    pub label: Option<String>,
+    /// Insertion order, assigned by `add_node`; orders nodes within a step
+    pub seq: u64,
     pub dependencies: Vec<String>,
 }
 
+impl DAGNode {
+    /// Every qubit the node acts on: controls first, then the target.
+    pub fn qubits(&self) -> Vec<usize> {
+        let mut qubits = self.controls.clone();
+        for q in [self.measure_source, self.control, self.target] {
+            if q >= 0 {
+                qubits.push(q as usize);
+            }
+        }
+        qubits
+    }
+
+    /// Whether the node occupies `qubit`. A barrier with no qubit list spans
+    /// every wire.
+    pub fn references(&self, qubit: usize) -> bool {
+        if self.type_name == "BARRIER" {
+            return self.controls.is_empty() || self.controls.contains(&qubit);
+        }
+        let q = qubit as isize;
+        self.target == q
+            || self.control == q
+            || self.measure_source == q
+            || self.controls.contains(&qubit)
+    }
+
+    /// Whether the node lies wholly inside the block `steps` × `qubits`: its
+    /// step and every qubit it acts on, so a multi-qubit gate with one end
+    /// outside is not. A barrier with no qubit list spans all `num_qubits`
+    /// wires.
+    pub fn inside_region(
+        &self,
+        steps: &RangeInclusive<isize>,
+        qubits: &RangeInclusive<usize>,
+        num_qubits: usize,
+    ) -> bool {
+        if !steps.contains(&self.step) {
+            return false;
+        }
+        if self.type_name == "BARRIER" && self.controls.is_empty() {
+            return (0..num_qubits).all(|q| qubits.contains(&q));
+        }
+        self.qubits().iter().all(|q| qubits.contains(q))
+    }
+}
+
 impl Default for DAGNode {
     fn default() -> Self {
         DAGNode {
@@ -136,17 +281,50 @@ impl Default for DAGNode {
             classical_control: -1,
             is_noise: false,
             noise_type: String::new(),
+            comment: None,
+            duration: None,
+            noise: None,
+            label: None,
+            seq: 0,
             dependencies: vec![],
         }
     }
 }
 
+/// A suspicious but parseable QASM line, such as a qubit index beyond the
+/// declared register.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseWarning {
+    /// Zero-based line index, as in parse errors
+    pub line: usize,
+    pub message: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct CircuitDAG {
     pub nodes: HashMap<String, DAGNode>,
     pub num_qubits: usize,
+    /// Width written as `creg c[n]`: the total declared by the parsed file,
+    /// grown to fit measurements and conditions. Unused bits are kept, so
+    /// `creg c[8];` with one measurement still saves as `c[8]`, and a
+    /// circuit with none writes no `creg` at all.
     pub num_cbits: usize,
+    pub ancillas: Vec<usize>,
+    /// Comments after the last gate, which have no node to attach to
+    pub trailing_comment: Option<String>,
+    /// Parsed from an `OPENQASM 3` header; controlled gates are then
+    /// written with `ctrl @` modifiers
+    pub openqasm3: bool,
+    /// Warnings from the last `parse_qasm`
+    pub warnings: Vec<ParseWarning>,
+    /// Labelled step ranges (start, end inclusive, label) boxed in the
+    /// diagram. Display only; saved as `// group` comments.
+    pub groups: Vec<(isize, isize, String)>,
+    /// Wire names shown instead of `q[n]`. Display only; saved as
+    /// `// qubit N NAME` comments.
+    pub qubit_labels: HashMap<usize, String>,
     root_nodes: Vec<String>,
+    next_seq: u64,
 }
 
 impl Default for CircuitDAG {
@@ -161,18 +339,36 @@ impl CircuitDAG {
             nodes: HashMap::new(),
             num_qubits: 0,
             num_cbits: 0,
+            ancillas: vec![],
+            trailing_comment: None,
+            openqasm3: false,
+            warnings: vec![],
+            groups: vec![],
+            qubit_labels: HashMap::new(),
             root_nodes: vec![],
+            next_seq: 0,
         }
     }
 
-    fn generate_node_id(gate_type: &str, target: isize, step: isize) -> String {
-        format!("{gate_type}_q{target}_s{step}")
+    /// `{gate}_q{target}_s{step}`, with a `_N` suffix when that ID is already
+    /// taken (e.g. a gate and its controlled form on the same target and step).
+    fn generate_node_id(&self, gate_type: &str, target: isize, step: isize) -> String {
+        let base = format!("{gate_type}_q{target}_s{step}");
+        if !self.nodes.contains_key(&base) {
+            return base;
+        }
+        (1..)
+            .map(|n| format!("{base}_{n}"))
+            .find(|id| !self.nodes.contains_key(id))
+            .unwrap()
     }
 
     pub fn add_node(&mut self, mut node: DAGNode) {
         if node.id.is_empty() {
-            node.id = Self::generate_node_id(&node.type_name, node.target, node.step);
+            node.id = self.generate_node_id(&node.type_name, node.target, node.step);
         }
+        node.seq = self.next_seq;
+        self.next_seq += 1;
 
         // Update qubit count
         let max_qubit = {
@@ -260,12 +456,11 @@ impl CircuitDAG {
             }
         }
 
-        let root_ids: Vec<String> = self.root_nodes.clone();
-        for root_id in &root_ids {
-            visit(root_id, &self.nodes, &mut visited, &mut result);
-        }
-        let all_ids: Vec<String> = self.nodes.keys().cloned().collect();
-        for id in &all_ids {
+        // Visit in (step, seq) order so independent nodes come out in the
+        // same order every time rather than in HashMap order.
+        let mut all_ids: Vec<&String> = self.nodes.keys().collect();
+        all_ids.sort_by_key(|id| (self.nodes[*id].step, self.nodes[*id].seq));
+        for id in all_ids {
             visit(id, &self.nodes, &mut visited, &mut result);
         }
         result
@@ -275,11 +470,34 @@ impl CircuitDAG {
         self.nodes.values().map(|n| n.step).max().unwrap_or(0)
     }
 
+    /// Classical bits the circuit writes or reads: measurement results and
+    /// conditions. `num_cbits` may be larger but never smaller.
+    pub fn required_cbits(&self) -> usize {
+        self.nodes
+            .values()
+            .map(|n| {
+                let mut needed = 0;
+                if n.type_name == "MEASURE" && n.target >= 0 {
+                    needed = n.target as usize + 1;
+                }
+                if n.measure_source >= 0 {
+                    needed = needed.max(n.measure_source as usize + 1);
+                }
+                if n.classical_control >= 0 {
+                    needed = needed.max(n.classical_control as usize + 1);
+                }
+                needed
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
     pub fn to_circuit(&self) -> Circuit {
         let mut circuit = Circuit {
             num_qubits: self.num_qubits,
             gates: Vec::new(),
             max_steps: self.max_step() as usize,
+            declared_cbits: self.num_cbits,
         };
 
         for node in self.nodes.values() {
@@ -300,6 +518,9 @@ impl CircuitDAG {
                 classical_control: node.classical_control,
                 is_noise: node.is_noise,
                 noise_type: node.noise_type.clone(),
+                duration: node.duration,
+                noise: node.noise.clone(),
+                label: node.label.clone(),
             };
             circuit.gates.push(gate);
         }
@@ -308,6 +529,13 @@ impl CircuitDAG {
     }
 
     pub fn to_qasm(&self) -> String {
+        self.to_qasm_with(false)
+    }
+
+    /// `to_qasm`, optionally with a full-width barrier between consecutive
+    /// steps so tools without columns keep the circuit's layers and depth.
+    /// No extra barrier is written next to a step that already has one.
+    pub fn to_qasm_with(&self, insert_layer_barriers: bool) -> String {
         let mut nodes: Vec<&DAGNode> = self.topological_sort();
         nodes.sort_by_key(|n| n.step);
 
@@ -337,14 +565,33 @@ impl CircuitDAG {
                 }
                 m
             });
-            (max_c + 1).max(1) as usize
+            (max_c + 1) as usize
         };
 
         let mut sb = String::new();
-        sb.push_str("OPENQASM 2.0;\n");
-        sb.push_str("include \"qelib1.inc\";\n\n");
+        if self.openqasm3 {
+            sb.push_str("OPENQASM 3.0;\n");
+            sb.push_str("include \"stdgates.inc\";\n\n");
+        } else {
+            sb.push_str("OPENQASM 2.0;\n");
+            sb.push_str("include \"qelib1.inc\";\n\n");
+        }
         sb.push_str(&format!("qreg q[{num_qubits}];\n"));
-        sb.push_str(&format!("creg c[{num_cbits}];\n\n"));
+        if num_cbits > 0 {
+            sb.push_str(&format!("creg c[{num_cbits}];\n"));
+        }
+        for &a in &self.ancillas {
+            sb.push_str(&format!("// ancilla q[{a}]\n"));
+        }
+        let mut labels: Vec<(&usize, &String)> = self.qubit_labels.iter().collect();
+        labels.sort();
+        for (q, label) in labels {
+            sb.push_str(&format!("// qubit {q} {label}\n"));
+        }
+        for (start, end, label) in &self.groups {
+            sb.push_str(&format!("// group {start} {end} {label}\n"));
+        }
+        sb.push('\n');
 
         // Group by step
         let max_step = nodes.iter().map(|n| n.step).max().unwrap_or(0);
@@ -353,42 +600,82 @@ impl CircuitDAG {
             step_map.entry(node.step).or_default().push(node);
         }
 
+        // Whether the last written step held a barrier
+        let mut prev_barrier: Option<bool> = None;
         for step in 0..=max_step {
             if let Some(step_nodes) = step_map.get(&step) {
+                let has_barrier = step_nodes.iter().any(|n| n.type_name == "BARRIER");
+                if insert_layer_barriers && prev_barrier == Some(false) && !has_barrier {
+                    let qubits: Vec<String> = (0..num_qubits).map(|q| format!("q[{q}]")).collect();
+                    sb.push_str(&format!("barrier {};\n", qubits.join(", ")));
+                }
+                prev_barrier = Some(has_barrier);
                 for node in step_nodes {
-                    sb.push_str(&write_node_qasm(node, num_qubits));
+                    let mut text = if self.openqasm3
+                        && let Some(line) = write_ctrl_modifier_qasm(node)
+                    {
+                        write_comment(&node.comment) + &line
+                    } else {
+                        write_node_qasm(node, num_qubits, self.openqasm3)
+                    };
+                    push_inline_noise(&mut text, node);
+                    push_barrier_label(&mut text, node);
+                    sb.push_str(&text);
                 }
             }
         }
+        sb.push_str(&write_comment(&self.trailing_comment));
 
         sb
     }
 
+    /// The circuit as readable sentences in execution order, one per line,
+    /// paired with the step each line describes. Measurements sharing a
+    /// step are merged into a single line.
+    pub fn to_listing(&self) -> Vec<(isize, String)> {
+        let mut nodes: Vec<&DAGNode> = self.topological_sort();
+        nodes.sort_by_key(|n| n.step);
+
+        let mut lines: Vec<(isize, String)> = Vec::new();
+        let mut measured: Vec<isize> = Vec::new();
+        let mut measure_step = -1;
+
+        for node in nodes {
+            if node.type_name == "MEASURE" && node.classical_control < 0 {
+                if measure_step != node.step && !measured.is_empty() {
+                    lines.push(measure_line(measure_step, &mut measured));
+                }
+                measure_step = node.step;
+                measured.push(node.target);
+                continue;
+            }
+            lines.push((
+                node.step,
+                format!("Step {}: {}", node.step, describe_node(node)),
+            ));
+        }
+        if !measured.is_empty() {
+            lines.push(measure_line(measure_step, &mut measured));
+        }
+
+        // Keep merged measurement lines in step order
+        lines.sort_by_key(|(step, _)| *step);
+        lines
+    }
+
     // ── Gate placement helpers ────────────────────────────────────────────────
 
+    /// The node on `qubit` at `step`. If several overlap there, the one
+    /// targeting `qubit` wins, then the one added first.
     pub fn get_node_at(&self, step: isize, qubit: usize) -> Option<&DAGNode> {
-        let q = qubit as isize;
-        self.nodes.values().find(|n| {
-            n.step == step
-                && (n.target == q
-                    || n.control == q
-                    || n.measure_source == q
-                    || n.controls.contains(&qubit))
-        })
+        self.nodes
+            .values()
+            .filter(|n| n.step == step && n.references(qubit))
+            .min_by_key(|n| (n.target != qubit as isize, n.seq))
     }
 
     pub fn get_node_at_mut(&mut self, step: isize, qubit: usize) -> Option<String> {
-        let q = qubit as isize;
-        self.nodes
-            .iter()
-            .find(|(_, n)| {
-                n.step == step
-                    && (n.target == q
-                        || n.control == q
-                        || n.measure_source == q
-                        || n.controls.contains(&qubit))
-            })
-            .map(|(id, _)| id.clone())
+        self.get_node_at(step, qubit).map(|n| n.id.clone())
     }
 
     pub fn can_place_gate_at(&self, step: isize, qubits: &[usize]) -> bool {
@@ -411,6 +698,25 @@ impl CircuitDAG {
         }
     }
 
+    /// Delete every node wholly inside `steps` × `qubits` (see
+    /// `DAGNode::inside_region`). Returns how many were removed.
+    pub fn remove_region(
+        &mut self,
+        steps: RangeInclusive<isize>,
+        qubits: RangeInclusive<usize>,
+    ) -> usize {
+        let ids: Vec<String> = self
+            .nodes
+            .values()
+            .filter(|n| n.inside_region(&steps, &qubits, self.num_qubits))
+            .map(|n| n.id.clone())
+            .collect();
+        for id in &ids {
+            self.remove_node(id);
+        }
+        ids.len()
+    }
+
     pub fn remove_nodes_on_qubit(&mut self, qubit: usize) {
         let q = qubit as isize;
         let to_remove: Vec<String> = self
@@ -427,39 +733,91 @@ impl CircuitDAG {
         for id in to_remove {
             self.remove_node(&id);
         }
+        self.ancillas.retain(|&a| a != qubit);
+        self.qubit_labels.remove(&qubit);
+    }
+
+    /// Attach a noise channel to the gate on `qubit` at `step`, or detach it
+    /// with None. Returns false when there is no gate there to carry it.
+    pub fn set_node_noise(
+        &mut self,
+        step: isize,
+        qubit: usize,
+        noise: Option<(String, f64)>,
+    ) -> bool {
+        let Some(id) = self.get_node_at_mut(step, qubit) else {
+            return false;
+        };
+        match self.nodes.get_mut(&id) {
+            Some(node) if !node.is_noise && node.type_name != "BARRIER" => {
+                node.noise = noise;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Name `qubit`'s wire, or go back to `q[n]` when `label` is blank.
+    pub fn set_qubit_label(&mut self, qubit: usize, label: &str) {
+        let label = label.trim();
+        if label.is_empty() {
+            self.qubit_labels.remove(&qubit);
+        } else {
+            self.qubit_labels.insert(qubit, label.to_string());
+        }
+    }
+
+    pub fn is_ancilla(&self, qubit: usize) -> bool {
+        self.ancillas.contains(&qubit)
+    }
+
+    /// Grow the register by one qubit and mark it as an ancilla.
+    /// Returns the index of the new qubit.
+    pub fn allocate_ancilla(&mut self) -> usize {
+        let q = self.num_qubits;
+        self.num_qubits += 1;
+        self.ancillas.push(q);
+        q
+    }
+
+    /// Undo `allocate_ancilla`: unmark `q` and drop its label, shrinking the
+    /// register again when `q` is its last qubit.
+    pub fn release_ancilla(&mut self, q: usize) {
+        self.ancillas.retain(|&a| a != q);
+        self.qubit_labels.remove(&q);
+        if q + 1 == self.num_qubits {
+            self.num_qubits -= 1;
+        }
     }
 
     // ─── Add helpers (mirrors dag.go) ─────────────────────────────────────────
 
-    fn build_deps(&self, qubits_used: &[usize], step: isize, gate_type: &str) -> Vec<String> {
-        let mut last_gate_on_qubit: HashMap<usize, String> = HashMap::new();
+    /// The latest node on each of `qubits_used` at or before `step`. Nodes
+    /// already in `step` were added earlier, so the new node follows them.
+    fn build_deps(&self, qubits_used: &[usize], step: isize) -> Vec<String> {
+        let mut last_gate_on_qubit: HashMap<usize, &DAGNode> = HashMap::new();
         for n in self.nodes.values() {
-            let mut qs = vec![];
-            if n.target >= 0 {
-                qs.push(n.target as usize);
-            }
-            if n.control >= 0 {
-                qs.push(n.control as usize);
-            }
-            for &c in &n.controls {
-                qs.push(c);
-            }
-            if n.measure_source >= 0 {
-                qs.push(n.measure_source as usize);
+            if n.step > step {
+                continue;
             }
-            for q in qs {
-                if n.step < step || (n.step == step && n.type_name.as_str() < gate_type) {
-                    last_gate_on_qubit.insert(q, n.id.clone());
+            for q in n.qubits() {
+                let later = last_gate_on_qubit
+                    .get(&q)
+                    .is_none_or(|prev| (n.step, n.seq) > (prev.step, prev.seq));
+                if later {
+                    last_gate_on_qubit.insert(q, n);
                 }
             }
         }
-        let mut dep_set: HashMap<String, bool> = HashMap::new();
-        for &q in qubits_used {
-            if let Some(id) = last_gate_on_qubit.get(&q) {
-                dep_set.insert(id.clone(), true);
+        let mut deps: Vec<String> = Vec::new();
+        for q in qubits_used {
+            if let Some(n) = last_gate_on_qubit.get(q)
+                && !deps.contains(&n.id)
+            {
+                deps.push(n.id.clone());
             }
         }
-        dep_set.into_keys().collect()
+        deps
     }
 
     pub fn add_gate(
@@ -475,8 +833,8 @@ impl CircuitDAG {
         } else {
             vec![target]
         };
-        let deps = self.build_deps(&qubits, step, gate_type);
-        let id = Self::generate_node_id(gate_type, target as isize, step);
+        let deps = self.build_deps(&qubits, step);
+        let id = self.generate_node_id(gate_type, target as isize, step);
         self.add_node(DAGNode {
             id,
             type_name: gate_type.to_string(),
@@ -502,8 +860,8 @@ impl CircuitDAG {
         } else {
             vec![target]
         };
-        let deps = self.build_deps(&qubits, step, gate_type);
-        let id = Self::generate_node_id(gate_type, target as isize, step);
+        let deps = self.build_deps(&qubits, step);
+        let id = self.generate_node_id(gate_type, target as isize, step);
         self.add_node(DAGNode {
             id,
             type_name: gate_type.to_string(),
@@ -525,8 +883,8 @@ impl CircuitDAG {
     ) {
         let mut qubits = vec![target];
         qubits.extend_from_slice(&controls);
-        let deps = self.build_deps(&qubits, step, gate_type);
-        let id = Self::generate_node_id(gate_type, target as isize, step);
+        let deps = self.build_deps(&qubits, step);
+        let id = self.generate_node_id(gate_type, target as isize, step);
         self.add_node(DAGNode {
             id,
             type_name: gate_type.to_string(),
@@ -548,8 +906,8 @@ impl CircuitDAG {
         if cbit + 1 > self.num_cbits {
             self.num_cbits = cbit + 1;
         }
-        let deps = self.build_deps(&[target], step, gate_type);
-        let id = Self::generate_node_id(gate_type, target as isize, step);
+        let deps = self.build_deps(&[target], step);
+        let id = self.generate_node_id(gate_type, target as isize, step);
         self.add_node(DAGNode {
             id,
             type_name: gate_type.to_string(),
@@ -562,8 +920,8 @@ impl CircuitDAG {
     }
 
     pub fn add_dagger_gate(&mut self, gate_type: &str, target: usize, step: isize) {
-        let deps = self.build_deps(&[target], step, gate_type);
-        let id = Self::generate_node_id(gate_type, target as isize, step);
+        let deps = self.build_deps(&[target], step);
+        let id = self.generate_node_id(gate_type, target as isize, step);
         self.add_node(DAGNode {
             id,
             type_name: gate_type.to_string(),
@@ -576,8 +934,8 @@ impl CircuitDAG {
     }
 
     pub fn add_reset(&mut self, target: usize, step: isize) {
-        let deps = self.build_deps(&[target], step, "RESET");
-        let id = Self::generate_node_id("RESET", target as isize, step);
+        let deps = self.build_deps(&[target], step);
+        let id = self.generate_node_id("RESET", target as isize, step);
         self.add_node(DAGNode {
             id,
             type_name: "RESET".to_string(),
@@ -590,8 +948,8 @@ impl CircuitDAG {
     }
 
     pub fn add_noise(&mut self, target: usize, step: isize, noise_type: &str, params: Vec<f64>) {
-        let deps = self.build_deps(&[target], step, "NOISE");
-        let id = Self::generate_node_id("NOISE", target as isize, step);
+        let deps = self.build_deps(&[target], step);
+        let id = self.generate_node_id("NOISE", target as isize, step);
         self.add_node(DAGNode {
             id,
             type_name: "NOISE".to_string(),
@@ -610,8 +968,8 @@ impl CircuitDAG {
             self.num_cbits = source + 1;
         }
         let qubits = [source, target];
-        let deps = self.build_deps(&qubits, step, "MCX");
-        let id = Self::generate_node_id("MCX", target as isize, step);
+        let deps = self.build_deps(&qubits, step);
+        let id = self.generate_node_id("MCX", target as isize, step);
         self.add_node(DAGNode {
             id,
             type_name: "MCX".to_string(),
@@ -634,7 +992,7 @@ impl CircuitDAG {
         for id in to_remove {
             self.remove_node(&id);
         }
-        let id = Self::generate_node_id("BARRIER", -1, step);
+        let id = self.generate_node_id("BARRIER", -1, step);
         self.add_node(DAGNode {
             id,
             type_name: "BARRIER".to_string(),
@@ -643,119 +1001,797 @@ impl CircuitDAG {
         });
     }
 
-    // ── QASM Parsing ──────────────────────────────────────────────────────────
+    /// Caption the barrier at `step`, or clear its caption when `label` is
+    /// blank.
+    pub fn set_barrier_label(&mut self, step: isize, label: &str) {
+        let label = label.trim();
+        for node in self.nodes.values_mut() {
+            if node.step == step && node.type_name == "BARRIER" {
+                node.label = (!label.is_empty()).then(|| label.to_string());
+            }
+        }
+    }
 
-    pub fn parse_qasm(&mut self, qasm: &str) -> Vec<(usize, String)> {
-        self.nodes.clear();
-        self.root_nodes.clear();
-        let mut errors = vec![];
+    /// Append a column of MEASURE gates on every qubit after the last step.
+    /// Returns the step the measurements were placed at.
+    pub fn measure_all(&mut self) -> isize {
+        let step = if self.nodes.is_empty() {
+            0
+        } else {
+            self.max_step() + 1
+        };
+        for q in 0..self.num_qubits {
+            self.add_gate("MEASURE", q, step, None);
+        }
+        self.num_cbits = self.num_cbits.max(self.num_qubits);
+        step
+    }
 
-        let lines: Vec<&str> = qasm.lines().collect();
-        let mut creg_map: HashMap<String, usize> = HashMap::new();
-        let mut creg_offset: usize = 0;
+    /// Reassign steps to the earliest column each node can occupy (ASAP),
+    /// closing gaps left by deleted gates. Relative order on every qubit and
+    /// classical bit is kept, multi-qubit gates keep their span to themselves,
+    /// and barriers still separate everything before them from everything after.
+    pub fn compact_steps(&mut self) {
+        let mut order: Vec<(isize, u64, String)> = self
+            .nodes
+            .values()
+            .map(|n| (n.step, n.seq, n.id.clone()))
+            .collect();
+        order.sort();
 
-        let resolve_cbit =
-            |reg_name: &str, bit_idx: &str, creg_map: &HashMap<String, usize>| -> usize {
-                if let Some(&start) = creg_map.get(reg_name) {
-                    if !bit_idx.is_empty() {
-                        let offset: usize = bit_idx.parse().unwrap_or(0);
-                        return start + offset;
-                    }
-                    return start;
-                }
-                // fallback: try to parse c[N] style
-                if reg_name.starts_with('c') {
-                    if let Ok(idx) = reg_name[1..].parse::<usize>() {
-                        return idx;
-                    }
-                }
-                0
-            };
+        let mut qubit_ready: HashMap<usize, isize> = HashMap::new();
+        let mut cbit_ready: HashMap<usize, isize> = HashMap::new();
+        let mut floor = 0;
 
-        let mut last_gate_on_qubit: HashMap<usize, String> = HashMap::new();
-        let mut current_step_qubits: HashMap<usize, bool> = HashMap::new();
-        let mut current_step: isize = 0;
+        for (_, _, id) in order {
+            let node = &self.nodes[&id];
 
-        let get_qubits_used = |node: &DAGNode| -> Vec<usize> {
-            let mut qs = vec![];
-            if node.target >= 0 {
-                qs.push(node.target as usize);
+            if node.type_name == "BARRIER" {
+                let step = qubit_ready.values().copied().fold(floor, isize::max);
+                floor = step + 1;
+                if let Some(node) = self.nodes.get_mut(&id) {
+                    node.step = step;
+                }
+                continue;
             }
-            if node.control >= 0 {
-                qs.push(node.control as usize);
+
+            let span = node_span(node);
+            let mut step = floor;
+            for q in &span {
+                step = step.max(qubit_ready.get(q).copied().unwrap_or(0));
             }
-            for &c in &node.controls {
-                qs.push(c);
+            if node.classical_control >= 0 {
+                let c = node.classical_control as usize;
+                step = step.max(cbit_ready.get(&c).copied().unwrap_or(0));
             }
-            if node.measure_source >= 0 {
-                qs.push(node.measure_source as usize);
+
+            let written_cbit = if node.type_name == "MEASURE" {
+                node.target
+            } else {
+                node.measure_source
+            };
+            if written_cbit >= 0 {
+                cbit_ready.insert(written_cbit as usize, step + 1);
             }
-            qs
-        };
+            for &q in &span {
+                qubit_ready.insert(q, step + 1);
+            }
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.step = step;
+            }
+        }
 
-        let mut i = 0;
-        while i < lines.len() {
-            let line_idx = i;
-            let line = lines[i].trim();
-            i += 1;
+        self.rebuild_dependencies();
+    }
 
-            if line.is_empty() {
-                continue;
-            }
+    /// Recompute every node's dependencies from the current step order: each
+    /// node depends on the previous node on each of its qubits, on the last
+    /// writer of any classical bit it reads, and on the most recent barrier.
+    /// Full-width barriers depend on everything placed since the barrier
+    /// before them; a barrier on listed qubits is ordered like a gate.
+    pub fn rebuild_dependencies(&mut self) {
+        let mut order: Vec<(isize, u64, String)> = self
+            .nodes
+            .values()
+            .map(|n| (n.step, n.seq, n.id.clone()))
+            .collect();
+        order.sort();
+
+        let mut last_on_qubit: HashMap<usize, String> = HashMap::new();
+        let mut last_writer: HashMap<usize, String> = HashMap::new();
+        let mut since_barrier: Vec<String> = Vec::new();
+        let mut last_barrier: Option<String> = None;
+
+        for (_, _, id) in order {
+            let node = &self.nodes[&id];
+            let mut deps: Vec<String> = Vec::new();
+            let mut push = |dep: &String| {
+                if !deps.contains(dep) {
+                    deps.push(dep.clone());
+                }
+            };
 
-            // Comments / noise
-            if line.starts_with("//") {
-                if let Some(caps) = noise_re().captures(line) {
-                    let target: usize = caps[2].parse().unwrap_or(0);
-                    let qubits_used = vec![target];
-                    for &q in &qubits_used {
-                        if *current_step_qubits.get(&q).unwrap_or(&false) {
-                            current_step += 1;
-                            current_step_qubits.clear();
-                            break;
-                        }
-                    }
-                    for &q in &qubits_used {
-                        current_step_qubits.insert(q, true);
+            if node.type_name == "BARRIER" && node.controls.is_empty() {
+                since_barrier.iter().for_each(&mut push);
+                if since_barrier.is_empty()
+                    && let Some(b) = &last_barrier
+                {
+                    push(b);
+                }
+                since_barrier.clear();
+                last_on_qubit.clear();
+                last_barrier = Some(id.clone());
+            } else {
+                let mut qubits: Vec<usize> = node.controls.clone();
+                for q in [node.target, node.control, node.measure_source] {
+                    if q >= 0 {
+                        qubits.push(q as usize);
                     }
-                    let noise_type = caps[1].to_string();
-                    let params = if let Some(m) = caps.get(3) {
-                        parse_param_expr(m.as_str())
-                            .map(|v| vec![v])
-                            .unwrap_or_default()
-                    } else {
-                        vec![]
-                    };
-                    let mut node = DAGNode {
-                        type_name: "NOISE".to_string(),
-                        target: target as isize,
-                        step: current_step,
-                        params,
-                        is_noise: true,
-                        noise_type,
-                        ..Default::default()
-                    };
-                    if let Some(last_id) = last_gate_on_qubit.get(&target) {
-                        node.dependencies.push(last_id.clone());
+                }
+                let mut has_qubit_dep = false;
+                for q in &qubits {
+                    if let Some(dep) = last_on_qubit.get(q) {
+                        push(dep);
+                        has_qubit_dep = true;
                     }
-                    node.id = Self::generate_node_id("NOISE", target as isize, current_step);
-                    let node_id = node.id.clone();
-                    self.add_node(node);
-                    last_gate_on_qubit.insert(target, node_id);
-                    current_step += 1;
-                    current_step_qubits.clear();
                 }
-                continue;
+                if !has_qubit_dep && let Some(b) = &last_barrier {
+                    push(b);
+                }
+                if node.classical_control >= 0
+                    && let Some(dep) = last_writer.get(&(node.classical_control as usize))
+                {
+                    push(dep);
+                }
+
+                for q in qubits {
+                    last_on_qubit.insert(q, id.clone());
+                }
+                let written_cbit = if node.type_name == "MEASURE" {
+                    node.target
+                } else {
+                    node.measure_source
+                };
+                if written_cbit >= 0 {
+                    last_writer.insert(written_cbit as usize, id.clone());
+                }
+                since_barrier.push(id.clone());
             }
 
-            if line.starts_with("OPENQASM") || line.starts_with("include") {
-                continue;
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.dependencies = deps;
             }
+        }
 
-            if line.starts_with("qreg") {
-                if let Some(caps) = qreg_re().captures(line) {
-                    let n: usize = caps[2].parse().unwrap_or(0);
+        self.update_root_nodes();
+    }
+
+    /// Pack gates into as few steps as the dependency graph allows: each
+    /// node moves to the length of the longest dependency path leading to
+    /// it, then on to the first step where every row it spans is free.
+    /// Unlike `compact_steps`, a gate may drop into a gap before a
+    /// multi-qubit gate it does not depend on.
+    pub fn reschedule_asap(&mut self) {
+        self.rebuild_dependencies();
+
+        // Dependencies always point to earlier (step, seq) positions, so
+        // that order is a valid topological order
+        let mut order: Vec<(isize, u64, String)> = self
+            .nodes
+            .values()
+            .map(|n| (n.step, n.seq, n.id.clone()))
+            .collect();
+        order.sort();
+
+        let mut new_step: HashMap<String, isize> = HashMap::new();
+        let mut occupied: HashSet<(isize, usize)> = HashSet::new();
+        let mut last_write: HashMap<usize, isize> = HashMap::new();
+        for (_, _, id) in order {
+            let node = &self.nodes[&id];
+            let span: Vec<usize> = if node.type_name == "BARRIER" && node.controls.is_empty() {
+                (0..self.num_qubits).collect()
+            } else {
+                node_span(node)
+            };
+            let written_cbit = if node.type_name == "MEASURE" {
+                node.target
+            } else {
+                node.measure_source
+            };
+
+            let mut step = node
+                .dependencies
+                .iter()
+                .filter_map(|dep| new_step.get(dep))
+                .map(|s| s + 1)
+                .max()
+                .unwrap_or(0);
+            // Two writes to one classical bit keep their order
+            if written_cbit >= 0
+                && let Some(&s) = last_write.get(&(written_cbit as usize))
+            {
+                step = step.max(s + 1);
+            }
+            while span.iter().any(|&q| occupied.contains(&(step, q))) {
+                step += 1;
+            }
+
+            if written_cbit >= 0 {
+                last_write.insert(written_cbit as usize, step);
+            }
+            occupied.extend(span.iter().map(|&q| (step, q)));
+            new_step.insert(id, step);
+        }
+
+        for (id, step) in new_step {
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.step = step;
+            }
+        }
+    }
+
+    /// Relabel qubits `a` and `b` everywhere. The circuit is the same up to
+    /// a wire permutation, so its unitary is conjugated by that permutation.
+    /// Measurements write the classical bit matching their qubit, so the
+    /// bits follow: a measurement that wrote c[a] now writes c[b].
+    pub fn swap_qubits(&mut self, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+        let swap = |q: usize| {
+            if q == a {
+                b
+            } else if q == b {
+                a
+            } else {
+                q
+            }
+        };
+        let swap_signed = |q: isize| if q >= 0 { swap(q as usize) as isize } else { q };
+
+        let old: Vec<DAGNode> = self.nodes.drain().map(|(_, n)| n).collect();
+        for mut node in old {
+            node.target = swap_signed(node.target);
+            node.control = swap_signed(node.control);
+            node.measure_source = swap_signed(node.measure_source);
+            for c in &mut node.controls {
+                *c = swap(*c);
+            }
+            node.id = self.generate_node_id(&node.type_name, node.target, node.step);
+            self.nodes.insert(node.id.clone(), node);
+        }
+        for q in &mut self.ancillas {
+            *q = swap(*q);
+        }
+        self.qubit_labels = self
+            .qubit_labels
+            .drain()
+            .map(|(q, label)| (swap(q), label))
+            .collect();
+        self.rebuild_dependencies();
+    }
+
+    /// Open `count` empty steps at `at`, moving every gate at or after it later.
+    pub fn insert_step(&mut self, at: isize, count: isize) {
+        if count <= 0 {
+            return;
+        }
+        let old: Vec<DAGNode> = self.nodes.drain().map(|(_, n)| n).collect();
+        for mut node in old {
+            if node.step >= at {
+                node.step += count;
+            }
+            node.id = self.generate_node_id(&node.type_name, node.target, node.step);
+            self.nodes.insert(node.id.clone(), node);
+        }
+        for (start, end, _) in &mut self.groups {
+            if *start >= at {
+                *start += count;
+            }
+            if *end >= at {
+                *end += count;
+            }
+        }
+        self.rebuild_dependencies();
+    }
+
+    /// Box steps `start..=end` under `label`, replacing any group it overlaps.
+    pub fn add_group(&mut self, start: isize, end: isize, label: &str) {
+        let (start, end) = (start.min(end), start.max(end));
+        self.groups.retain(|(s, e, _)| *e < start || *s > end);
+        self.groups.push((start, end, label.to_string()));
+        self.groups.sort_by_key(|(s, _, _)| *s);
+    }
+
+    /// Remove the group covering `step`. Returns its label.
+    pub fn remove_group_at(&mut self, step: isize) -> Option<String> {
+        let i = self
+            .groups
+            .iter()
+            .position(|(s, e, _)| (*s..=*e).contains(&step))?;
+        Some(self.groups.remove(i).2)
+    }
+
+    /// A reproducible random circuit for demos and stress tests. Each of the
+    /// `depth` steps gets at most one two-qubit gate, on neighbouring wires so
+    /// its connector crosses no other gate, and single-qubit gates on most of
+    /// the remaining qubits. The same `seed` always gives the same circuit.
+    pub fn random(num_qubits: usize, depth: usize, seed: u64) -> CircuitDAG {
+        const SINGLE: &[&str] = &["H", "X", "Y", "Z", "S", "T", "SX", "RX", "RY", "RZ"];
+        const DOUBLE: &[&str] = &["CX", "CZ", "SWAP", "CP"];
+        let mut rng = SplitMix64(seed);
+        let mut dag = CircuitDAG::new();
+        dag.num_qubits = num_qubits.max(1);
+
+        for step in 0..depth as isize {
+            let mut free = vec![true; dag.num_qubits];
+            if dag.num_qubits >= 2 && rng.below(3) == 0 {
+                let q = rng.below(dag.num_qubits - 1);
+                let (control, target) = if rng.below(2) == 0 {
+                    (q, q + 1)
+                } else {
+                    (q + 1, q)
+                };
+                let gate = DOUBLE[rng.below(DOUBLE.len())];
+                if gate == "CP" {
+                    let angle = rng.quarter_turns();
+                    dag.add_parameterized_gate(gate, target, step, vec![angle], Some(control));
+                } else {
+                    dag.add_gate(gate, target, step, Some(control));
+                }
+                free[q] = false;
+                free[q + 1] = false;
+            }
+            for (q, is_free) in free.into_iter().enumerate() {
+                if !is_free || rng.below(4) == 0 {
+                    continue;
+                }
+                let gate = SINGLE[rng.below(SINGLE.len())];
+                if gate.starts_with('R') {
+                    let angle = rng.quarter_turns();
+                    dag.add_parameterized_gate(gate, q, step, vec![angle], None);
+                } else {
+                    dag.add_gate(gate, q, step, None);
+                }
+            }
+        }
+        dag.rebuild_dependencies();
+        dag
+    }
+
+    /// Insert an `n`-qubit quantum Fourier transform on qubits
+    /// `start..start + n`, in fresh steps opened at `start_step`. The highest
+    /// qubit is treated as the most significant bit: each qubit gets an H and
+    /// CP(π/2^k) from every lower qubit, then a SWAP network reverses the
+    /// qubit order. Every gate gets a step of its own.
+    pub fn add_qft(&mut self, start: usize, n: usize, start_step: isize) {
+        if n == 0 {
+            return;
+        }
+        let count = n + n * (n - 1) / 2 + n / 2;
+        self.insert_step(start_step, count as isize);
+
+        let mut step = start_step;
+        for j in (0..n).rev() {
+            self.add_gate("H", start + j, step, None);
+            step += 1;
+            for k in (0..j).rev() {
+                let angle = std::f64::consts::PI / (1u64 << (j - k)) as f64;
+                self.add_parameterized_gate("CP", start + j, step, vec![angle], Some(start + k));
+                step += 1;
+            }
+        }
+        for i in 0..n / 2 {
+            self.add_gate("SWAP", start + n - 1 - i, step, Some(start + i));
+            step += 1;
+        }
+        self.rebuild_dependencies();
+    }
+
+    /// Insert the Grover diffusion operator on `qubits` in new steps at
+    /// `start_step`: H and X on each, a Z on the last controlled by the
+    /// rest, then X and H again. The controlled Z is a CZ for two qubits and
+    /// H·CCX·H for three; wider ones chain CCXs through fresh ancillas,
+    /// which are returned to |0⟩. Equals I − 2|s⟩⟨s|, the reflection about
+    /// the mean up to global phase.
+    pub fn add_diffusion(&mut self, qubits: &[usize], start_step: isize) {
+        let Some((&target, controls)) = qubits.split_last() else {
+            return;
+        };
+        let chain = controls.len().saturating_sub(2);
+        let mcz_steps = if controls.len() < 2 { 1 } else { 3 + 2 * chain };
+        self.insert_step(start_step, (4 + mcz_steps) as isize);
+        let ancillas: Vec<usize> = (0..chain).map(|_| self.allocate_ancilla()).collect();
+
+        let mut step = start_step;
+        for gate in ["H", "X"] {
+            for &q in qubits {
+                self.add_gate(gate, q, step, None);
+            }
+            step += 1;
+        }
+        match controls {
+            [] => self.add_gate("Z", target, step, None),
+            [c] => self.add_gate("CZ", target, step, Some(*c)),
+            _ => {
+                // Ancilla k holds the AND of controls[..k + 2]
+                let and_of = |k: usize| if k == 0 { controls[0] } else { ancillas[k - 1] };
+                for (k, &a) in ancillas.iter().enumerate() {
+                    self.add_multi_control_gate("CCX", a, step, vec![and_of(k), controls[k + 1]]);
+                    step += 1;
+                }
+                let last = controls[controls.len() - 1];
+                self.add_gate("H", target, step, None);
+                self.add_multi_control_gate("CCX", target, step + 1, vec![and_of(chain), last]);
+                self.add_gate("H", target, step + 2, None);
+                step += 2;
+                for (k, &a) in ancillas.iter().enumerate().rev() {
+                    step += 1;
+                    self.add_multi_control_gate("CCX", a, step, vec![and_of(k), controls[k + 1]]);
+                }
+            }
+        }
+        step += 1;
+        for gate in ["X", "H"] {
+            for &q in qubits {
+                self.add_gate(gate, q, step, None);
+            }
+            step += 1;
+        }
+        self.rebuild_dependencies();
+    }
+
+    /// A copy of the circuit with every gate that has a rewrite into `basis`
+    /// replaced by it; other gates are kept as they are. Rewrites are equal
+    /// up to global phase. A step whose longest rewrite has k gates becomes k
+    /// steps, so gates keep their order and later steps move right.
+    pub fn transpile_to_basis(&self, basis: &[&str]) -> CircuitDAG {
+        let rewrite = |node: &DAGNode| {
+            if node.step < 0 || (!node.is_dagger && basis.contains(&node.type_name.as_str())) {
+                return None;
+            }
+            basis_decomposition(node).filter(|seq| seq.iter().all(|(g, _)| basis.contains(g)))
+        };
+
+        let max_step = self.max_step().max(0) as usize;
+        let mut width = vec![1; max_step + 1];
+        for node in self.nodes.values() {
+            if let Some(seq) = rewrite(node) {
+                let w = &mut width[node.step as usize];
+                *w = (*w).max(seq.len() as isize);
+            }
+        }
+        let mut start = Vec::with_capacity(max_step + 2);
+        start.push(0);
+        for w in &width {
+            start.push(start.last().unwrap() + w);
+        }
+        // Steps past the last gate (e.g. a group's end) shift with it
+        let map_step = |step: isize| match start.get(step.max(0) as usize) {
+            Some(&s) => s,
+            None => step + start[max_step + 1] - (max_step as isize + 1),
+        };
+
+        let mut nodes: Vec<&DAGNode> = self.nodes.values().collect();
+        nodes.sort_by_key(|n| (n.step, n.seq));
+        let mut out = self.clone();
+        out.nodes.clear();
+        for node in nodes {
+            let step = map_step(node.step);
+            match rewrite(node) {
+                Some(seq) => {
+                    // The sequence shares the gate's duration; noise and label
+                    // move to its last gate so they still act after the whole gate
+                    let (len, last) = (seq.len(), seq.len() - 1);
+                    for (i, (gate, angle)) in seq.into_iter().enumerate() {
+                        out.add_node(DAGNode {
+                            type_name: gate.to_string(),
+                            target: node.target,
+                            step: step + i as isize,
+                            params: if gate == "RZ" { vec![angle] } else { vec![] },
+                            classical_control: node.classical_control,
+                            comment: if i == 0 { node.comment.clone() } else { None },
+                            duration: node.duration.map(|d| d / len as f64),
+                            noise: if i == last { node.noise.clone() } else { None },
+                            label: if i == last { node.label.clone() } else { None },
+                            ..Default::default()
+                        });
+                    }
+                }
+                None => {
+                    let mut kept = node.clone();
+                    kept.id.clear();
+                    kept.step = step;
+                    out.add_node(kept);
+                }
+            }
+        }
+        for (s, e, _) in &mut out.groups {
+            *s = map_step(*s);
+            *e = map_step(*e + 1) - 1;
+        }
+        out.rebuild_dependencies();
+        out
+    }
+
+    /// Delete every identity gate. Returns how many were removed.
+    pub fn remove_identities(&mut self) -> usize {
+        let ids: Vec<String> = self
+            .nodes
+            .values()
+            .filter(|n| n.type_name == "I")
+            .map(|n| n.id.clone())
+            .collect();
+        for id in &ids {
+            self.remove_node(id);
+        }
+        ids.len()
+    }
+
+    /// Delete adjacent pairs of gates that cancel: two H, X, Y, Z, CX, CZ,
+    /// CH, SWAP or CCX on the same qubits, or S, T or SX next to its
+    /// inverse. Each qubit's gates are walked in step order, and pairs that
+    /// a removal brings together are caught too. Returns how many pairs
+    /// were removed.
+    pub fn simplify_cancellations(&mut self) -> usize {
+        let mut removed = 0;
+        while let Some((first, second)) = self.find_cancelling_pair() {
+            self.remove_node(&first);
+            self.remove_node(&second);
+            removed += 1;
+        }
+        if removed > 0 {
+            self.rebuild_dependencies();
+        }
+        removed
+    }
+
+    fn find_cancelling_pair(&self) -> Option<(String, String)> {
+        let mut order: Vec<&DAGNode> = self.nodes.values().collect();
+        order.sort_by_key(|n| (n.step, n.seq));
+        for qubit in 0..self.num_qubits {
+            let wire: Vec<&DAGNode> = order
+                .iter()
+                .copied()
+                .filter(|n| n.references(qubit))
+                .collect();
+            for pair in wire.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                if !cancels(a, b) {
+                    continue;
+                }
+                // A multi-qubit pair must also be adjacent on its other wires.
+                let qubits = a.qubits();
+                let mut between = order
+                    .iter()
+                    .skip_while(|n| n.id != a.id)
+                    .skip(1)
+                    .take_while(|n| n.id != b.id);
+                if between.all(|n| !qubits.iter().any(|&q| n.references(q))) {
+                    return Some((a.id.clone(), b.id.clone()));
+                }
+            }
+        }
+        None
+    }
+
+    /// Parse `qasm` and place its gates after the current circuit, starting
+    /// at `max_step() + 1`. Qubit and classical-bit counts become the larger
+    /// of the two; bits are not offset, since measurements here always write
+    /// `c[q]` for qubit `q`. Nothing is changed if `qasm` has errors.
+    pub fn append_qasm(&mut self, qasm: &str) -> Result<(), String> {
+        let mut other = CircuitDAG::new();
+        let errors = other.parse_qasm(qasm);
+        if let Some((line, msg)) = errors.first() {
+            return Err(format!("line {}: {msg}", line + 1));
+        }
+
+        let offset = if self.nodes.is_empty() {
+            0
+        } else {
+            self.max_step() + 1
+        };
+        self.num_qubits = self.num_qubits.max(other.num_qubits);
+        self.num_cbits = self.num_cbits.max(other.num_cbits);
+        for q in other.ancillas {
+            if !self.ancillas.contains(&q) {
+                self.ancillas.push(q);
+            }
+        }
+        for (q, label) in other.qubit_labels {
+            self.qubit_labels.entry(q).or_insert(label);
+        }
+
+        let mut nodes: Vec<DAGNode> = other.nodes.into_values().collect();
+        nodes.sort_by_key(|n| (n.step, n.target));
+        // A trailing comment of the current circuit now precedes the appended gates
+        if let Some(first) = nodes.first_mut()
+            && let Some(trailing) = self.trailing_comment.take()
+        {
+            first.comment = Some(match first.comment.take() {
+                Some(c) => format!("{trailing}\n{c}"),
+                None => trailing,
+            });
+        }
+        if other.trailing_comment.is_some() {
+            self.trailing_comment = other.trailing_comment;
+        }
+        for mut node in nodes {
+            node.id.clear();
+            node.dependencies.clear();
+            node.step += offset;
+            self.add_node(node);
+        }
+        for (start, end, label) in other.groups {
+            self.add_group(start + offset, end + offset, &label);
+        }
+        self.rebuild_dependencies();
+        Ok(())
+    }
+
+    // ── QASM Parsing ──────────────────────────────────────────────────────────
+
+    pub fn parse_qasm(&mut self, qasm: &str) -> Vec<(usize, String)> {
+        self.nodes.clear();
+        self.root_nodes.clear();
+        self.ancillas.clear();
+        self.qubit_labels.clear();
+        self.groups.clear();
+        self.trailing_comment = None;
+        self.openqasm3 = false;
+        self.warnings.clear();
+        let mut errors = vec![];
+        // Size from `qreg`; indices past it still grow the circuit, with a warning
+        let mut declared_qubits: Option<usize> = None;
+        // `reset` of a whole register names it as declared
+        let mut qreg_name = "q".to_string();
+        // Ordinary comments waiting for the next node to attach to
+        let mut pending_comment: Vec<String> = Vec::new();
+
+        let lines: Vec<&str> = qasm.lines().collect();
+        let mut creg_map: HashMap<String, usize> = HashMap::new();
+        let mut creg_offset: usize = 0;
+
+        let resolve_cbit =
+            |reg_name: &str, bit_idx: &str, creg_map: &HashMap<String, usize>| -> usize {
+                if let Some(&start) = creg_map.get(reg_name) {
+                    if !bit_idx.is_empty() {
+                        let offset: usize = bit_idx.parse().unwrap_or(0);
+                        return start + offset;
+                    }
+                    return start;
+                }
+                // fallback: try to parse c[N] style
+                if reg_name.starts_with('c')
+                    && let Ok(idx) = reg_name[1..].parse::<usize>()
+                {
+                    return idx;
+                }
+                0
+            };
+
+        let mut last_gate_on_qubit: HashMap<usize, String> = HashMap::new();
+        let mut current_step_qubits: HashMap<usize, bool> = HashMap::new();
+        let mut current_step: isize = 0;
+        // Last step that measured into or was conditioned on each classical bit
+        let mut cbit_step: HashMap<usize, isize> = HashMap::new();
+        let mut last_cbit_writer: HashMap<usize, String> = HashMap::new();
+
+        let get_qubits_used = |node: &DAGNode| -> Vec<usize> {
+            let mut qs = vec![];
+            if node.target >= 0 {
+                qs.push(node.target as usize);
+            }
+            if node.control >= 0 {
+                qs.push(node.control as usize);
+            }
+            for &c in &node.controls {
+                qs.push(c);
+            }
+            if node.measure_source >= 0 {
+                qs.push(node.measure_source as usize);
+            }
+            qs
+        };
+        let get_written_cbit = |node: &DAGNode| -> Option<usize> {
+            let written = if node.type_name == "MEASURE" {
+                node.target
+            } else {
+                node.measure_source
+            };
+            (written >= 0).then_some(written as usize)
+        };
+        let get_cbits_used = |node: &DAGNode| -> Vec<usize> {
+            let read = (node.classical_control >= 0).then_some(node.classical_control as usize);
+            get_written_cbit(node).into_iter().chain(read).collect()
+        };
+
+        let mut i = 0;
+        while i < lines.len() {
+            let line_idx = i;
+            let line = lines[i].trim();
+            i += 1;
+
+            if line.is_empty() {
+                continue;
+            }
+
+            // Comments / noise / ancilla markers
+            if let Some(text) = line.strip_prefix("//") {
+                if let Some(caps) = ancilla_re().captures(line) {
+                    let q: usize = caps[1].parse().unwrap_or(0);
+                    if !self.ancillas.contains(&q) {
+                        self.ancillas.push(q);
+                    }
+                } else if let Some(caps) = qubit_label_re().captures(line) {
+                    let q: usize = caps[1].parse().unwrap_or(0);
+                    self.set_qubit_label(q, &caps[2]);
+                } else if let Some(caps) = group_re().captures(line) {
+                    let start: isize = caps[1].parse().unwrap_or(0);
+                    let end: isize = caps[2].parse().unwrap_or(0);
+                    self.add_group(start, end, caps[3].trim());
+                } else if let Some(caps) = noise_re().captures(line) {
+                    let target: usize = caps[2].parse().unwrap_or(0);
+                    let qubits_used = vec![target];
+                    self.warnings
+                        .extend(range_warning(line_idx, &qubits_used, declared_qubits));
+                    for &q in &qubits_used {
+                        if *current_step_qubits.get(&q).unwrap_or(&false) {
+                            current_step += 1;
+                            current_step_qubits.clear();
+                            break;
+                        }
+                    }
+                    for &q in &qubits_used {
+                        current_step_qubits.insert(q, true);
+                    }
+                    let noise_type = caps[1].to_string();
+                    let params = if let Some(m) = caps.get(3) {
+                        parse_param_expr(m.as_str())
+                            .map(|v| vec![v])
+                            .unwrap_or_default()
+                    } else {
+                        vec![]
+                    };
+                    let mut node = DAGNode {
+                        type_name: "NOISE".to_string(),
+                        target: target as isize,
+                        step: current_step,
+                        params,
+                        is_noise: true,
+                        noise_type,
+                        comment: take_comment(&mut pending_comment),
+                        ..Default::default()
+                    };
+                    if let Some(last_id) = last_gate_on_qubit.get(&target) {
+                        node.dependencies.push(last_id.clone());
+                    }
+                    node.id = self.generate_node_id("NOISE", target as isize, current_step);
+                    let node_id = node.id.clone();
+                    self.add_node(node);
+                    last_gate_on_qubit.insert(target, node_id);
+                    current_step += 1;
+                    current_step_qubits.clear();
+                } else {
+                    pending_comment.push(text.trim().to_string());
+                }
+                continue;
+            }
+
+            if let Some(version) = line.strip_prefix("OPENQASM") {
+                self.openqasm3 = version.trim_start().starts_with('3');
+                continue;
+            }
+            if line.starts_with("include") {
+                continue;
+            }
+
+            if line.starts_with("qreg") {
+                if let Some(caps) = qreg_re().captures(line) {
+                    let n: usize = caps[2].parse().unwrap_or(0);
                     self.num_qubits = n;
+                    declared_qubits = Some(n);
+                    qreg_name = caps[1].to_string();
                 } else {
                     errors.push((line_idx, format!("Invalid qreg declaration: {}", line)));
                 }
@@ -768,70 +1804,150 @@ impl CircuitDAG {
                     let reg_size: usize = caps[2].parse().unwrap_or(0);
                     creg_map.insert(reg_name, creg_offset);
                     creg_offset += reg_size;
+                    self.num_cbits = self.num_cbits.max(creg_offset);
                 } else {
                     errors.push((line_idx, format!("Invalid creg declaration: {}", line)));
                 }
                 continue;
             }
 
-            // Parse gate line
-            let node_opt = parse_gate_line(line, &lines, &mut i, &creg_map, &resolve_cbit);
-
-            if let Some(mut node) = node_opt {
-                let qubits_used = get_qubits_used(&node);
-
-                // Barriers always start a new step
-                if node.type_name == "BARRIER" {
-                    if !current_step_qubits.is_empty() {
-                        current_step += 1;
-                        current_step_qubits.clear();
+            // Parse gate line, less any noise attached to it
+            let (line, noise) = match inline_noise_re().captures(line) {
+                Some(caps) => {
+                    let p = caps
+                        .get(3)
+                        .and_then(|m| parse_param_expr(m.as_str()))
+                        .unwrap_or(DEFAULT_NOISE_PARAM);
+                    (
+                        caps.get(1).map_or(line, |m| m.as_str()),
+                        Some((caps[2].to_string(), p)),
+                    )
+                }
+                None => (line, None),
+            };
+            let (line, label) = match barrier_label_re().captures(line) {
+                Some(caps) => (
+                    caps.get(1).map_or(line, |m| m.as_str()),
+                    Some(caps[2].to_string()).filter(|l| !l.is_empty()),
+                ),
+                None => (line, None),
+            };
+            // `reset q;` resets every qubit of the register
+            let parsed = if reset_all_re()
+                .captures(line)
+                .is_some_and(|caps| caps[1] == qreg_name)
+            {
+                Some(
+                    (0..self.num_qubits)
+                        .map(|q| DAGNode {
+                            type_name: "RESET".to_string(),
+                            target: q as isize,
+                            is_reset: true,
+                            ..Default::default()
+                        })
+                        .collect(),
+                )
+            } else if ctrl_modifier_re().is_match(line) {
+                match parse_ctrl_modifier(line) {
+                    Ok(nodes) => Some(nodes),
+                    Err(msg) => {
+                        errors.push((line_idx, msg));
+                        continue;
                     }
-                    node.step = current_step;
-                    current_step += 1;
-                    current_step_qubits.clear();
-                } else {
-                    let conflict = qubits_used
-                        .iter()
-                        .any(|q| *current_step_qubits.get(q).unwrap_or(&false));
+                }
+            } else {
+                parse_gate_line(line, &lines, &mut i, &creg_map, &resolve_cbit).map(|n| vec![n])
+            };
 
-                    // Multi-qubit gates always start a new step
-                    if node.control >= 0 || !node.controls.is_empty() || node.measure_source >= 0 {
+            if let Some(parsed) = parsed {
+                for mut node in parsed {
+                    node.noise = noise.clone();
+                    node.label = label.clone();
+                    // Comments the parser stepped over follow those before the line
+                    pending_comment.extend(node.comment.take());
+                    node.comment = take_comment(&mut pending_comment);
+                    let qubits_used = get_qubits_used(&node);
+                    self.warnings
+                        .extend(range_warning(line_idx, &qubits_used, declared_qubits));
+
+                    // Barriers always start a new step
+                    if node.type_name == "BARRIER" {
                         if !current_step_qubits.is_empty() {
                             current_step += 1;
                             current_step_qubits.clear();
                         }
-                    } else if conflict {
+                        node.step = current_step;
                         current_step += 1;
                         current_step_qubits.clear();
-                    }
+                    } else {
+                        // A multi-qubit gate's connector crosses every wire between
+                        // its outermost qubits, so it claims that whole span; gates
+                        // on other wires share its step
+                        let lo = qubits_used.iter().copied().min().unwrap_or(0);
+                        let hi = qubits_used.iter().copied().max().unwrap_or(0);
+                        // A gate conditioned on a bit measured this step (or
+                        // the reverse) has to wait for the next one
+                        let cbits_used = get_cbits_used(&node);
+                        let conflict = (lo..=hi)
+                            .any(|q| *current_step_qubits.get(&q).unwrap_or(&false))
+                            || cbits_used
+                                .iter()
+                                .any(|c| cbit_step.get(c) == Some(&current_step));
+                        if conflict {
+                            current_step += 1;
+                            current_step_qubits.clear();
+                        }
 
-                    node.step = current_step;
-                    for &q in &qubits_used {
-                        current_step_qubits.insert(q, true);
+                        node.step = current_step;
+                        for q in lo..=hi {
+                            current_step_qubits.insert(q, true);
+                        }
+                        for c in cbits_used {
+                            cbit_step.insert(c, current_step);
+                        }
                     }
-                }
 
-                // Establish dependencies
-                let mut dep_set: HashMap<String, bool> = HashMap::new();
-                for &qubit in &qubits_used {
-                    if let Some(last_id) = last_gate_on_qubit.get(&qubit) {
-                        dep_set.insert(last_id.clone(), true);
+                    // Establish dependencies
+                    let mut dep_set: HashMap<String, bool> = HashMap::new();
+                    for &qubit in &qubits_used {
+                        if let Some(last_id) = last_gate_on_qubit.get(&qubit) {
+                            dep_set.insert(last_id.clone(), true);
+                        }
+                    }
+                    if node.classical_control >= 0
+                        && let Some(writer) =
+                            last_cbit_writer.get(&(node.classical_control as usize))
+                    {
+                        dep_set.insert(writer.clone(), true);
+                    }
+                    for dep_id in dep_set.into_keys() {
+                        node.dependencies.push(dep_id);
                     }
-                }
-                for dep_id in dep_set.into_keys() {
-                    node.dependencies.push(dep_id);
-                }
 
-                node.id = Self::generate_node_id(&node.type_name, node.target, node.step);
-                let node_id = node.id.clone();
-                for &qubit in &qubits_used {
-                    last_gate_on_qubit.insert(qubit, node_id.clone());
+                    node.id = self.generate_node_id(&node.type_name, node.target, node.step);
+                    let node_id = node.id.clone();
+                    for &qubit in &qubits_used {
+                        last_gate_on_qubit.insert(qubit, node_id.clone());
+                    }
+                    if let Some(c) = get_written_cbit(&node) {
+                        last_cbit_writer.insert(c, node_id.clone());
+                    }
+                    self.add_node(node);
                 }
-                self.add_node(node);
             } else {
                 errors.push((line_idx, format!("Unrecognized QASM line: {}", line)));
             }
         }
+        self.trailing_comment = take_comment(&mut pending_comment);
+
+        // A barrier listing every qubit is the full-width barrier `to_qasm`
+        // writes, and keeps spanning the register as qubits are added
+        let num_qubits = self.num_qubits;
+        for node in self.nodes.values_mut() {
+            if node.type_name == "BARRIER" && node.controls.len() == num_qubits {
+                node.controls.clear();
+            }
+        }
 
         errors
     }
@@ -841,13 +1957,236 @@ impl CircuitDAG {
     }
 }
 
+/// Rows a node occupies in its step. A multi-qubit gate's wire is drawn
+/// across every row between its outermost qubits.
+fn node_span(node: &DAGNode) -> Vec<usize> {
+    let mut qubits: Vec<usize> = Vec::new();
+    for q in [node.target, node.control, node.measure_source] {
+        if q >= 0 {
+            qubits.push(q as usize);
+        }
+    }
+    qubits.extend_from_slice(&node.controls);
+    match (qubits.iter().min(), qubits.iter().max()) {
+        (Some(&lo), Some(&hi)) if qubits.len() > 1 => (lo..=hi).collect(),
+        _ => qubits,
+    }
+}
+
+/// Gates `transpile_to_basis` can rewrite, as (gate, RZ angle) pairs in time
+/// order over {RZ, SX}, equal to `node` up to global phase.
+fn basis_decomposition(node: &DAGNode) -> Option<Vec<(&'static str, f64)>> {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+    if node.control >= 0 || !node.controls.is_empty() || node.measure_source >= 0 || node.is_noise {
+        return None;
+    }
+    let sign = if node.is_dagger { -1.0 } else { 1.0 };
+    Some(match node.type_name.as_str() {
+        "H" => vec![("RZ", FRAC_PI_2), ("SX", 0.0), ("RZ", FRAC_PI_2)],
+        "X" => vec![("SX", 0.0), ("SX", 0.0)],
+        // Y = iXZ
+        "Y" => vec![("RZ", PI), ("SX", 0.0), ("SX", 0.0)],
+        "Z" => vec![("RZ", PI)],
+        "S" => vec![("RZ", sign * FRAC_PI_2)],
+        "T" => vec![("RZ", sign * FRAC_PI_4)],
+        // SX† = Z·SX·Z up to phase
+        "SX" if node.is_dagger => vec![("RZ", PI), ("SX", 0.0), ("RZ", PI)],
+        "P" | "U1" => vec![("RZ", node.params.first().copied().unwrap_or(0.0))],
+        _ => return None,
+    })
+}
+
+/// Gate name with the dagger flag folded into S, T and SX.
+fn op_name(node: &DAGNode) -> &str {
+    match node.type_name.as_str() {
+        "S" if node.is_dagger => "SDG",
+        "T" if node.is_dagger => "TDG",
+        "SX" if node.is_dagger => "SXDG",
+        name => name,
+    }
+}
+
+/// Whether `b`, directly after `a` on its qubits, undoes it. Parameterised,
+/// classically controlled and noisy gates are left alone.
+fn cancels(a: &DAGNode, b: &DAGNode) -> bool {
+    let plain = |n: &DAGNode| {
+        n.params.is_empty() && n.classical_control < 0 && n.noise.is_none() && !n.is_noise
+    };
+    if !plain(a) || !plain(b) {
+        return false;
+    }
+    let inverse = match (op_name(a), op_name(b)) {
+        (x, y) if x == y => matches!(
+            x,
+            "H" | "X" | "Y" | "Z" | "CX" | "CZ" | "CH" | "SWAP" | "CCX"
+        ),
+        pair => matches!(
+            pair,
+            ("S", "SDG")
+                | ("SDG", "S")
+                | ("T", "TDG")
+                | ("TDG", "T")
+                | ("SX", "SXDG")
+                | ("SXDG", "SX")
+        ),
+    };
+    let sorted = |mut qubits: Vec<usize>| {
+        qubits.sort_unstable();
+        qubits
+    };
+    // CZ and SWAP act the same whichever qubit is the target.
+    let same_target = a.target == b.target || matches!(op_name(a), "CZ" | "SWAP");
+    inverse && same_target && sorted(a.qubits()) == sorted(b.qubits())
+}
+
 // ── QASM node writer ──────────────────────────────────────────────────────────
 
-fn write_node_qasm(node: &DAGNode, num_qubits: usize) -> String {
-    let mut s = String::new();
+/// Warn when a line uses a qubit index past the declared `qreg` size.
+fn range_warning(line: usize, qubits: &[usize], declared: Option<usize>) -> Option<ParseWarning> {
+    let n = declared?;
+    let q = qubits.iter().copied().filter(|&q| q >= n).max()?;
+    Some(ParseWarning {
+        line,
+        message: format!("q[{q}] is outside qreg q[{n}]"),
+    })
+}
+
+/// Join collected comment lines into one comment, leaving the buffer empty.
+fn take_comment(lines: &mut Vec<String>) -> Option<String> {
+    if lines.is_empty() {
+        None
+    } else {
+        Some(std::mem::take(lines).join("\n"))
+    }
+}
+
+fn write_comment(comment: &Option<String>) -> String {
+    comment
+        .iter()
+        .flat_map(|c| c.lines())
+        .map(|line| format!("// {line}\n"))
+        .collect()
+}
+
+fn measure_line(step: isize, qubits: &mut Vec<isize>) -> (isize, String) {
+    qubits.sort();
+    let list: Vec<String> = qubits.drain(..).map(|q| format!("q{q}")).collect();
+    (step, format!("Step {step}: Measure {}", list.join(", ")))
+}
+
+/// One sentence for a single node, without the step prefix.
+fn describe_node(node: &DAGNode) -> String {
+    let t = node.target;
+    let name = if node.is_dagger {
+        gate_name(&format!("{}DG", node.type_name))
+    } else {
+        gate_name(&node.type_name)
+    }
+    .map(str::to_string)
+    .unwrap_or_else(|| node.type_name.clone());
+    let params = if node.params.is_empty() {
+        String::new()
+    } else {
+        let p: Vec<String> = node.params.iter().map(|&v| format_param(v)).collect();
+        format!("({})", p.join(", "))
+    };
+
+    let mut text = match node.type_name.as_str() {
+        "BARRIER" => "Barrier".to_string(),
+        "RESET" => format!("Reset q{t}"),
+        "MEASURE" => format!("Measure q{t}"),
+        "NOISE" => {
+            let mut kind = node.noise_type.replace('_', " ");
+            if let Some(first) = kind.get_mut(0..1) {
+                first.make_ascii_uppercase();
+            }
+            let p = node.params.first().copied().unwrap_or(0.01);
+            format!("{kind} noise on q{t} (p={p})")
+        }
+        "MCX" => format!("{name} q{}→q{t}", node.measure_source),
+        "SWAP" | "MS" => format!("{name}{params} q{}↔q{t}", node.control),
+        _ if !node.controls.is_empty() => {
+            let ctrls: Vec<String> = node.controls.iter().map(|c| format!("q{c}")).collect();
+            format!("{name}{params} {}→q{t}", ctrls.join(","))
+        }
+        _ if node.control >= 0 => format!("{name}{params} q{}→q{t}", node.control),
+        _ => format!("{name}{params} on q{t}"),
+    };
+    if node.classical_control >= 0 {
+        text.push_str(&format!(" if c[{}]", node.classical_control));
+    }
+    if let Some((kind, p)) = &node.noise {
+        text.push_str(&format!(" with {} noise (p={p})", kind.replace('_', " ")));
+    }
+    text
+}
+
+/// A quantum-controlled gate as `ctrl @ … base q[c…], q[t];`, or `None`
+/// for nodes that are not controlled gates.
+fn write_ctrl_modifier_qasm(node: &DAGNode) -> Option<String> {
+    if node.classical_control >= 0 || node.measure_source >= 0 || node.is_noise {
+        return None;
+    }
+    let controls: Vec<usize> = if !node.controls.is_empty() {
+        node.controls.clone()
+    } else if node.control >= 0 && !matches!(node.type_name.as_str(), "SWAP" | "MS" | "RXX" | "RZZ")
+    {
+        vec![node.control as usize]
+    } else {
+        return None;
+    };
+    let base = match node.type_name.as_str() {
+        "CCX" | "TOFFOLI" => "x".to_string(),
+        t => t.strip_prefix('C').unwrap_or(t).to_lowercase(),
+    };
+    let params = if node.params.is_empty() {
+        String::new()
+    } else {
+        let ps: Vec<String> = node.params.iter().map(|&p| format_param(p)).collect();
+        format!("({})", ps.join(", "))
+    };
+    let qubits: Vec<String> = controls
+        .iter()
+        .chain(std::iter::once(&(node.target as usize)))
+        .map(|q| format!("q[{q}]"))
+        .collect();
+    Some(format!(
+        "{}{base}{params} {};\n",
+        "ctrl @ ".repeat(controls.len()),
+        qubits.join(", ")
+    ))
+}
+
+/// Append the noise attached to a gate to the end of its line.
+fn push_inline_noise(text: &mut String, node: &DAGNode) {
+    if let Some((kind, p)) = &node.noise
+        && text.ends_with(";\n")
+    {
+        text.pop();
+        text.push_str(&format!(" // noise {kind} {}\n", format_param(*p)));
+    }
+}
+
+/// Append a barrier's caption to the end of its line.
+fn push_barrier_label(text: &mut String, node: &DAGNode) {
+    if let Some(label) = &node.label
+        && node.type_name == "BARRIER"
+        && text.ends_with(";\n")
+    {
+        text.pop();
+        text.push_str(&format!(" // label: {label}\n"));
+    }
+}
+
+fn write_node_qasm(node: &DAGNode, num_qubits: usize, openqasm3: bool) -> String {
+    let mut s = write_comment(&node.comment);
 
     if node.type_name == "BARRIER" {
-        let qubits: Vec<String> = (0..num_qubits).map(|q| format!("q[{q}]")).collect();
+        let qubits: Vec<String> = if node.controls.is_empty() {
+            (0..num_qubits).map(|q| format!("q[{q}]")).collect()
+        } else {
+            node.controls.iter().map(|q| format!("q[{q}]")).collect()
+        };
         s.push_str(&format!("barrier {};\n", qubits.join(", ")));
     } else if node.is_noise {
         if !node.params.is_empty() {
@@ -866,43 +2205,17 @@ fn write_node_qasm(node: &DAGNode, num_qubits: usize) -> String {
     } else if node.is_reset {
         s.push_str(&format!("reset q[{}];\n", node.target));
     } else if node.classical_control >= 0 {
-        if node.control >= 0 {
-            s.push_str(&format!(
-                "if (c[{}]==1) cx q[{}], q[{}];\n",
-                node.classical_control, node.control, node.target
-            ));
-        } else if !node.controls.is_empty() {
-            let gate_type = node.type_name.to_lowercase();
-            let ctrl_strs: Vec<String> = node.controls.iter().map(|c| format!("q[{c}]")).collect();
-            s.push_str(&format!(
-                "if (c[{}]==1) {} {}, q[{}];\n",
-                node.classical_control,
-                gate_type,
-                ctrl_strs.join(", "),
-                node.target
-            ));
-        } else {
-            let gate_type = node.type_name.to_lowercase();
-            if !node.params.is_empty() {
-                s.push_str(&format!(
-                    "if (c[{}]==1) {}({}) q[{}];\n",
-                    node.classical_control,
-                    gate_type,
-                    format_param(node.params[0]),
-                    node.target
-                ));
-            } else if node.is_dagger {
-                s.push_str(&format!(
-                    "if (c[{}]==1) {}dg q[{}];\n",
-                    node.classical_control, gate_type, node.target
-                ));
-            } else {
-                s.push_str(&format!(
-                    "if (c[{}]==1) {} q[{}];\n",
-                    node.classical_control, gate_type, node.target
-                ));
-            }
-        }
+        // The conditioned body is the gate as it is written unconditioned
+        let body = DAGNode {
+            classical_control: -1,
+            comment: None,
+            ..node.clone()
+        };
+        s.push_str(&format!(
+            "if (c[{}]==1) {}",
+            node.classical_control,
+            write_node_qasm(&body, num_qubits, openqasm3)
+        ));
     } else if node.measure_source >= 0 {
         s.push_str(&format!(
             "measure q[{}] -> c[{}];\n",
@@ -919,21 +2232,29 @@ fn write_node_qasm(node: &DAGNode, num_qubits: usize) -> String {
         ));
     } else if !node.controls.is_empty() {
         match node.type_name.as_str() {
-            "CCX" | "TOFFOLI" if node.controls.len() >= 2 => {
+            "CCX" | "TOFFOLI" if node.controls.len() == 2 => {
                 s.push_str(&format!(
                     "ccx q[{}], q[{}], q[{}];\n",
                     node.controls[0], node.controls[1], node.target
                 ));
             }
-            _ => {
-                let gate_type = node.type_name.to_lowercase();
-                let ctrl_strs: Vec<String> =
-                    node.controls.iter().map(|c| format!("q[{c}]")).collect();
+            // qelib1 has no wider gate, so anything else keeps every control
+            // as a `ctrl @` modifier on its single-qubit base
+            type_name => {
+                let base = match type_name {
+                    "CCX" | "TOFFOLI" => "x".to_string(),
+                    t => t.strip_prefix('C').unwrap_or(t).to_lowercase(),
+                };
+                let qubits: Vec<String> = node
+                    .controls
+                    .iter()
+                    .chain([&(node.target as usize)])
+                    .map(|q| format!("q[{q}]"))
+                    .collect();
                 s.push_str(&format!(
-                    "{} {}, q[{}];\n",
-                    gate_type,
-                    ctrl_strs.join(", "),
-                    node.target
+                    "{}{base} {};\n",
+                    "ctrl @ ".repeat(node.controls.len()),
+                    qubits.join(", ")
                 ));
             }
         }
@@ -943,30 +2264,25 @@ fn write_node_qasm(node: &DAGNode, num_qubits: usize) -> String {
             "CZ" => s.push_str(&format!("cz q[{}], q[{}];\n", node.control, node.target)),
             "SWAP" => s.push_str(&format!("swap q[{}], q[{}];\n", node.control, node.target)),
             "CH" => s.push_str(&format!("ch q[{}], q[{}];\n", node.control, node.target)),
-            "CRX" if !node.params.is_empty() => s.push_str(&format!(
-                "crx({}) q[{}], q[{}];\n",
-                format_param(node.params[0]),
-                node.control,
-                node.target
-            )),
-            "CRY" if !node.params.is_empty() => s.push_str(&format!(
-                "cry({}) q[{}], q[{}];\n",
-                format_param(node.params[0]),
-                node.control,
-                node.target
-            )),
-            "CRZ" if !node.params.is_empty() => s.push_str(&format!(
-                "crz({}) q[{}], q[{}];\n",
-                format_param(node.params[0]),
-                node.control,
-                node.target
-            )),
-            "CP" | "CU1" if !node.params.is_empty() => s.push_str(&format!(
-                "cu1({}) q[{}], q[{}];\n",
-                format_param(node.params[0]),
+            // A missing angle is written as 0 so the gate never degrades to a cx
+            "CRX" | "CRY" | "CRZ" | "MS" | "CP" | "CU1" | "RXX" | "RZZ" => s.push_str(&format!(
+                "{}({}) q[{}], q[{}];\n",
+                node.type_name.to_lowercase(),
+                format_param(node.params.first().copied().unwrap_or(0.0)),
                 node.control,
                 node.target
             )),
+            "CU3" => {
+                let param = |k: usize| format_param(node.params.get(k).copied().unwrap_or(0.0));
+                s.push_str(&format!(
+                    "cu3({}, {}, {}) q[{}], q[{}];\n",
+                    param(0),
+                    param(1),
+                    param(2),
+                    node.control,
+                    node.target
+                ));
+            }
             _ => s.push_str(&format!("cx q[{}], q[{}];\n", node.control, node.target)),
         }
     } else {
@@ -982,6 +2298,13 @@ fn write_node_qasm(node: &DAGNode, num_qubits: usize) -> String {
                     ));
                 }
             }
+            "i" => s.push_str(&format!("id q[{}];\n", node.target)),
+            // A delay without a length idles for zero time
+            "u0" => s.push_str(&format!(
+                "u0({}) q[{}];\n",
+                format_param(node.params.first().copied().unwrap_or(0.0)),
+                node.target
+            )),
             "u2" => {
                 if node.params.len() == 2 {
                     s.push_str(&format!(
@@ -992,10 +2315,12 @@ fn write_node_qasm(node: &DAGNode, num_qubits: usize) -> String {
                     ));
                 }
             }
-            "u3" => {
+            // `U` is OpenQASM 3's built-in U3; 2.0 only knows `u3`
+            "u3" | "u" => {
                 if node.params.len() == 3 {
                     s.push_str(&format!(
-                        "u3({}, {}, {}) q[{}];\n",
+                        "{}({}, {}, {}) q[{}];\n",
+                        if openqasm3 { "U" } else { "u3" },
                         format_param(node.params[0]),
                         format_param(node.params[1]),
                         format_param(node.params[2]),
@@ -1003,12 +2328,8 @@ fn write_node_qasm(node: &DAGNode, num_qubits: usize) -> String {
                     ));
                 }
             }
-            "s" | "t" | "sx" | "sy" | "sz" => {
-                if node.is_dagger {
-                    s.push_str(&format!("{}dg q[{}];\n", gate_type, node.target));
-                } else {
-                    s.push_str(&format!("{} q[{}];\n", gate_type, node.target));
-                }
+            "s" | "t" | "sx" | "sy" | "sz" if node.is_dagger => {
+                s.push_str(&format!("{}dg q[{}];\n", gate_type, node.target));
             }
             _ => {
                 s.push_str(&format!("{} q[{}];\n", gate_type, node.target));
@@ -1019,15 +2340,219 @@ fn write_node_qasm(node: &DAGNode, num_qubits: usize) -> String {
     s
 }
 
+/// Small seeded generator (SplitMix64) behind `CircuitDAG::random` and
+/// measurement sampling.
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n` (n > 0).
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A nonzero multiple of π/4 in (−π, π].
+    fn quarter_turns(&mut self) -> f64 {
+        let k = [-3, -2, -1, 1, 2, 3, 4][self.below(7)];
+        k as f64 * std::f64::consts::FRAC_PI_4
+    }
+}
+
+/// Statement keywords offered by Tab completion in the QASM editor: the
+/// menu's gate types and the qelib1 table `native_gate_type` reads, sorted.
+fn qasm_gate_keywords() -> &'static [String] {
+    static K: OnceLock<Vec<String>> = OnceLock::new();
+    K.get_or_init(|| {
+        let menu = GATE_MENU
+            .iter()
+            .flat_map(|c| c.items.iter())
+            .map(|item| item.gate_type)
+            // Measurement bases and noise are saved as other statements, the
+            // identity as `id` and measure-controlled X as measure + if
+            .filter(|t| !t.contains('_') && !matches!(*t, "I" | "MCX"))
+            .map(str::to_lowercase);
+        let mut keywords: Vec<String> = menu
+            .chain(QELIB1_GATES.iter().map(|(name, _)| name.to_string()))
+            .collect();
+        keywords.sort();
+        keywords.dedup();
+        keywords
+    })
+}
+
+/// Gate keywords starting with `prefix` (case-insensitive), in alphabetical order.
+pub fn complete_gate_token(prefix: &str) -> Vec<&'static str> {
+    let prefix = prefix.to_lowercase();
+    qasm_gate_keywords()
+        .iter()
+        .map(String::as_str)
+        .filter(|kw| kw.starts_with(&prefix))
+        .collect()
+}
+
 // ── Gate line parser ──────────────────────────────────────────────────────────
 
+/// Parse a command-mode gate such as `rx(pi/2) 0` or `cx 1 0` (controls
+/// first, target last) by rewriting it as a QASM line. The node's step is
+/// left at 0 for the caller to set.
+pub fn parse_command(cmd: &str) -> Result<DAGNode, String> {
+    let cmd = cmd.trim();
+    let caps = command_re()
+        .captures(cmd)
+        .ok_or_else(|| format!("Expected a gate followed by qubit indices, got '{cmd}'"))?;
+    let gate = caps[1].to_lowercase();
+    let qubits: Vec<&str> = caps[2]
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|q| !q.is_empty())
+        .collect();
+    let line = if gate == "measure" && qubits.len() == 1 {
+        format!("measure q[{0}] -> c[{0}];", qubits[0])
+    } else {
+        let operands: Vec<String> = qubits.iter().map(|q| format!("q[{q}]")).collect();
+        format!("{gate} {};", operands.join(", "))
+    };
+    let resolve: &CbitResolver = &|_, bit, _| bit.parse().unwrap_or(0);
+    let mut idx = 0;
+    let node = parse_gate_line(&line, &[], &mut idx, &HashMap::new(), resolve)
+        .ok_or_else(|| format!("Unknown gate: '{cmd}'"))?;
+    // The QASM parser accepts any gate name; commands are limited to the menu's
+    let arity = match node.type_name.as_str() {
+        "CCX" => 3,
+        "CX" | "CZ" | "CH" | "SWAP" | "CRX" | "CRY" | "CRZ" | "MS" | "CP" | "CU1" | "CU3" => 2,
+        _ => 1,
+    };
+    let qubits = node.qubits();
+    if gate_name(&node.type_name).is_none() || matches!(node.type_name.as_str(), "MCX" | "BARRIER")
+    {
+        Err(format!("Unknown gate: '{gate}'"))
+    } else if qubits.len() != arity {
+        Err(format!("{gate} takes {arity} qubit(s)"))
+    } else if (1..arity).any(|i| qubits[i..].contains(&qubits[i - 1])) {
+        Err(format!("{gate} needs distinct qubits"))
+    } else {
+        Ok(node)
+    }
+}
+
+/// Parse a line of `ctrl @` / `negctrl @` modifiers: the leading qubits are
+/// controls and the last is the target. One modifier gives a single-control
+/// node, more a `controls` list. A `negctrl` control fires on |0⟩, so the
+/// node is wrapped in X gates on those controls. Only bases the writer and
+/// simulator both handle are accepted: x, z, h, rx, ry, rz, p, u1 and u3 (or
+/// u) under one control, and x alone under several.
+fn parse_ctrl_modifier(line: &str) -> Result<Vec<DAGNode>, String> {
+    let Some(caps) = ctrl_modifier_re().captures(line) else {
+        return Err(format!("Unrecognized QASM line: {line}"));
+    };
+    let negated: Vec<bool> = caps[1]
+        .split('@')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(|m| m == "negctrl")
+        .collect();
+    let n_ctrl = negated.len();
+    let base = caps[2].to_lowercase();
+    let type_name = match (base.as_str(), n_ctrl) {
+        ("x", 1) => "CX",
+        ("x", _) => "CCX",
+        ("z", 1) => "CZ",
+        ("h", 1) => "CH",
+        ("rx", 1) => "CRX",
+        ("ry", 1) => "CRY",
+        ("rz", 1) => "CRZ",
+        ("p", 1) => "CP",
+        ("u1", 1) => "CU1",
+        ("u3" | "u", 1) => "CU3",
+        (_, 1) => return Err(format!("Unsupported controlled gate: ctrl @ {base}")),
+        _ => return Err(format!("Only x takes more than one control, not {base}")),
+    }
+    .to_string();
+    let params: Vec<f64> = caps
+        .get(3)
+        .map(|m| {
+            m.as_str()
+                .split(',')
+                .filter_map(|s| parse_param_expr(s.trim()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let qubits: Vec<usize> = qubit_operand_re()
+        .captures_iter(&caps[4])
+        .filter_map(|c| c[1].parse().ok())
+        .collect();
+    if qubits.len() != n_ctrl + 1 {
+        return Err(format!(
+            "{base} with {n_ctrl} control(s) takes {} qubits",
+            n_ctrl + 1
+        ));
+    }
+    let target = qubits[n_ctrl] as isize;
+    let node = if n_ctrl == 1 {
+        DAGNode {
+            type_name,
+            target,
+            control: qubits[0] as isize,
+            params,
+            ..Default::default()
+        }
+    } else {
+        DAGNode {
+            type_name,
+            target,
+            controls: qubits[..n_ctrl].to_vec(),
+            params,
+            ..Default::default()
+        }
+    };
+
+    let flips: Vec<DAGNode> = qubits
+        .iter()
+        .zip(&negated)
+        .filter(|&(_, &neg)| neg)
+        .map(|(&q, _)| DAGNode {
+            type_name: "X".to_string(),
+            target: q as isize,
+            ..Default::default()
+        })
+        .collect();
+    let mut nodes = flips.clone();
+    nodes.push(node);
+    nodes.extend(flips);
+    Ok(nodes)
+}
+
+type CbitResolver = dyn Fn(&str, &str, &HashMap<String, usize>) -> usize;
+
 fn parse_gate_line(
     line: &str,
     lines: &[&str],
     idx: &mut usize,
     creg_map: &HashMap<String, usize>,
-    resolve_cbit: &dyn Fn(&str, &str, &HashMap<String, usize>) -> usize,
+    resolve_cbit: &CbitResolver,
 ) -> Option<DAGNode> {
+    // OpenQASM 3 `inv @` modifier: parse the rest and take its inverse
+    if let Some(rest) = line.strip_prefix("inv")
+        && let Some(rest) = rest.trim_start().strip_prefix('@')
+    {
+        let mut node = parse_gate_line(rest.trim_start(), lines, idx, creg_map, resolve_cbit)?;
+        if node.classical_control >= 0 || node.measure_source >= 0 || node.is_reset {
+            return None;
+        }
+        return invert_gate(&node.type_name, &mut node.params, &mut node.is_dagger).then_some(node);
+    }
+
     // Reset
     if let Some(caps) = reset_re().captures(line) {
         let target: usize = caps[1].parse().unwrap_or(0);
@@ -1039,10 +2564,23 @@ fn parse_gate_line(
         });
     }
 
-    // Barrier
-    if barrier_re().is_match(line) {
+    // Barrier: a bare `barrier;` or the whole register spans every wire,
+    // otherwise only the listed qubits
+    if let Some(caps) = barrier_re().captures(line) {
+        let mut qubits: Vec<usize> = caps
+            .get(1)
+            .map(|args| {
+                qubit_operand_re()
+                    .captures_iter(args.as_str())
+                    .filter_map(|c| c[1].parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        qubits.sort_unstable();
+        qubits.dedup();
         return Some(DAGNode {
             type_name: "BARRIER".to_string(),
+            controls: qubits,
             ..Default::default()
         });
     }
@@ -1052,25 +2590,44 @@ fn parse_gate_line(
         let source: usize = caps[1].parse().unwrap_or(0);
         let cbit = resolve_cbit(&caps[2], &caps[3], creg_map);
 
-        // Look ahead for MCX pattern
-        if *idx < lines.len() {
-            let next_line = lines[*idx].trim();
-            if let Some(if_caps) = if_re().captures(next_line) {
-                let cond_bit = resolve_cbit(
-                    &if_caps[1],
-                    if_caps.get(2).map_or("", |m| m.as_str()),
-                    creg_map,
-                );
-                let target: usize = if_caps[5].parse().unwrap_or(0);
-                if cond_bit == cbit {
-                    *idx += 1;
-                    return Some(DAGNode {
-                        type_name: "MCX".to_string(),
-                        target: target as isize,
-                        measure_source: source as isize,
-                        ..Default::default()
-                    });
-                }
+        // Look ahead for MCX pattern: the next statement, past blank lines
+        // and plain comments (which move onto the fused node)
+        let mut next = *idx;
+        let mut skipped: Vec<String> = Vec::new();
+        while let Some(next_line) = lines.get(next).map(|l| l.trim()) {
+            if next_line.is_empty() {
+                next += 1;
+            } else if let Some(text) = next_line.strip_prefix("//")
+                && !ancilla_re().is_match(next_line)
+                && !group_re().is_match(next_line)
+                && !qubit_label_re().is_match(next_line)
+                && !noise_re().is_match(next_line)
+            {
+                skipped.push(text.trim().to_string());
+                next += 1;
+            } else {
+                break;
+            }
+        }
+        if let Some(if_caps) = lines.get(next).and_then(|l| if_re().captures(l.trim())) {
+            let cond_bit = resolve_cbit(
+                &if_caps[1],
+                if_caps.get(2).map_or("", |m| m.as_str()),
+                creg_map,
+            );
+            let target: usize = if_caps[5].parse().unwrap_or(0);
+            // Only an X on another qubit, conditioned on this very bit being
+            // 1, is the measure-controlled X; anything else stays a plain
+            // conditioned gate on its own line
+            if cond_bit == cbit && &if_caps[3] == "1" && &if_caps[4] == "x" && target != source {
+                *idx = next + 1;
+                return Some(DAGNode {
+                    type_name: "MCX".to_string(),
+                    target: target as isize,
+                    measure_source: source as isize,
+                    comment: take_comment(&mut skipped),
+                    ..Default::default()
+                });
             }
         }
 
@@ -1081,32 +2638,26 @@ fn parse_gate_line(
         });
     }
 
-    // Classically-controlled parameterized gate
-    if let Some(caps) = if_param_re().captures(line) {
+    // Classically-controlled gate: the body is parsed like any other line
+    if let Some(caps) = if_prefix_re().captures(line) {
         let cbit = resolve_cbit(&caps[1], caps.get(2).map_or("", |m| m.as_str()), creg_map);
-        let gate_type = caps[4].to_uppercase();
-        let param = parse_param_expr(&caps[5]).unwrap_or(0.0);
-        let target: usize = caps[6].parse().unwrap_or(0);
-        return Some(DAGNode {
-            type_name: gate_type,
-            target: target as isize,
-            params: vec![param],
-            classical_control: cbit as isize,
-            ..Default::default()
-        });
+        let mut node = parse_gate_line(caps[4].trim(), &[], &mut 0, creg_map, resolve_cbit)?;
+        if node.classical_control >= 0
+            || node.measure_source >= 0
+            || node.is_reset
+            || matches!(node.type_name.as_str(), "MEASURE" | "BARRIER")
+        {
+            return None;
+        }
+        node.classical_control = cbit as isize;
+        return Some(node);
     }
 
-    // Classically-controlled gate
-    if let Some(caps) = if_re().captures(line) {
-        let cbit = resolve_cbit(&caps[1], caps.get(2).map_or("", |m| m.as_str()), creg_map);
-        let gate_type = caps[4].to_uppercase();
-        let target: usize = caps[5].parse().unwrap_or(0);
-        return Some(DAGNode {
-            type_name: gate_type,
-            target: target as isize,
-            classical_control: cbit as isize,
-            ..Default::default()
-        });
+    // `ctrl @` modifiers; `negctrl @` needs more than one node, so the
+    // caller goes through `parse_ctrl_modifier` for those
+    if ctrl_modifier_re().is_match(line) {
+        let mut nodes = parse_ctrl_modifier(line).ok()?;
+        return (nodes.len() == 1).then(|| nodes.remove(0));
     }
 
     // Three-qubit gates
@@ -1125,22 +2676,25 @@ fn parse_gate_line(
 
     // Two-qubit parameterized
     if let Some(caps) = two_qubit_param_re().captures(line) {
-        let gate_type = caps[1].to_uppercase();
-        let param = parse_param_expr(&caps[2]).unwrap_or(0.0);
+        let gate_type = native_gate_type(&caps[1]);
+        let params: Vec<f64> = caps[2]
+            .split(',')
+            .filter_map(|s| parse_param_expr(s.trim()))
+            .collect();
         let q1: usize = caps[3].parse().unwrap_or(0);
         let q2: usize = caps[4].parse().unwrap_or(0);
         return Some(DAGNode {
             type_name: gate_type,
             target: q2 as isize,
             control: q1 as isize,
-            params: vec![param],
+            params,
             ..Default::default()
         });
     }
 
     // Two-qubit gate
     if let Some(caps) = two_qubit_re().captures(line) {
-        let gate_type = caps[1].to_uppercase();
+        let gate_type = native_gate_type(&caps[1]);
         let q1: usize = caps[2].parse().unwrap_or(0);
         let q2: usize = caps[3].parse().unwrap_or(0);
         return Some(DAGNode {
@@ -1153,7 +2707,7 @@ fn parse_gate_line(
 
     // Single-qubit parameterized
     if let Some(caps) = single_gate_param_re().captures(line) {
-        let gate_type = caps[1].to_uppercase();
+        let gate_type = native_gate_type(&caps[1]);
         let params_str = caps[2].to_string();
         let target: usize = caps[3].parse().unwrap_or(0);
         let params: Vec<f64> = params_str
@@ -1170,7 +2724,7 @@ fn parse_gate_line(
 
     // Single-qubit gate (including dagger)
     if let Some(caps) = single_gate_re().captures(line) {
-        let mut gate_type = caps[1].to_uppercase();
+        let mut gate_type = native_gate_type(&caps[1]);
         let target: usize = caps[2].parse().unwrap_or(0);
 
         let mut is_dagger = false;
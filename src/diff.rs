@@ -0,0 +1,78 @@
+/// One line of a diff: in both texts, only in the new one, or only in the old.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// Line-by-line diff from `old` to `new` along their longest common
+/// subsequence. Within a change, removed lines come before added ones, so a
+/// gate moved to another line shows as a removal and an addition.
+pub fn line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    // lcs[i][j]: length of the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            out.push(DiffLine::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            out.push(DiffLine::Removed(a[i].to_string()));
+            i += 1;
+        } else {
+            out.push(DiffLine::Added(b[j].to_string()));
+            j += 1;
+        }
+    }
+    out.extend(a[i..].iter().map(|l| DiffLine::Removed(l.to_string())));
+    out.extend(b[j..].iter().map(|l| DiffLine::Added(l.to_string())));
+    out
+}
+
+/// Count of (added, removed) lines in a diff.
+pub fn diff_counts(diff: &[DiffLine]) -> (usize, usize) {
+    diff.iter().fold((0, 0), |(add, del), line| match line {
+        DiffLine::Added(_) => (add + 1, del),
+        DiffLine::Removed(_) => (add, del + 1),
+        DiffLine::Same(_) => (add, del),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_marks_added_removed_and_kept_lines() {
+        let old = "qreg q[2];\nh q[0];\ncx q[0], q[1];\n";
+        let new = "qreg q[2];\nx q[0];\ncx q[0], q[1];\nmeasure q[1] -> c[0];\n";
+        let diff = line_diff(old, new);
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("qreg q[2];".to_string()),
+                DiffLine::Removed("h q[0];".to_string()),
+                DiffLine::Added("x q[0];".to_string()),
+                DiffLine::Same("cx q[0], q[1];".to_string()),
+                DiffLine::Added("measure q[1] -> c[0];".to_string()),
+            ]
+        );
+        assert_eq!(diff_counts(&diff), (2, 1));
+        assert_eq!(diff_counts(&line_diff(old, old)), (0, 0));
+    }
+}
@@ -0,0 +1,214 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+
+/// One row of the help overlay: a key (or key pair) and what it does in a
+/// focus mode.
+#[derive(Clone, Debug)]
+pub struct KeyBinding {
+    pub mode: &'static str,
+    pub keys: &'static str,
+    pub action: &'static str,
+}
+
+impl KeyBinding {
+    /// The key presses `keys` names, to check the table against the key
+    /// handlers. `↑↓`, `jk` and `1-5` stand for every key in them, `Arrows`
+    /// for all four and `Type` for a printable key.
+    pub fn key_events(&self) -> Vec<(KeyCode, KeyModifiers)> {
+        self.keys.split(" / ").flat_map(key_events).collect()
+    }
+}
+
+fn key_events(name: &str) -> Vec<(KeyCode, KeyModifiers)> {
+    let plain = |codes: &[KeyCode]| codes.iter().map(|&c| (c, KeyModifiers::NONE)).collect();
+    match name {
+        "↑↓" => plain(&[KeyCode::Up, KeyCode::Down]),
+        "←→" => plain(&[KeyCode::Left, KeyCode::Right]),
+        "Arrows" => plain(&[KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right]),
+        "Home" => plain(&[KeyCode::Home]),
+        "End" => plain(&[KeyCode::End]),
+        "Enter" => plain(&[KeyCode::Enter]),
+        "Esc" => plain(&[KeyCode::Esc]),
+        "Tab" => plain(&[KeyCode::Tab]),
+        "Space" => plain(&[KeyCode::Char(' ')]),
+        "Bksp" => plain(&[KeyCode::Backspace]),
+        "Del" => plain(&[KeyCode::Delete]),
+        "Type" => plain(&[KeyCode::Char('z')]),
+        _ => {
+            if let Some(key) = name.strip_prefix("Ctrl+") {
+                return key
+                    .chars()
+                    .map(|c| (KeyCode::Char(c.to_ascii_lowercase()), KeyModifiers::CONTROL))
+                    .collect();
+            }
+            let chars: Vec<char> = name.chars().collect();
+            match chars.as_slice() {
+                [from, '-', to] => plain(&(*from..=*to).map(KeyCode::Char).collect::<Vec<_>>()),
+                _ => plain(&chars.into_iter().map(KeyCode::Char).collect::<Vec<_>>()),
+            }
+        }
+    }
+}
+
+const fn bind(mode: &'static str, keys: &'static str, action: &'static str) -> KeyBinding {
+    KeyBinding { mode, keys, action }
+}
+
+/// Every keybinding, grouped by focus mode in the order the help overlay
+/// lists them. Keep in step with the key handlers in `main.rs`.
+pub static KEYBINDINGS: &[KeyBinding] = &[
+    bind("Circuit", "↑↓ / jk", "Move between qubits"),
+    bind("Circuit", "←→ / hl", "Move between steps"),
+    bind("Circuit", "w / b", "Next / previous gate on this qubit"),
+    bind("Circuit", "0", "Jump to step 0"),
+    bind("Circuit", "a", "Add gate menu"),
+    bind(
+        "Circuit",
+        ":",
+        "Command (gate, qft, diffusion, group, random, name, noise, simplify, wrap, summary, phase, csv)",
+    ),
+    bind("Circuit", "e", "Edit the gate at the cursor"),
+    bind("Circuit", "Bksp / Del", "Delete the gate at the cursor"),
+    bind("Circuit", "X", "Select a region of gates"),
+    bind("Circuit", "+ / -", "Add / remove a qubit"),
+    bind("Circuit", "< / >", "Remove / add a classical bit"),
+    bind("Circuit", "S", "Swap the cursor qubit with another wire"),
+    bind("Circuit", "M", "Measure all qubits"),
+    bind(
+        "Circuit",
+        "Enter",
+        "Measure (if nothing is) and sample shots",
+    ),
+    bind("Circuit", "C", "Compact steps"),
+    bind("Circuit", "I", "Strip identity gates"),
+    bind("Circuit", "G", "Random circuit"),
+    bind("Circuit", "T", "Transpile to RZ, SX, CX (again to undo)"),
+    bind("Circuit", "B", "Simulate from a basis input state"),
+    bind("Circuit", "P", "Play the simulation step by step"),
+    bind("Circuit", "F", "Freeze / resume live simulation"),
+    bind("Circuit", ".", "Refresh a frozen simulation"),
+    bind("Circuit", "Space", "Pause / resume playback"),
+    bind("Circuit", "v", "Toggle statevector view"),
+    bind(
+        "Circuit",
+        "V",
+        "Cycle amplitude format (a+bi, polar, magnitude)",
+    ),
+    bind("Circuit", "m", "Toggle unitary matrix view"),
+    bind("Circuit", "o", "Observables panel"),
+    bind("Circuit", "L", "Toggle circuit listing"),
+    bind("Circuit", "i", "Circuit stats"),
+    bind("Circuit", "n", "Check the state's total probability"),
+    bind(
+        "Circuit",
+        "[ / ]",
+        "Lower / raise the probability threshold",
+    ),
+    bind("Circuit", "f", "Toggle simulation frontier shading"),
+    bind("Circuit", "p", "Toggle P₁ annotations"),
+    bind("Circuit", "W", "Toggle wire style"),
+    bind("Circuit", "O", "Draw the highest qubit on top"),
+    bind("Circuit", "r", "Toggle wrapped layout"),
+    bind("Circuit", "N", "Toggle noise simulation"),
+    bind("Circuit", "A", "Append a QASM file"),
+    bind("Circuit", "Ctrl+S", "Save circuit.qasm"),
+    bind("Circuit", "D", "Diff against the last save"),
+    bind("Circuit", "E", "Export circuit.svg"),
+    bind(
+        "Circuit",
+        "R / Esc",
+        "Recover / discard autosave (when offered)",
+    ),
+    bind("Circuit", "Tab", "Switch to the QASM editor"),
+    bind("Circuit", "?", "This help"),
+    bind("Circuit", "q", "Quit"),
+    bind("QASM editor", "Arrows / Home / End", "Move the cursor"),
+    bind(
+        "QASM editor",
+        "Tab",
+        "Complete a gate name, or leave the editor",
+    ),
+    bind("QASM editor", "Ctrl+F", "Reformat"),
+    bind(
+        "Add Gate menu",
+        "↑↓",
+        "Select a gate (wraps; :wrap toggles)",
+    ),
+    bind("Add Gate menu", "←→", "Change category (wraps)"),
+    bind("Add Gate menu", "1-5", "Place a recently used gate"),
+    bind("Add Gate menu", "Tab", "Place on all qubits"),
+    bind("Add Gate menu", "Type", "Filter gates"),
+    bind("Add Gate menu", "Enter", "Place the selected gate"),
+    bind("Add Gate menu", "Esc", "Clear the filter, or close"),
+    bind("Target / controls", "↑↓ / jk", "Choose a qubit"),
+    bind("Target / controls", "Enter", "Confirm"),
+    bind(
+        "Target / controls",
+        "n",
+        "Use a fresh ancilla as the target",
+    ),
+    bind("Target / controls", "Esc", "Cancel"),
+    bind("Edit gate", "↑↓ / jk", "Select an option"),
+    bind("Edit gate", "Enter", "Apply the option"),
+    bind("Edit gate", "Esc", "Close"),
+    bind("Prompts", "Enter", "Confirm"),
+    bind("Prompts", "Bksp", "Delete a character"),
+    bind("Prompts", "Esc", "Cancel"),
+    bind(
+        "Prompts",
+        "Tab",
+        "Parameter: place the gate on a qubit range",
+    ),
+    bind(
+        "Barrier label",
+        "Enter",
+        "Place the barrier with this label",
+    ),
+    bind("Barrier label", "Esc", "Place it without a label"),
+    bind("Qubit range", "↑↓ / jk", "Extend the range from the cursor"),
+    bind(
+        "Qubit range",
+        "Enter",
+        "Place the gate on every qubit in the range",
+    ),
+    bind("Qubit range", "Esc", "Cancel"),
+    bind(
+        "Region",
+        "Arrows / hjkl",
+        "Extend the selection from its corner",
+    ),
+    bind("Region", "d / Bksp", "Delete every gate wholly inside"),
+    bind("Region", "Esc / X", "Cancel"),
+    bind("Observables", "Enter", "New line"),
+    bind("Observables", "Esc / Tab", "Back to the circuit"),
+    bind("Histogram", "k", "Keep the measurements that were added"),
+    bind("Histogram", "Esc", "Close, removing added measurements"),
+    bind("Diff", "↑↓ / jk", "Scroll"),
+    bind("Diff", "Esc / D", "Close"),
+    bind("Help", "↑↓ / jk", "Scroll"),
+    bind("Help", "Esc / ? / q", "Close"),
+];
+
+pub fn keybindings() -> &'static [KeyBinding] {
+    KEYBINDINGS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn no_key_is_listed_twice_in_a_mode() {
+        let mut seen = HashSet::new();
+        for binding in KEYBINDINGS {
+            assert!(!binding.key_events().is_empty(), "{binding:?}");
+            for key in binding.key_events() {
+                assert!(
+                    seen.insert((binding.mode, key)),
+                    "{key:?} listed twice under {}",
+                    binding.mode
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+pub mod app;
+pub mod circuit;
+pub mod config;
+pub mod dag;
+pub mod diff;
+pub mod help;
+pub mod matrix;
+pub mod menu;
+pub mod params;
+#[cfg(feature = "parallel")]
+mod pool;
+pub mod quantum;
+pub mod render;
+
+pub use circuit::{Circuit, Gate};
+pub use dag::{CircuitDAG, DAGNode};
+pub use params::{format_param, parse_param_expr, parse_params};
+pub use quantum::{StateVector, simulate_circuit};
+
+/// Probability at or below which `run_qasm` leaves a basis state out.
+const RUN_QASM_CUTOFF: f64 = 1e-12;
+
+/// Simulate a QASM program without the TUI: parse it, run every gate from
+/// |0…0⟩ and return the basis states with nonzero probability as (index,
+/// probability), ascending by index. Bit q of an index is qubit q. Fails on
+/// the first line that does not parse.
+pub fn run_qasm(qasm: &str) -> Result<Vec<(usize, f64)>, String> {
+    let mut dag = CircuitDAG::new();
+    let errors = dag.parse_qasm(qasm);
+    if let Some((line, msg)) = errors.first() {
+        return Err(format!("line {}: {msg}", line + 1));
+    }
+    let state = simulate_circuit(&dag.to_circuit(), -1);
+    Ok(state
+        .amplitudes
+        .iter()
+        .map(|a| a.norm_sqr())
+        .enumerate()
+        .filter(|&(_, p)| p > RUN_QASM_CUTOFF)
+        .collect())
+}
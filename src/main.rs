@@ -1,23 +1,23 @@
-pub mod app;
-pub mod circuit;
-pub mod dag;
-pub mod matrix;
-pub mod menu;
-pub mod params;
-pub mod quantum;
-pub mod render;
-
 use std::io;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
+use ratatui::{Terminal, backend::CrosstermBackend};
+
+use q_deck_rs::{app, circuit, config, help, menu, params, render};
+
+use app::{App, DEFAULT_RANDOM_DEPTH, Focus, MAX_AMP_THRESHOLD, MIN_AMP_THRESHOLD, Playback};
+use circuit::{Gate, invert_gate};
+use config::Config;
 
-use app::{App, Focus};
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time between steps during simulation playback.
+const PLAYBACK_INTERVAL: Duration = Duration::from_millis(500);
 
 fn main() -> Result<(), io::Error> {
     // Setup terminal
@@ -27,8 +27,12 @@ fn main() -> Result<(), io::Error> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
+    let config = Config::from_env();
     let mut app = App::new();
-    let result = run_app(&mut terminal, &mut app);
+    app.max_sim_qubits = config.max_sim_qubits;
+    app.check_autosave();
+    app.load_saved_qasm();
+    let result = run_app(&mut terminal, &mut app, &config);
 
     // Restore terminal
     disable_raw_mode()?;
@@ -39,9 +43,16 @@ fn main() -> Result<(), io::Error> {
     )?;
     terminal.show_cursor()?;
 
-    if let Err(e) = result {
+    if let Err(e) = &result {
         eprintln!("Error: {e}");
     }
+    // A clean quit has nothing to recover; keep an unanswered recovery offer
+    if result.is_ok() && !app.recover_pending {
+        app.remove_autosave();
+    }
+    if let Err(e) = app.settings().save() {
+        eprintln!("Could not save settings: {e}");
+    }
 
     Ok(())
 }
@@ -49,80 +60,114 @@ fn main() -> Result<(), io::Error> {
 fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
+    config: &Config,
 ) -> Result<(), io::Error> {
+    let frame = config.frame_interval();
+    let mut dirty = true;
+    let mut last_draw = Instant::now();
+    let mut last_autosave = Instant::now();
+    let mut last_play_tick = Instant::now();
     loop {
-        terminal.draw(|f| render::render(f, app))?;
+        if last_autosave.elapsed() >= AUTOSAVE_INTERVAL {
+            if let Err(e) = app.autosave() {
+                app.status_msg = format!("Autosave error: {e}");
+                dirty = true;
+            }
+            last_autosave = Instant::now();
+        }
+
+        // The first step after starting or resuming waits a full interval
+        if app.playback != Playback::Playing {
+            last_play_tick = Instant::now();
+        } else if last_play_tick.elapsed() >= PLAYBACK_INTERVAL {
+            dirty |= app.playback_tick();
+            last_play_tick = Instant::now();
+        }
+
+        // Redraw only after input, and at most once per frame; idle polls
+        // block for a whole frame so the loop never spins.
+        if dirty && last_draw.elapsed() >= frame {
+            terminal.draw(|f| render::render(f, app))?;
+            dirty = false;
+            last_draw = Instant::now();
+        }
 
-        if !event::poll(Duration::from_millis(100))? {
+        let timeout = if dirty {
+            frame.saturating_sub(last_draw.elapsed())
+        } else {
+            frame
+        };
+        if !event::poll(timeout)? {
             continue;
         }
 
         let evt = event::read()?;
+        dirty = true;
         if let Event::Key(key) = evt {
             // Clear status message on any key
             app.status_msg.clear();
+            app.status_is_error = false;
 
             let code = key.code;
             let mods = key.modifiers;
+            if code != KeyCode::Tab {
+                app.qasm_completion = None;
+            }
 
             // Global: Ctrl+C always quits
             if code == KeyCode::Char('c') && mods.contains(KeyModifiers::CONTROL) {
                 return Ok(());
             }
 
-            match app.focus {
-                Focus::Circuit => {
-                    if handle_circuit_keys(app, code, mods) {
-                        return Ok(());
-                    }
-                }
-                Focus::Qasm => match code {
-                    KeyCode::Tab => {
-                        app.focus = Focus::Circuit;
-                        app.parse_qasm_input();
-                    }
-                    KeyCode::Left => app.qasm_move_left(),
-                    KeyCode::Right => app.qasm_move_right(),
-                    KeyCode::Up => app.qasm_move_up(),
-                    KeyCode::Down => app.qasm_move_down(),
-                    KeyCode::Home => app.qasm_move_home(),
-                    KeyCode::End => app.qasm_move_end(),
-                    KeyCode::Backspace => {
-                        app.qasm_backspace();
-                        app.parse_qasm_input();
-                    }
-                    KeyCode::Delete => {
-                        app.qasm_delete_forward();
-                        app.parse_qasm_input();
-                    }
-                    KeyCode::Enter => {
-                        app.qasm_insert_char('\n');
-                        app.parse_qasm_input();
-                    }
-                    KeyCode::Char(c) => {
-                        app.qasm_insert_char(c);
-                        app.parse_qasm_input();
-                    }
-                    _ => {}
-                },
-                Focus::Menu => handle_menu_keys(app, code),
-                Focus::SelectTarget => handle_select_target_keys(app, code),
-                Focus::SelectControls => handle_select_controls_keys(app, code),
-                Focus::InputParam => handle_input_param_keys(app, code),
-                Focus::EditGate => handle_edit_gate_keys(app, code),
-                Focus::EditParam => handle_edit_param_keys(app, code),
-                Focus::EditTarget => handle_edit_target_keys(app, code),
-                Focus::EditControl => handle_edit_control_keys(app, code),
+            if handle_key(app, code, mods) == KeyOutcome::Quit {
+                return Ok(());
             }
+            app.refresh_recover_prompt();
         }
     }
 }
 
+/// What a key handler did with a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyOutcome {
+    /// No binding in the current focus matched the key.
+    Ignored,
+    Handled,
+    Quit,
+}
+
+fn handle_key(app: &mut App, code: KeyCode, mods: KeyModifiers) -> KeyOutcome {
+    match app.focus {
+        Focus::Circuit => handle_circuit_keys(app, code, mods),
+        Focus::Qasm => handle_qasm_keys(app, code, mods),
+        Focus::Menu => handle_menu_keys(app, code),
+        Focus::SelectTarget => handle_select_target_keys(app, code),
+        Focus::SelectSwap => handle_select_swap_keys(app, code),
+        Focus::Stats => handle_stats_keys(app, code),
+        Focus::SelectControls => handle_select_controls_keys(app, code),
+        Focus::InputParam => handle_input_param_keys(app, code),
+        Focus::InputPath => handle_input_path_keys(app, code),
+        Focus::Command => handle_command_keys(app, code),
+        Focus::InputState => handle_input_state_keys(app, code),
+        Focus::InputLabel => handle_input_label_keys(app, code),
+        Focus::EditGate => handle_edit_gate_keys(app, code),
+        Focus::EditParam => handle_edit_param_keys(app, code),
+        Focus::EditTarget => handle_edit_target_keys(app, code),
+        Focus::EditControl => handle_edit_control_keys(app, code),
+        Focus::Observables => handle_observables_keys(app, code),
+        Focus::SelectRange => handle_select_range_keys(app, code),
+        Focus::SelectRegion => handle_select_region_keys(app, code),
+        Focus::Help => handle_help_keys(app, code),
+        Focus::Histogram => handle_histogram_keys(app, code),
+        Focus::Diff => handle_diff_keys(app, code),
+    }
+}
+
 // ── Focus::Circuit ─────────────────────────────────────────────────────────────
 
-fn handle_circuit_keys(app: &mut App, code: KeyCode, mods: KeyModifiers) -> bool {
+fn handle_circuit_keys(app: &mut App, code: KeyCode, mods: KeyModifiers) -> KeyOutcome {
     match code {
-        KeyCode::Char('q') => return true,
+        KeyCode::Char('q') => return KeyOutcome::Quit,
         KeyCode::Tab => {
             app.focus = Focus::Qasm;
         }
@@ -131,19 +176,13 @@ fn handle_circuit_keys(app: &mut App, code: KeyCode, mods: KeyModifiers) -> bool
             Err(e) => app.status_msg = format!("Save error: {e}"),
         },
         KeyCode::Up | KeyCode::Char('k') => {
-            if app.cursor_qubit > 0 {
-                app.cursor_qubit -= 1;
-            }
+            app.cursor_qubit = app.qubit_after_rows(app.cursor_qubit, -1);
         }
         KeyCode::Down | KeyCode::Char('j') => {
-            if app.cursor_qubit + 1 < app.dag.num_qubits {
-                app.cursor_qubit += 1;
-            }
+            app.cursor_qubit = app.qubit_after_rows(app.cursor_qubit, 1);
         }
-        KeyCode::Left | KeyCode::Char('h') => {
-            if app.cursor_step > 0 {
-                app.cursor_step -= 1;
-            }
+        KeyCode::Left | KeyCode::Char('h') if app.cursor_step > 0 => {
+            app.cursor_step -= 1;
         }
         KeyCode::Right | KeyCode::Char('l') => {
             app.cursor_step += 1;
@@ -152,33 +191,52 @@ fn handle_circuit_keys(app: &mut App, code: KeyCode, mods: KeyModifiers) -> bool
             app.dag.num_qubits += 1;
             app.sync_from_dag();
         }
-        KeyCode::Char('-') => {
-            if app.dag.num_qubits > 1 {
-                let q = app.dag.num_qubits - 1;
-                app.dag.remove_nodes_on_qubit(q);
-                app.dag.num_qubits -= 1;
-                if app.cursor_qubit >= app.dag.num_qubits {
-                    app.cursor_qubit = app.dag.num_qubits.saturating_sub(1);
-                }
-                app.sync_from_dag();
+        KeyCode::Char('-') if app.dag.num_qubits > 1 => {
+            let q = app.dag.num_qubits - 1;
+            app.dag.remove_nodes_on_qubit(q);
+            app.dag.num_qubits -= 1;
+            if app.cursor_qubit >= app.dag.num_qubits {
+                app.cursor_qubit = app.dag.num_qubits.saturating_sub(1);
             }
+            app.sync_from_dag();
+        }
+        KeyCode::Char('>') => {
+            app.dag.num_cbits += 1;
+            app.sync_from_dag();
+            app.status_msg = format!("Classical bits: {}", app.dag.num_cbits);
+        }
+        KeyCode::Char('<') => {
+            let required = app.dag.required_cbits();
+            app.dag.num_cbits = app.dag.num_cbits.saturating_sub(1).max(required);
+            app.sync_from_dag();
+            app.status_msg = if app.dag.num_cbits == required && required > 0 {
+                format!("Classical bits: {required} (all in use)")
+            } else {
+                format!("Classical bits: {}", app.dag.num_cbits)
+            };
         }
         KeyCode::Char('a') => {
             app.focus = Focus::Menu;
             app.menu_cat = 0;
             app.menu_item = 0;
+            app.menu_filter.clear();
+            app.apply_all_qubits = false;
         }
         KeyCode::Backspace | KeyCode::Delete => {
             app.dag.remove_node_at(app.cursor_step, app.cursor_qubit);
             app.sync_from_dag();
         }
+        KeyCode::Char('X') => {
+            app.region_anchor = (app.cursor_step, app.cursor_qubit);
+            app.focus = Focus::SelectRegion;
+        }
         KeyCode::Char('e') => {
             let node = app
                 .dag
                 .get_node_at(app.cursor_step, app.cursor_qubit)
                 .cloned();
             if let Some(node) = node {
-                let gate = crate::circuit::Gate {
+                let gate = Gate {
                     step: node.step,
                     type_name: node.type_name.clone(),
                     target: if node.target >= 0 {
@@ -195,6 +253,9 @@ fn handle_circuit_keys(app: &mut App, code: KeyCode, mods: KeyModifiers) -> bool
                     classical_control: node.classical_control,
                     is_noise: node.is_noise,
                     noise_type: node.noise_type.clone(),
+                    duration: node.duration,
+                    noise: node.noise.clone(),
+                    label: node.label.clone(),
                 };
                 app.edit_gate = Some(gate);
                 app.edit_menu_idx = 0;
@@ -205,93 +266,349 @@ fn handle_circuit_keys(app: &mut App, code: KeyCode, mods: KeyModifiers) -> bool
         KeyCode::Char('v') => {
             app.show_statevector = !app.show_statevector;
         }
+        KeyCode::Char('V') => {
+            app.amp_format = app.amp_format.cycled();
+            app.status_msg = format!("Amplitudes: {}", app.amp_format.label());
+        }
         KeyCode::Char('m') => {
             app.show_matrix = !app.show_matrix;
             app.matrix_scroll = 0;
         }
-        _ => {}
+        KeyCode::Char('p') => {
+            app.show_qubit_probs = !app.show_qubit_probs;
+        }
+        KeyCode::Char('f') => {
+            app.show_sim_frontier = !app.show_sim_frontier;
+        }
+        KeyCode::Enter => app.measure_and_sample(),
+        KeyCode::Char('M') => {
+            let step = app.dag.measure_all();
+            app.cursor_step = step + 1;
+            app.sync_from_dag();
+            app.status_msg = format!("Measured all qubits at step {step}");
+        }
+        KeyCode::Char('W') => {
+            app.wire_style = app.wire_style.toggled();
+        }
+        KeyCode::Char('O') => {
+            app.reverse_qubits = !app.reverse_qubits;
+            app.status_msg = if app.reverse_qubits {
+                "Drawing the highest qubit on top".to_string()
+            } else {
+                "Drawing q[0] on top".to_string()
+            };
+        }
+        KeyCode::Char('w') | KeyCode::Char('b') => {
+            let forward = code == KeyCode::Char('w');
+            if let Some(step) =
+                app.circuit()
+                    .next_gate_step(app.cursor_qubit, app.cursor_step, forward)
+            {
+                app.cursor_step = step;
+            }
+        }
+        KeyCode::Char('F') => {
+            app.toggle_sim_auto();
+            app.status_msg = if app.sim_auto {
+                "Simulation: live".to_string()
+            } else {
+                "Simulation: frozen (. to refresh)".to_string()
+            };
+        }
+        KeyCode::Char('.') if !app.sim_auto => {
+            if let Some(msg) = app.simulation_limit_message() {
+                app.status_msg = msg;
+            } else {
+                app.refresh_simulation();
+                app.status_msg = "Simulation refreshed".to_string();
+            }
+        }
+        KeyCode::Char('N') => {
+            app.noise_enabled = !app.noise_enabled;
+            app.status_msg = if app.noise_enabled {
+                "Probabilities: noisy".to_string()
+            } else {
+                "Probabilities: ideal".to_string()
+            };
+        }
+        KeyCode::Char('R') if app.recover_pending => match app.recover_autosave() {
+            Ok(()) => app.status_msg = "Recovered autosaved circuit".to_string(),
+            Err(e) => {
+                app.recover_pending = false;
+                app.status_msg = format!("Recover error: {e}");
+            }
+        },
+        KeyCode::Esc if app.recover_pending => {
+            app.recover_pending = false;
+            app.status_msg = "Discarded autosave".to_string();
+        }
+        KeyCode::Char('S') if app.dag.num_qubits > 1 => {
+            app.target_qubit = if app.cursor_qubit + 1 < app.dag.num_qubits {
+                app.cursor_qubit + 1
+            } else {
+                app.cursor_qubit - 1
+            };
+            app.focus = Focus::SelectSwap;
+        }
+        KeyCode::Char('i') => app.focus = Focus::Stats,
+        KeyCode::Char('D') => app.show_diff(),
+        KeyCode::Char('n') => app.check_normalization(),
+        KeyCode::Char('P') => {
+            app.toggle_playback();
+            app.status_msg = if app.playback == Playback::Off {
+                "Playback off".to_string()
+            } else {
+                "Playing: Space Pause  0 Restart  P Stop".to_string()
+            };
+        }
+        KeyCode::Char(' ') if app.playback != Playback::Off => app.pause_playback(),
+        KeyCode::Char('0') => app.cursor_step = 0,
+        KeyCode::Char('[') | KeyCode::Char(']') => {
+            // One decade per press
+            let factor = if code == KeyCode::Char(']') {
+                10.0
+            } else {
+                0.1
+            };
+            app.amp_threshold =
+                (app.amp_threshold * factor).clamp(MIN_AMP_THRESHOLD, MAX_AMP_THRESHOLD);
+            app.status_msg = format!("Hiding states with P ≤ {:.0e}", app.amp_threshold);
+        }
+        KeyCode::Char(':') => {
+            app.param_input.clear();
+            app.focus = Focus::Command;
+        }
+        KeyCode::Char('G') => app.random_circuit(DEFAULT_RANDOM_DEPTH, None),
+        KeyCode::Char('T') => app.transpile_circuit(),
+        KeyCode::Char('B') => {
+            app.param_input.clear();
+            app.focus = Focus::InputState;
+        }
+        KeyCode::Char('?') => {
+            app.help_scroll = 0;
+            app.focus = Focus::Help;
+        }
+        KeyCode::Char('r') => {
+            app.wrap_circuit = !app.wrap_circuit;
+            app.status_msg = if app.wrap_circuit {
+                "Layout: wrapped rows".to_string()
+            } else {
+                "Layout: scrolling".to_string()
+            };
+        }
+        KeyCode::Char('A') => {
+            app.path_input.clear();
+            app.focus = Focus::InputPath;
+        }
+        KeyCode::Char('E') => match app.export_svg() {
+            Ok(()) => app.status_msg = "Exported circuit.svg".to_string(),
+            Err(e) => app.status_msg = format!("Export error: {e}"),
+        },
+        KeyCode::Char('I') => {
+            let removed = app.dag.remove_identities();
+            app.sync_from_dag();
+            app.status_msg = format!("Removed {removed} identity gate(s)");
+        }
+        KeyCode::Char('C') => {
+            app.dag.compact_steps();
+            app.cursor_step = app.cursor_step.min(app.dag.max_step() + 1);
+            app.sync_from_dag();
+            app.status_msg = "Compacted steps".to_string();
+        }
+        KeyCode::Char('L') => {
+            app.show_listing = !app.show_listing;
+            if app.show_listing {
+                app.show_matrix = false;
+                app.show_observables = false;
+            }
+        }
+        KeyCode::Char('o') => {
+            app.show_observables = !app.show_observables;
+            if app.show_observables {
+                app.show_matrix = false;
+                app.focus = Focus::Observables;
+            }
+        }
+        _ => return KeyOutcome::Ignored,
     }
-    false
+    KeyOutcome::Handled
 }
 
-// ── Focus::Menu ────────────────────────────────────────────────────────────────
+// ── Focus::Qasm ────────────────────────────────────────────────────────────────
 
-fn handle_menu_keys(app: &mut App, code: KeyCode) {
+fn handle_qasm_keys(app: &mut App, code: KeyCode, mods: KeyModifiers) -> KeyOutcome {
     match code {
-        KeyCode::Esc => app.focus = Focus::Circuit,
-        KeyCode::Up | KeyCode::Char('k') => {
-            if app.menu_item > 0 {
-                app.menu_item -= 1;
+        KeyCode::Tab if app.qasm_complete() => app.parse_qasm_input(),
+        KeyCode::Char('f') if mods.contains(KeyModifiers::CONTROL) => {
+            if app.reformat_qasm() {
+                app.status_msg = "Reformatted QASM".to_string();
+            } else {
+                app.status_msg = "Cannot reformat: fix QASM errors first".to_string();
             }
         }
-        KeyCode::Down | KeyCode::Char('j') => {
-            let max = menu::GATE_MENU[app.menu_cat].items.len().saturating_sub(1);
-            if app.menu_item < max {
-                app.menu_item += 1;
-            }
+        KeyCode::Tab => {
+            app.focus = Focus::Circuit;
+            app.parse_qasm_input();
+            app.clamp_cursor();
+        }
+        KeyCode::Left => app.qasm_move_left(),
+        KeyCode::Right => app.qasm_move_right(),
+        KeyCode::Up => app.qasm_move_up(),
+        KeyCode::Down => app.qasm_move_down(),
+        KeyCode::Home => app.qasm_move_home(),
+        KeyCode::End => app.qasm_move_end(),
+        KeyCode::Backspace => {
+            app.qasm_backspace();
+            app.parse_qasm_input();
         }
-        KeyCode::Left | KeyCode::Char('h') => {
-            if app.menu_cat > 0 {
-                app.menu_cat -= 1;
+        KeyCode::Delete => {
+            app.qasm_delete_forward();
+            app.parse_qasm_input();
+        }
+        KeyCode::Enter => {
+            app.qasm_insert_char('\n');
+            app.parse_qasm_input();
+        }
+        KeyCode::Char(c) => {
+            app.qasm_insert_char(c);
+            app.parse_qasm_input();
+        }
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::Stats ───────────────────────────────────────────────────────────────
+
+fn handle_stats_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('i') => app.focus = Focus::Circuit,
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::Observables ─────────────────────────────────────────────────────────
+
+fn handle_observables_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc | KeyCode::Tab => app.focus = Focus::Circuit,
+        KeyCode::Backspace => {
+            app.observables_input.pop();
+        }
+        KeyCode::Enter => app.observables_input.push('\n'),
+        KeyCode::Char(c) if c.is_ascii_digit() || " IXYZixyz".contains(c) => {
+            app.observables_input.push(c.to_ascii_uppercase());
+        }
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::Menu ────────────────────────────────────────────────────────────────
+
+fn handle_menu_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc if !app.menu_filter.is_empty() => {
+            app.menu_filter.clear();
+            app.menu_item = 0;
+        }
+        KeyCode::Esc => app.focus = Focus::Circuit,
+        KeyCode::Up | KeyCode::Down => {
+            let len = app.menu_items().len();
+            let forward = code == KeyCode::Down;
+            app.menu_item = menu::step_index(app.menu_item, len, forward, app.menu_wrap);
+        }
+        KeyCode::Left | KeyCode::Right if app.menu_filter.is_empty() => {
+            let len = menu::GATE_MENU.len();
+            let forward = code == KeyCode::Right;
+            let cat = menu::step_index(app.menu_cat, len, forward, app.menu_wrap);
+            if cat != app.menu_cat {
+                app.menu_cat = cat;
                 app.menu_item = 0;
             }
         }
-        KeyCode::Right | KeyCode::Char('l') => {
-            if app.menu_cat + 1 < menu::GATE_MENU.len() {
-                app.menu_cat += 1;
-                app.menu_item = 0;
+        KeyCode::Backspace => {
+            app.menu_filter.pop();
+            app.menu_item = 0;
+        }
+        KeyCode::Tab => app.apply_all_qubits = !app.apply_all_qubits,
+        KeyCode::Char(c @ '1'..='5') if app.menu_filter.is_empty() => {
+            let idx = c as usize - '1' as usize;
+            if let Some(item) = app
+                .recent_gates
+                .get(idx)
+                .and_then(|g| menu::find_menu_item(g))
+            {
+                start_gate_placement(app, item);
             }
         }
+        KeyCode::Char(c) => {
+            app.menu_filter.push(c);
+            app.menu_item = 0;
+        }
         KeyCode::Enter => {
-            let item = &crate::menu::GATE_MENU[app.menu_cat].items[app.menu_item];
-            let gate_type = item.gate_type.to_string();
-            app.pending_gate = gate_type.clone();
-
-            if crate::menu::is_parameterized_gate(&gate_type) {
-                app.param_input.clear();
-                app.focus = Focus::InputParam;
-                return;
+            if let Some(item) = app.selected_menu_item() {
+                start_gate_placement(app, item);
             }
+        }
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
 
-            if gate_type == "CCX" {
-                if app.dag.num_qubits < 3 {
-                    app.focus = Focus::Circuit;
-                    return;
-                }
-                app.control_qubits.clear();
-                app.focus = Focus::SelectControls;
-                app.target_qubit = if app.cursor_qubit + 1 < app.dag.num_qubits {
-                    app.cursor_qubit + 1
-                } else {
-                    app.cursor_qubit.saturating_sub(1)
-                };
-                return;
-            }
+/// Begin placing `item` at the cursor, prompting for parameters, controls
+/// or a target as the gate requires.
+fn start_gate_placement(app: &mut App, item: &'static menu::MenuItem) {
+    let gate_type = item.gate_type.to_string();
+    app.pending_gate = gate_type.clone();
 
-            if item.needs_target {
-                if app.dag.num_qubits < 2 {
-                    app.focus = Focus::Circuit;
-                    return;
-                }
-                app.focus = Focus::SelectTarget;
-                app.target_qubit = if app.cursor_qubit + 1 < app.dag.num_qubits {
-                    app.cursor_qubit + 1
-                } else {
-                    app.cursor_qubit.saturating_sub(1)
-                };
-            } else {
-                if app.place_gate(&gate_type.clone(), -1) {
-                    app.focus = Focus::Circuit;
-                }
-            }
+    if menu::is_parameterized_gate(&gate_type) {
+        app.param_input.clear();
+        app.focus = Focus::InputParam;
+        return;
+    }
+
+    if gate_type == "BARRIER" {
+        app.param_input.clear();
+        app.focus = Focus::InputLabel;
+        return;
+    }
+
+    if gate_type == "CCX" {
+        if app.dag.num_qubits < 3 {
+            app.focus = Focus::Circuit;
+            return;
+        }
+        app.control_qubits.clear();
+        app.focus = Focus::SelectControls;
+        app.target_qubit = if app.cursor_qubit + 1 < app.dag.num_qubits {
+            app.cursor_qubit + 1
+        } else {
+            app.cursor_qubit.saturating_sub(1)
+        };
+        return;
+    }
+
+    if item.needs_target {
+        if app.dag.num_qubits < 2 {
+            app.focus = Focus::Circuit;
+            return;
         }
-        _ => {}
+        app.focus = Focus::SelectTarget;
+        app.target_qubit = if app.cursor_qubit + 1 < app.dag.num_qubits {
+            app.cursor_qubit + 1
+        } else {
+            app.cursor_qubit.saturating_sub(1)
+        };
+    } else if app.place_gate(&gate_type, -1) {
+        app.focus = Focus::Circuit;
     }
 }
 
 // ── Focus::SelectTarget ─────────────────────────────────────────────────────────
 
-fn handle_select_target_keys(app: &mut App, code: KeyCode) {
+fn handle_select_target_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
     match code {
         KeyCode::Esc => {
             app.focus = Focus::Circuit;
@@ -321,13 +638,50 @@ fn handle_select_target_keys(app: &mut App, code: KeyCode) {
                 app.focus = Focus::Circuit;
             }
         }
-        _ => {}
+        KeyCode::Char('n') => {
+            let gate = app.pending_gate.clone();
+            if app.place_gate_on_ancilla(&gate) {
+                app.focus = Focus::Circuit;
+            }
+        }
+        _ => return KeyOutcome::Ignored,
     }
+    KeyOutcome::Handled
+}
+
+// ── Focus::SelectSwap ───────────────────────────────────────────────────────────
+
+fn handle_select_swap_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc => app.focus = Focus::Circuit,
+        KeyCode::Up | KeyCode::Char('k') => {
+            if let Some(next) = app.next_available_target(app.target_qubit, -1, &[app.cursor_qubit])
+            {
+                app.target_qubit = next;
+            }
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            if let Some(next) = app.next_available_target(app.target_qubit, 1, &[app.cursor_qubit])
+            {
+                app.target_qubit = next;
+            }
+        }
+        KeyCode::Enter => {
+            let (a, b) = (app.cursor_qubit, app.target_qubit);
+            app.dag.swap_qubits(a, b);
+            app.cursor_qubit = b;
+            app.sync_from_dag();
+            app.status_msg = format!("Swapped q[{a}] and q[{b}]");
+            app.focus = Focus::Circuit;
+        }
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
 }
 
 // ── Focus::SelectControls ───────────────────────────────────────────────────────
 
-fn handle_select_controls_keys(app: &mut App, code: KeyCode) {
+fn handle_select_controls_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
     match code {
         KeyCode::Esc => {
             app.focus = Focus::Circuit;
@@ -361,13 +715,14 @@ fn handle_select_controls_keys(app: &mut App, code: KeyCode) {
                 }
             }
         }
-        _ => {}
+        _ => return KeyOutcome::Ignored,
     }
+    KeyOutcome::Handled
 }
 
 // ── Focus::InputParam ──────────────────────────────────────────────────────────
 
-fn handle_input_param_keys(app: &mut App, code: KeyCode) {
+fn handle_input_param_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
     match code {
         KeyCode::Esc => {
             app.focus = Focus::Circuit;
@@ -377,21 +732,26 @@ fn handle_input_param_keys(app: &mut App, code: KeyCode) {
         KeyCode::Backspace => {
             app.param_input.pop();
         }
-        KeyCode::Enter => {
+        KeyCode::Enter | KeyCode::Tab => {
             // Validate params
             if !app.param_input.is_empty() {
-                if crate::params::parse_params(&app.param_input).is_none() {
+                let Some(params) = params::parse_params(&app.param_input) else {
                     app.status_msg =
                         "Invalid parameter — use numbers or pi expressions (e.g. pi/2, 3*pi/4)"
                             .to_string();
-                    return;
+                    return KeyOutcome::Handled;
+                };
+                if let Err(e) = menu::validate_param_count(&app.pending_gate, params.len()) {
+                    app.status_msg = e;
+                    return KeyOutcome::Handled;
                 }
             }
-            let item = &crate::menu::GATE_MENU[app.menu_cat].items[app.menu_item];
-            if item.needs_target {
+            let needs_target =
+                menu::find_menu_item(&app.pending_gate).is_some_and(|item| item.needs_target);
+            if needs_target {
                 if app.dag.num_qubits < 2 {
                     app.focus = Focus::Circuit;
-                    return;
+                    return KeyOutcome::Handled;
                 }
                 app.focus = Focus::SelectTarget;
                 app.target_qubit = if app.cursor_qubit + 1 < app.dag.num_qubits {
@@ -399,6 +759,13 @@ fn handle_input_param_keys(app: &mut App, code: KeyCode) {
                 } else {
                     app.cursor_qubit.saturating_sub(1)
                 };
+            } else if code == KeyCode::Tab
+                && menu::is_single_qubit_gate(&app.pending_gate)
+                && app.dag.num_qubits > 1
+            {
+                // Range mode: the cursor qubit anchors one end
+                app.target_qubit = app.cursor_qubit;
+                app.focus = Focus::SelectRange;
             } else {
                 let gate = app.pending_gate.clone();
                 if app.place_gate(&gate, -1) {
@@ -407,16 +774,214 @@ fn handle_input_param_keys(app: &mut App, code: KeyCode) {
             }
         }
         KeyCode::Char(c) => app.handle_char_input(c),
-        _ => {}
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::SelectRange ─────────────────────────────────────────────────────────
+
+fn handle_select_range_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc => {
+            app.focus = Focus::Circuit;
+            app.param_input.clear();
+            app.pending_gate.clear();
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.target_qubit = app.qubit_after_rows(app.target_qubit, -1);
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            app.target_qubit = app.qubit_after_rows(app.target_qubit, 1);
+        }
+        KeyCode::Enter => {
+            let gate = app.pending_gate.clone();
+            app.place_gate_on_range(&gate);
+            app.focus = Focus::Circuit;
+        }
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::SelectRegion ────────────────────────────────────────────────────────
+
+fn handle_select_region_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc | KeyCode::Char('X') => app.focus = Focus::Circuit,
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.cursor_qubit = app.qubit_after_rows(app.cursor_qubit, -1);
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            app.cursor_qubit = app.qubit_after_rows(app.cursor_qubit, 1);
+        }
+        KeyCode::Left | KeyCode::Char('h') if app.cursor_step > 0 => {
+            app.cursor_step -= 1;
+        }
+        KeyCode::Right | KeyCode::Char('l') => {
+            app.cursor_step += 1;
+        }
+        KeyCode::Char('d') | KeyCode::Backspace | KeyCode::Delete => {
+            app.delete_region();
+            app.focus = Focus::Circuit;
+        }
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::InputPath ───────────────────────────────────────────────────────────
+
+fn handle_input_path_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc => {
+            app.focus = Focus::Circuit;
+            app.path_input.clear();
+        }
+        KeyCode::Backspace => {
+            app.path_input.pop();
+        }
+        KeyCode::Enter if !app.path_input.is_empty() => {
+            match app.append_file() {
+                Ok(()) => app.status_msg = format!("Appended {}", app.path_input),
+                Err(e) => app.status_msg = format!("Append error: {e}"),
+            }
+            app.focus = Focus::Circuit;
+        }
+        KeyCode::Char(c) => app.path_input.push(c),
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::Command ─────────────────────────────────────────────────────────────
+
+fn handle_command_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc => {
+            app.focus = Focus::Circuit;
+            app.param_input.clear();
+        }
+        KeyCode::Backspace => {
+            app.param_input.pop();
+        }
+        KeyCode::Enter => match app.run_command() {
+            Ok(()) => {
+                if app.status_msg.is_empty() {
+                    app.status_msg = format!("Placed {}", app.param_input.trim());
+                }
+                app.param_input.clear();
+                app.focus = Focus::Circuit;
+            }
+            Err(e) => app.status_msg = e,
+        },
+        KeyCode::Char(c) => app.param_input.push(c),
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::InputLabel ──────────────────────────────────────────────────────────
+
+/// Optional caption for the barrier being placed; Esc places it bare.
+fn handle_input_label_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc => {
+            app.param_input.clear();
+            app.place_gate("BARRIER", -1);
+            app.focus = Focus::Circuit;
+        }
+        KeyCode::Backspace => {
+            app.param_input.pop();
+        }
+        KeyCode::Enter => {
+            app.place_gate("BARRIER", -1);
+            app.focus = Focus::Circuit;
+        }
+        KeyCode::Char(c) => app.param_input.push(c),
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::InputState ──────────────────────────────────────────────────────────
+
+fn handle_input_state_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc => {
+            app.focus = Focus::Circuit;
+            app.param_input.clear();
+        }
+        KeyCode::Backspace => {
+            app.param_input.pop();
+        }
+        KeyCode::Enter => match app.set_input_state() {
+            Ok(()) => {
+                app.status_msg = format!(
+                    "Simulating from input |{:0width$b}⟩",
+                    app.input_state,
+                    width = app.dag.num_qubits
+                );
+                app.param_input.clear();
+                app.focus = Focus::Circuit;
+            }
+            Err(e) => app.status_msg = e,
+        },
+        KeyCode::Char(c) => app.param_input.push(c),
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::Help ────────────────────────────────────────────────────────────────
+
+fn handle_help_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc | KeyCode::Char('?') | KeyCode::Char('q') => app.focus = Focus::Circuit,
+        KeyCode::Up | KeyCode::Char('k') => app.help_scroll = app.help_scroll.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => {
+            let rows = help::keybindings().len() + render::symbol_legend(app.wire_style).len();
+            app.help_scroll = (app.help_scroll + 1).min(rows);
+        }
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::Histogram ───────────────────────────────────────────────────────────
+
+fn handle_histogram_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Char('k') => app.close_histogram(true),
+        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('d') | KeyCode::Char('q') => {
+            app.close_histogram(false)
+        }
+        _ => return KeyOutcome::Ignored,
+    }
+    KeyOutcome::Handled
+}
+
+// ── Focus::Diff ────────────────────────────────────────────────────────────────
+
+fn handle_diff_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
+    match code {
+        KeyCode::Esc | KeyCode::Char('D') | KeyCode::Char('q') => app.close_diff(),
+        KeyCode::Up | KeyCode::Char('k') => app.diff_scroll = app.diff_scroll.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => {
+            let rows = app.diff.as_ref().map_or(0, Vec::len);
+            app.diff_scroll = (app.diff_scroll + 1).min(rows);
+        }
+        _ => return KeyOutcome::Ignored,
     }
+    KeyOutcome::Handled
 }
 
 // ── Focus::EditGate ────────────────────────────────────────────────────────────
 
-fn handle_edit_gate_keys(app: &mut App, code: KeyCode) {
+fn handle_edit_gate_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
     if app.edit_gate.is_none() {
         app.focus = Focus::Circuit;
-        return;
+        return KeyOutcome::Handled;
     }
     let opts = app.get_edit_options();
     match code {
@@ -424,82 +989,83 @@ fn handle_edit_gate_keys(app: &mut App, code: KeyCode) {
             app.focus = Focus::Circuit;
             app.edit_gate = None;
         }
-        KeyCode::Up | KeyCode::Char('k') => {
-            if app.edit_menu_idx > 0 {
-                app.edit_menu_idx -= 1;
-            }
+        KeyCode::Up | KeyCode::Char('k') if app.edit_menu_idx > 0 => {
+            app.edit_menu_idx -= 1;
         }
-        KeyCode::Down | KeyCode::Char('j') => {
-            if app.edit_menu_idx + 1 < opts.len() {
-                app.edit_menu_idx += 1;
-            }
+        KeyCode::Down | KeyCode::Char('j') if app.edit_menu_idx + 1 < opts.len() => {
+            app.edit_menu_idx += 1;
         }
-        KeyCode::Enter => {
-            if app.edit_menu_idx < opts.len() {
-                let action = opts[app.edit_menu_idx].action;
-                let ctrl_idx = opts[app.edit_menu_idx].ctrl_idx;
-                match action {
-                    "edit_param" => {
-                        app.param_input.clear();
-                        app.focus = Focus::EditParam;
+        KeyCode::Enter if app.edit_menu_idx < opts.len() => {
+            let action = opts[app.edit_menu_idx].action;
+            let ctrl_idx = opts[app.edit_menu_idx].ctrl_idx;
+            match action {
+                "edit_param" => {
+                    app.param_input.clear();
+                    app.focus = Focus::EditParam;
+                }
+                "invert" => {
+                    if let Some(g) = &mut app.edit_gate {
+                        invert_gate(&g.type_name, &mut g.params, &mut g.is_dagger);
                     }
-                    "edit_target" => {
-                        if let Some(g) = &app.edit_gate {
-                            app.target_qubit = g.target;
-                        }
-                        app.focus = Focus::EditTarget;
+                    commit_edit_to_dag(app);
+                }
+                "edit_target" => {
+                    if let Some(g) = &app.edit_gate {
+                        app.target_qubit = g.target;
                     }
-                    "add_control" => {
-                        if let Some(g) = &app.edit_gate {
-                            let nq = app.dag.num_qubits;
-                            app.target_qubit = (g.target + 1) % nq;
-                            let mut count = 0;
-                            while g.references(app.target_qubit) && count < nq {
-                                app.target_qubit = (app.target_qubit + 1) % nq;
-                                count += 1;
-                            }
-                            if count < nq {
-                                app.focus = Focus::EditControl;
-                                app.edit_control_idx = -2; // Special value for adding
-                            } else {
-                                app.status_msg = "No more qubits available for control".to_string();
-                            }
+                    app.focus = Focus::EditTarget;
+                }
+                "add_control" => {
+                    if let Some(g) = &app.edit_gate {
+                        let nq = app.dag.num_qubits;
+                        app.target_qubit = (g.target + 1) % nq;
+                        let mut count = 0;
+                        while g.references(app.target_qubit) && count < nq {
+                            app.target_qubit = (app.target_qubit + 1) % nq;
+                            count += 1;
                         }
-                    }
-                    "edit_control" => {
-                        app.edit_control_idx = ctrl_idx;
-                        if let Some(g) = &app.edit_gate {
-                            app.target_qubit = if ctrl_idx == -1 {
-                                g.control.max(0) as usize
-                            } else if (ctrl_idx as usize) < g.controls.len() {
-                                g.controls[ctrl_idx as usize]
-                            } else {
-                                0
-                            };
+                        if count < nq {
+                            app.focus = Focus::EditControl;
+                            app.edit_control_idx = -2; // Special value for adding
+                        } else {
+                            app.status_msg = "No more qubits available for control".to_string();
                         }
-                        app.focus = Focus::EditControl;
                     }
-                    "delete" => {
-                        let step = app.edit_orig_step;
-                        if let Some(g) = &app.edit_gate {
-                            let target = g.target;
-                            app.dag.remove_node_at(step, target);
-                        }
-                        app.edit_gate = None;
-                        app.focus = Focus::Circuit;
-                        app.sync_from_dag();
+                }
+                "edit_control" => {
+                    app.edit_control_idx = ctrl_idx;
+                    if let Some(g) = &app.edit_gate {
+                        app.target_qubit = if ctrl_idx == -1 {
+                            g.control.max(0) as usize
+                        } else if (ctrl_idx as usize) < g.controls.len() {
+                            g.controls[ctrl_idx as usize]
+                        } else {
+                            0
+                        };
                     }
-                    _ => {}
+                    app.focus = Focus::EditControl;
                 }
+                "delete" => {
+                    let step = app.edit_orig_step;
+                    if let Some(g) = &app.edit_gate {
+                        let target = g.target;
+                        app.dag.remove_node_at(step, target);
+                    }
+                    app.edit_gate = None;
+                    app.focus = Focus::Circuit;
+                    app.sync_from_dag();
+                }
+                _ => {}
             }
         }
-        _ => {}
+        _ => return KeyOutcome::Ignored,
     }
+    KeyOutcome::Handled
 }
 
 // ── Focus::EditParam ───────────────────────────────────────────────────────────
 
-fn handle_edit_param_keys(app: &mut App, code: KeyCode) {
+fn handle_edit_param_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
     match code {
         KeyCode::Esc => {
             app.param_input.clear();
@@ -510,14 +1076,18 @@ fn handle_edit_param_keys(app: &mut App, code: KeyCode) {
         }
         KeyCode::Enter => {
             if !app.param_input.is_empty() {
-                if let Some(params) = crate::params::parse_params(&app.param_input) {
+                if let Some(params) = params::parse_params(&app.param_input) {
                     if let Some(g) = &mut app.edit_gate {
+                        if let Err(e) = menu::validate_param_count(&g.type_name, params.len()) {
+                            app.status_msg = e;
+                            return KeyOutcome::Handled;
+                        }
                         g.params = params;
                     }
                 } else {
                     app.status_msg =
                         "Invalid parameter — use numbers or pi expressions".to_string();
-                    return;
+                    return KeyOutcome::Handled;
                 }
             }
             app.param_input.clear();
@@ -526,13 +1096,14 @@ fn handle_edit_param_keys(app: &mut App, code: KeyCode) {
             app.focus = Focus::EditGate;
         }
         KeyCode::Char(c) => app.handle_char_input(c),
-        _ => {}
+        _ => return KeyOutcome::Ignored,
     }
+    KeyOutcome::Handled
 }
 
 // ── Focus::EditTarget ──────────────────────────────────────────────────────────
 
-fn handle_edit_target_keys(app: &mut App, code: KeyCode) {
+fn handle_edit_target_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
     match code {
         KeyCode::Esc => app.focus = Focus::EditGate,
         KeyCode::Up | KeyCode::Char('k') => {
@@ -570,19 +1141,18 @@ fn handle_edit_target_keys(app: &mut App, code: KeyCode) {
             }
         }
         KeyCode::Enter => {
-            if let Some(g) = &mut app.edit_gate {
-                g.target = app.target_qubit;
-            }
-            commit_edit_to_dag(app);
+            let target = app.target_qubit;
+            edit_qubits(app, |g| g.target = target);
             app.focus = Focus::EditGate;
         }
-        _ => {}
+        _ => return KeyOutcome::Ignored,
     }
+    KeyOutcome::Handled
 }
 
 // ── Focus::EditControl ─────────────────────────────────────────────────────────
 
-fn handle_edit_control_keys(app: &mut App, code: KeyCode) {
+fn handle_edit_control_keys(app: &mut App, code: KeyCode) -> KeyOutcome {
     let unavailable: Vec<usize> = app
         .edit_gate
         .as_ref()
@@ -618,45 +1188,64 @@ fn handle_edit_control_keys(app: &mut App, code: KeyCode) {
         }
         KeyCode::Enter => {
             let ci = app.edit_control_idx;
-            if let Some(g) = &mut app.edit_gate {
+            let qubit = app.target_qubit;
+            edit_qubits(app, |g| {
                 if ci == -1 {
-                    g.control = app.target_qubit as isize;
+                    g.control = qubit as isize;
                 } else if ci == -2 {
-                    // Adding a NEW control
-                    if g.control >= 0 {
-                        g.controls.push(g.control as usize);
-                        g.control = -1;
-                    }
-                    g.controls.push(app.target_qubit);
-
-                    // Update gate name if it's a standard one
-                    let utype = g.type_name.to_uppercase();
-                    if utype == "X" {
-                        g.type_name = "CX".to_string();
-                    } else if utype == "CX" {
-                        g.type_name = "CCX".to_string();
-                    } else if !utype.starts_with('C')
-                        && utype != "SWAP"
-                        && utype != "MEASURE"
-                        && utype != "BARRIER"
-                    {
-                        g.type_name = format!("C{}", g.type_name);
+                    // Adding a NEW control: the first is the single
+                    // `control`, a second turns both into `controls`
+                    let Some(controlled) = g.controlled_type() else {
+                        return;
+                    };
+                    if g.control < 0 && g.controls.is_empty() {
+                        g.control = qubit as isize;
+                    } else {
+                        if g.control >= 0 {
+                            g.controls.push(g.control as usize);
+                            g.control = -1;
+                        }
+                        g.controls.push(qubit);
                     }
+                    g.type_name = controlled.to_string();
                 } else if (ci as usize) < g.controls.len() {
-                    g.controls[ci as usize] = app.target_qubit;
+                    g.controls[ci as usize] = qubit;
                 }
-            }
-            commit_edit_to_dag(app);
+            });
             app.focus = Focus::EditGate;
         }
-        _ => {}
+        _ => return KeyOutcome::Ignored,
     }
+    KeyOutcome::Handled
 }
 
 // ── Edit commit helper ─────────────────────────────────────────────────────────
 
+/// Apply `change` to the qubits of the gate being edited and commit it,
+/// unless a control would land on the target or repeat another control; the
+/// gate is then left as it was.
+fn edit_qubits(app: &mut App, change: impl FnOnce(&mut Gate)) {
+    let Some(mut gate) = app.edit_gate.clone() else {
+        return;
+    };
+    let old_target = gate.target;
+    change(&mut gate);
+    if let Some(conflict) = gate.qubit_conflict() {
+        app.status_msg = conflict;
+        return;
+    }
+    // A new target no longer finds the original node, so drop it here
+    app.dag.remove_node_at(app.edit_orig_step, old_target);
+    app.edit_gate = Some(gate);
+    commit_edit_to_dag(app);
+}
+
 fn commit_edit_to_dag(app: &mut App) {
     if let Some(gate) = app.edit_gate.clone() {
+        if let Some(conflict) = gate.qubit_conflict() {
+            app.status_msg = conflict;
+            return;
+        }
         // Remove the old node
         app.dag.remove_node_at(app.edit_orig_step, gate.target);
 
@@ -709,8 +1298,146 @@ fn commit_edit_to_dag(app: &mut App) {
                 .add_gate(&gate.type_name, gate.target, app.edit_orig_step, None);
         }
 
+        app.dag
+            .set_node_noise(app.edit_orig_step, gate.target, gate.noise.clone());
+
         // Update edit_gate to reflect the new state
         app.edit_gate = Some(gate);
         app.sync_from_dag();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An app in the focus a help section describes, with the state its
+    /// guarded bindings need: a gate under the cursor, an offered autosave,
+    /// a playing simulation and so on.
+    fn app_in(mode: &str) -> App {
+        let mut app = App::new();
+        app.apply_settings(&config::Settings::default());
+        app.dag.add_gate("H", 0, 0, None);
+        app.dag.add_gate("H", 0, 1, None);
+        app.sync_from_dag();
+        app.cursor_step = 1;
+        app.recover_pending = true;
+        app.sim_auto = false;
+        app.playback = Playback::Playing;
+        app.pending_gate = "RX".to_string();
+        app.target_qubit = 1;
+        match mode {
+            "Circuit" => {}
+            "QASM editor" => app.focus = Focus::Qasm,
+            "Add Gate menu" => app.focus = Focus::Menu,
+            "Target / controls" => app.focus = Focus::SelectTarget,
+            "Edit gate" => {
+                handle_key(&mut app, KeyCode::Char('e'), KeyModifiers::NONE);
+                app.edit_menu_idx = 1;
+            }
+            "Prompts" => app.focus = Focus::InputParam,
+            "Barrier label" => app.focus = Focus::InputLabel,
+            "Qubit range" => app.focus = Focus::SelectRange,
+            "Region" => {
+                handle_key(&mut app, KeyCode::Char('X'), KeyModifiers::NONE);
+            }
+            "Observables" => app.focus = Focus::Observables,
+            "Histogram" => {
+                handle_key(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+            }
+            "Diff" => {
+                app.diff = Some(Vec::new());
+                app.focus = Focus::Diff;
+            }
+            "Help" => app.focus = Focus::Help,
+            _ => panic!("no focus for help section {mode}"),
+        }
+        assert!(
+            mode == "Circuit" || app.focus != Focus::Circuit,
+            "could not open {mode}"
+        );
+        app
+    }
+
+    #[test]
+    fn every_listed_key_is_handled() {
+        // Some bindings save or export into the working directory. The guard
+        // moves back out before deleting it, even if the test panics.
+        struct ScratchCwd {
+            previous: std::path::PathBuf,
+            dir: std::path::PathBuf,
+        }
+        impl Drop for ScratchCwd {
+            fn drop(&mut self) {
+                let _ = std::env::set_current_dir(&self.previous);
+                let _ = std::fs::remove_dir_all(&self.dir);
+            }
+        }
+        let dir = std::env::temp_dir().join(format!("q-deck-keys-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let _cwd = ScratchCwd {
+            previous: std::env::current_dir().unwrap(),
+            dir: dir.clone(),
+        };
+        std::env::set_current_dir(&dir).unwrap();
+
+        let mut unhandled = Vec::new();
+        for binding in help::KEYBINDINGS {
+            for (code, mods) in binding.key_events() {
+                let mut app = app_in(binding.mode);
+                if handle_key(&mut app, code, mods) == KeyOutcome::Ignored {
+                    unhandled.push(format!("{}: {code:?} ({})", binding.mode, binding.keys));
+                }
+            }
+        }
+        assert!(unhandled.is_empty(), "unhandled keys: {unhandled:#?}");
+    }
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            handle_key(app, KeyCode::Char(c), KeyModifiers::NONE);
+        }
+    }
+
+    #[test]
+    fn classical_bits_resize_but_not_below_those_in_use() {
+        let mut app = App::new();
+        app.apply_settings(&config::Settings::default());
+        app.dag.num_qubits = 2;
+        app.dag.add_gate("MEASURE", 0, 0, None);
+        app.sync_from_dag();
+        assert!(app.qasm_text.contains("creg c[1];"));
+
+        press(&mut app, ">>");
+        assert!(app.qasm_text.contains("creg c[3];"), "{}", app.qasm_text);
+        press(&mut app, "<<<<");
+        assert!(app.qasm_text.contains("creg c[1];"), "{}", app.qasm_text);
+        assert_eq!(app.status_msg, "Classical bits: 1 (all in use)");
+    }
+
+    #[test]
+    fn cx_edits_cannot_put_the_control_on_the_target() {
+        let mut app = App::new();
+        app.apply_settings(&config::Settings::default());
+        app.dag.num_qubits = 3;
+        app.dag.add_gate("CX", 1, 0, Some(0));
+        app.sync_from_dag();
+        let before = app.dag.to_qasm();
+        app.cursor_qubit = 1;
+        press(&mut app, "e");
+        assert_eq!(app.focus, Focus::EditGate);
+
+        app.focus = Focus::EditTarget;
+        app.target_qubit = 0;
+        handle_key(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(app.status_msg, "CX: q[0] cannot be both control and target");
+        assert_eq!(app.dag.to_qasm(), before);
+
+        app.focus = Focus::EditControl;
+        app.edit_control_idx = -1;
+        app.target_qubit = 1;
+        handle_key(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(app.status_msg, "CX: q[1] cannot be both control and target");
+        assert_eq!(app.dag.to_qasm(), before);
+    }
+}
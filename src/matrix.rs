@@ -57,6 +57,31 @@ impl UnitaryMatrix {
         }
         UnitaryMatrix { data, dim: n }
     }
+
+    /// Whether `other` equals this matrix times a global phase e^{iφ}.
+    pub fn equal_up_to_phase(&self, other: &UnitaryMatrix) -> bool {
+        const TOL: f64 = 1e-9;
+        if self.dim != other.dim {
+            return false;
+        }
+        let pivot = self
+            .data
+            .iter()
+            .flatten()
+            .zip(other.data.iter().flatten())
+            .find(|(a, _)| a.norm() > TOL);
+        let phase = match pivot {
+            Some((a, b)) => b / a,
+            None => return other.data.iter().flatten().all(|b| b.norm() <= TOL),
+        };
+        (phase.norm() - 1.0).abs() <= TOL
+            && self
+                .data
+                .iter()
+                .flatten()
+                .zip(other.data.iter().flatten())
+                .all(|(a, b)| (b - phase * a).norm() <= TOL)
+    }
 }
 
 // ── Single-qubit gate matrices (2x2) ──────────────────────────────────────────
@@ -120,6 +145,12 @@ pub fn gate_matrix_sx() -> UnitaryMatrix {
     mat2(half + hi, half - hi, half - hi, half + hi)
 }
 
+pub fn gate_matrix_sxdg() -> UnitaryMatrix {
+    let half = C64::new(0.5, 0.0);
+    let hi = C64::new(0.0, 0.5);
+    mat2(half - hi, half + hi, half + hi, half - hi)
+}
+
 pub fn gate_matrix_sy() -> UnitaryMatrix {
     let half = C64::new(0.5, 0.0);
     let hi = C64::new(0.0, 0.5);
@@ -260,7 +291,7 @@ fn single_qubit_matrix(gate_type: &str, params: &[f64], is_dagger: bool) -> Opti
         "X" => gate_matrix_x(),
         "Y" => gate_matrix_y(),
         "Z" => gate_matrix_z(),
-        "I" => gate_matrix_i(),
+        "I" | "ID" | "U0" => gate_matrix_i(),
         "S" => {
             if is_dagger {
                 gate_matrix_sdg()
@@ -277,7 +308,13 @@ fn single_qubit_matrix(gate_type: &str, params: &[f64], is_dagger: bool) -> Opti
             }
         }
         "TDG" | "Tdg" => gate_matrix_tdg(),
-        "SX" => gate_matrix_sx(),
+        "SX" => {
+            if is_dagger {
+                gate_matrix_sxdg()
+            } else {
+                gate_matrix_sx()
+            }
+        }
         "SY" => gate_matrix_sy(),
         "RX" => gate_matrix_rx(params.first().copied().unwrap_or(0.0)),
         "RY" => gate_matrix_ry(params.first().copied().unwrap_or(0.0)),
@@ -288,7 +325,7 @@ fn single_qubit_matrix(gate_type: &str, params: &[f64], is_dagger: bool) -> Opti
             let lambda = params.get(1).copied().unwrap_or(0.0);
             gate_matrix_u2(phi, lambda)
         }
-        "U3" => {
+        "U3" | "U" => {
             let theta = params.first().copied().unwrap_or(0.0);
             let phi = params.get(1).copied().unwrap_or(0.0);
             let lambda = params.get(2).copied().unwrap_or(0.0);
@@ -374,17 +411,17 @@ fn lift_swap_gate(q1: usize, q2: usize, num_qubits: usize) -> UnitaryMatrix {
     let n = 1 << num_qubits;
     let mut result = vec![vec![zero(); n]; n];
 
-    for i in 0..n {
-        // Swap bits q1 and q2
-        let b1 = (i >> q1) & 1;
-        let b2 = (i >> q2) & 1;
-        let mut j = i;
+    // SWAP is its own inverse, so row j has its 1 in the column of j with bits swapped
+    for (j, row) in result.iter_mut().enumerate() {
+        let b1 = (j >> q1) & 1;
+        let b2 = (j >> q2) & 1;
+        let mut i = j;
         // Clear bits q1, q2
-        j &= !((1 << q1) | (1 << q2));
+        i &= !((1 << q1) | (1 << q2));
         // Set swapped
-        j |= b2 << q1;
-        j |= b1 << q2;
-        result[j][i] = one();
+        i |= b2 << q1;
+        i |= b1 << q2;
+        row[i] = one();
     }
 
     UnitaryMatrix {
@@ -393,6 +430,37 @@ fn lift_swap_gate(q1: usize, q2: usize, num_qubits: usize) -> UnitaryMatrix {
     }
 }
 
+/// Lift an XX rotation (Mølmer–Sørensen) between q1 and q2 into the full n-qubit space.
+fn lift_rxx_gate(q1: usize, q2: usize, theta: f64, num_qubits: usize) -> UnitaryMatrix {
+    let n = 1 << num_qubits;
+    let mask = (1 << q1) | (1 << q2);
+    let c = C64::new((theta / 2.0).cos(), 0.0);
+    let js = C64::new(0.0, -(theta / 2.0).sin());
+    let mut result = vec![vec![zero(); n]; n];
+
+    for (i, row) in result.iter_mut().enumerate() {
+        row[i] = c;
+        row[i ^ mask] = js;
+    }
+
+    UnitaryMatrix {
+        data: result,
+        dim: n,
+    }
+}
+
+/// Lift a ZZ rotation between q1 and q2 into the full n-qubit space.
+fn lift_rzz_gate(q1: usize, q2: usize, theta: f64, num_qubits: usize) -> UnitaryMatrix {
+    let n = 1 << num_qubits;
+    let mut result = UnitaryMatrix::identity(n);
+
+    for (i, row) in result.data.iter_mut().enumerate() {
+        let odd = ((i >> q1) ^ (i >> q2)) & 1 == 1;
+        row[i] = C64::from_polar(1.0, if odd { theta / 2.0 } else { -theta / 2.0 });
+    }
+    result
+}
+
 /// Lift a Toffoli (CCX) gate with given controls and target into n-qubit space.
 fn lift_ccx_gate(controls: &[usize], target: usize, num_qubits: usize) -> UnitaryMatrix {
     let n = 1 << num_qubits;
@@ -559,7 +627,7 @@ fn build_gate_full_matrix(gate: &crate::circuit::Gate, num_qubits: usize) -> Opt
                 None
             }
         }
-        "CU1" => {
+        "CU1" | "CP" => {
             if gate.control >= 0 {
                 let lambda = gate.params.first().copied().unwrap_or(0.0);
                 let u = gate_matrix_u1(lambda);
@@ -584,6 +652,48 @@ fn build_gate_full_matrix(gate: &crate::circuit::Gate, num_qubits: usize) -> Opt
                 None
             }
         }
+        "MS" | "RXX" => {
+            if gate.control >= 0 {
+                let theta = gate.params.first().copied().unwrap_or(0.0);
+                Some(lift_rxx_gate(
+                    gate.control as usize,
+                    gate.target,
+                    theta,
+                    num_qubits,
+                ))
+            } else {
+                None
+            }
+        }
+        "CU3" => {
+            if gate.control >= 0 {
+                let theta = gate.params.first().copied().unwrap_or(0.0);
+                let phi = gate.params.get(1).copied().unwrap_or(0.0);
+                let lambda = gate.params.get(2).copied().unwrap_or(0.0);
+                let u = gate_matrix_u3(theta, phi, lambda);
+                Some(lift_controlled_gate(
+                    &u,
+                    gate.control as usize,
+                    gate.target,
+                    num_qubits,
+                ))
+            } else {
+                None
+            }
+        }
+        "RZZ" => {
+            if gate.control >= 0 {
+                let theta = gate.params.first().copied().unwrap_or(0.0);
+                Some(lift_rzz_gate(
+                    gate.control as usize,
+                    gate.target,
+                    theta,
+                    num_qubits,
+                ))
+            } else {
+                None
+            }
+        }
         "CCX" => {
             if !gate.controls.is_empty() {
                 Some(lift_ccx_gate(&gate.controls, gate.target, num_qubits))
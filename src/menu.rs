@@ -9,6 +9,7 @@ pub struct MenuItem {
     pub name: &'static str,
     pub gate_type: &'static str,
     pub symbol: &'static str,
+    pub description: &'static str,
     pub needs_target: bool,
     pub needs_params: bool,
     pub param_hint: Option<ParameterHint>,
@@ -28,6 +29,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Hadamard",
                 gate_type: "H",
                 symbol: "H",
+                description: "Creates equal superposition of |0⟩ and |1⟩",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -36,6 +38,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Pauli-X (NOT)",
                 gate_type: "X",
                 symbol: "X",
+                description: "Flips |0⟩ ↔ |1⟩ (bit flip)",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -44,6 +47,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Pauli-Y",
                 gate_type: "Y",
                 symbol: "Y",
+                description: "Bit and phase flip: rotates π about the Y axis",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -52,6 +56,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Pauli-Z",
                 gate_type: "Z",
                 symbol: "Z",
+                description: "Flips the phase of |1⟩ (phase flip)",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -60,6 +65,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Identity",
                 gate_type: "I",
                 symbol: "I",
+                description: "Does nothing; holds a qubit idle for a step",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -68,6 +74,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Phase (S)",
                 gate_type: "S",
                 symbol: "S",
+                description: "Quarter-turn phase: adds phase i to |1⟩",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -76,6 +83,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Phase Dagger (S†)",
                 gate_type: "SDG",
                 symbol: "S†",
+                description: "Inverse of S: adds phase -i to |1⟩",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -84,6 +92,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "T Gate",
                 gate_type: "T",
                 symbol: "T",
+                description: "Eighth-turn phase: adds phase e^(iπ/4) to |1⟩",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -92,6 +101,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "T Dagger (T†)",
                 gate_type: "TDG",
                 symbol: "T†",
+                description: "Inverse of T: adds phase e^(-iπ/4) to |1⟩",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -100,6 +110,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "√X (SX)",
                 gate_type: "SX",
                 symbol: "√X",
+                description: "Square root of X; two in a row make an X",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -108,6 +119,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "√Y (SY)",
                 gate_type: "SY",
                 symbol: "√Y",
+                description: "Square root of Y; two in a row make a Y",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -121,6 +133,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Rotate X",
                 gate_type: "RX",
                 symbol: "RX",
+                description: "Rotates the qubit by θ about the X axis",
                 needs_target: false,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -132,6 +145,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Rotate Y",
                 gate_type: "RY",
                 symbol: "RY",
+                description: "Rotates the qubit by θ about the Y axis",
                 needs_target: false,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -143,6 +157,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Rotate Z",
                 gate_type: "RZ",
                 symbol: "RZ",
+                description: "Rotates the qubit by θ about the Z axis",
                 needs_target: false,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -154,6 +169,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Phase Shift",
                 gate_type: "P",
                 symbol: "P",
+                description: "Adds phase e^(iθ) to |1⟩",
                 needs_target: false,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -165,6 +181,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Universal U1",
                 gate_type: "U1",
                 symbol: "U1",
+                description: "Phase gate: adds phase e^(iλ) to |1⟩",
                 needs_target: false,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -176,6 +193,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Universal U2",
                 gate_type: "U2",
                 symbol: "U2",
+                description: "Single-pulse gate U(π/2, φ, λ)",
                 needs_target: false,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -187,6 +205,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Universal U3",
                 gate_type: "U3",
                 symbol: "U3",
+                description: "General single-qubit rotation U(θ, φ, λ)",
                 needs_target: false,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -203,6 +222,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "CNOT",
                 gate_type: "CX",
                 symbol: "●─⊕",
+                description: "Flips the target when the control is |1⟩",
                 needs_target: true,
                 needs_params: false,
                 param_hint: None,
@@ -211,6 +231,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Controlled-Z",
                 gate_type: "CZ",
                 symbol: "●─●",
+                description: "Flips the phase of |11⟩; entangles two qubits",
                 needs_target: true,
                 needs_params: false,
                 param_hint: None,
@@ -219,6 +240,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Controlled-H",
                 gate_type: "CH",
                 symbol: "●─H",
+                description: "Applies Hadamard to the target when the control is |1⟩",
                 needs_target: true,
                 needs_params: false,
                 param_hint: None,
@@ -227,6 +249,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "SWAP",
                 gate_type: "SWAP",
                 symbol: "×─×",
+                description: "Exchanges the states of two qubits",
                 needs_target: true,
                 needs_params: false,
                 param_hint: None,
@@ -235,6 +258,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Toffoli (CCX)",
                 gate_type: "CCX",
                 symbol: "●─●─⊕",
+                description: "Flips the target when both controls are |1⟩",
                 needs_target: true,
                 needs_params: false,
                 param_hint: None,
@@ -243,6 +267,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "C-Rotate X",
                 gate_type: "CRX",
                 symbol: "●─RX",
+                description: "Rotates the target about X by θ when the control is |1⟩",
                 needs_target: true,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -254,6 +279,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "C-Rotate Y",
                 gate_type: "CRY",
                 symbol: "●─RY",
+                description: "Rotates the target about Y by θ when the control is |1⟩",
                 needs_target: true,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -265,6 +291,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "C-Rotate Z",
                 gate_type: "CRZ",
                 symbol: "●─RZ",
+                description: "Rotates the target about Z by θ when the control is |1⟩",
                 needs_target: true,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -273,9 +300,34 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 }),
             },
             MenuItem {
-                name: "C-Phase (CU1)",
+                name: "Mølmer–Sørensen",
+                gate_type: "MS",
+                symbol: "MS═MS",
+                description: "Trapped-ion XX interaction exp(-iθ/2·XX); π/2 entangles",
+                needs_target: true,
+                needs_params: true,
+                param_hint: Some(ParameterHint {
+                    required: true,
+                    example: "pi/2",
+                }),
+            },
+            MenuItem {
+                name: "C-Phase (CP)",
+                gate_type: "CP",
+                symbol: "●─P",
+                description: "Adds phase e^(iθ) to |11⟩",
+                needs_target: true,
+                needs_params: true,
+                param_hint: Some(ParameterHint {
+                    required: true,
+                    example: "lambda",
+                }),
+            },
+            MenuItem {
+                name: "C-U1 (CU1)",
                 gate_type: "CU1",
                 symbol: "●─U1",
+                description: "Adds phase e^(iλ) to |11⟩ (legacy name for CP)",
                 needs_target: true,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -283,6 +335,18 @@ pub static GATE_MENU: &[MenuCategory] = &[
                     example: "lambda",
                 }),
             },
+            MenuItem {
+                name: "C-U3 (CU3)",
+                gate_type: "CU3",
+                symbol: "●─U3",
+                description: "Applies U3(θ,φ,λ) to the target when the control is |1⟩",
+                needs_target: true,
+                needs_params: true,
+                param_hint: Some(ParameterHint {
+                    required: true,
+                    example: "theta,phi,lambda",
+                }),
+            },
         ],
     },
     MenuCategory {
@@ -292,6 +356,25 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Measure",
                 gate_type: "MEASURE",
                 symbol: "M",
+                description: "Measures the qubit into its classical bit",
+                needs_target: false,
+                needs_params: false,
+                param_hint: None,
+            },
+            MenuItem {
+                name: "Measure X",
+                gate_type: "MEASURE_X",
+                symbol: "Mx",
+                description: "Measures in the X basis (H then measure)",
+                needs_target: false,
+                needs_params: false,
+                param_hint: None,
+            },
+            MenuItem {
+                name: "Measure Y",
+                gate_type: "MEASURE_Y",
+                symbol: "My",
+                description: "Measures in the Y basis (S† and H, then measure)",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -300,6 +383,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Measure-Ctrl X",
                 gate_type: "MCX",
                 symbol: "M─⊕",
+                description: "Measures a qubit, then flips the target if the result is 1",
                 needs_target: true,
                 needs_params: false,
                 param_hint: None,
@@ -313,6 +397,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Reset",
                 gate_type: "RESET",
                 symbol: "|0⟩",
+                description: "Returns the qubit to |0⟩",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -321,6 +406,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Barrier",
                 gate_type: "BARRIER",
                 symbol: "┃",
+                description: "Stops gates from being reordered across this step",
                 needs_target: false,
                 needs_params: false,
                 param_hint: None,
@@ -334,6 +420,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Depolarizing",
                 gate_type: "NOISE_DEPOL",
                 symbol: "N",
+                description: "Replaces the state with a random Pauli error with probability p",
                 needs_target: false,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -345,6 +432,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Amplitude Damping",
                 gate_type: "NOISE_AMP",
                 symbol: "N",
+                description: "Energy loss: decays |1⟩ toward |0⟩ with probability γ",
                 needs_target: false,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -356,6 +444,7 @@ pub static GATE_MENU: &[MenuCategory] = &[
                 name: "Phase Damping",
                 gate_type: "NOISE_PHASE",
                 symbol: "N",
+                description: "Dephasing: erodes superpositions with probability λ",
                 needs_target: false,
                 needs_params: true,
                 param_hint: Some(ParameterHint {
@@ -367,6 +456,41 @@ pub static GATE_MENU: &[MenuCategory] = &[
     },
 ];
 
+/// Human-readable menu name for a gate type, e.g. "H" → "Hadamard".
+pub fn gate_name(gate_type: &str) -> Option<&'static str> {
+    find_menu_item(gate_type).map(|item| item.name)
+}
+
+/// The menu entry that places `gate_type`.
+pub fn find_menu_item(gate_type: &str) -> Option<&'static MenuItem> {
+    GATE_MENU
+        .iter()
+        .flat_map(|cat| cat.items.iter())
+        .find(|item| item.gate_type == gate_type)
+}
+
+/// Move `current` one place forward or back in a list of `len` entries.
+/// With `wrap`, stepping past either end lands on the other; without it the
+/// index stops at the first or last entry.
+pub fn step_index(current: usize, len: usize, forward: bool, wrap: bool) -> usize {
+    let last = len.saturating_sub(1);
+    match (forward, wrap) {
+        (true, _) if current < last => current + 1,
+        (true, true) => 0,
+        (true, false) => last,
+        (false, _) if current > 0 => current - 1,
+        (false, true) => last,
+        (false, false) => 0,
+    }
+}
+
+/// Whether a menu item matches the type-to-filter text (case-insensitive,
+/// against the display name or gate type). An empty filter matches everything.
+pub fn menu_item_matches(item: &MenuItem, filter: &str) -> bool {
+    let filter = filter.to_lowercase();
+    item.name.to_lowercase().contains(&filter) || item.gate_type.to_lowercase().contains(&filter)
+}
+
 pub fn is_parameterized_gate(gate_type: &str) -> bool {
     matches!(
         gate_type,
@@ -380,8 +504,42 @@ pub fn is_parameterized_gate(gate_type: &str) -> bool {
             | "CRY"
             | "CRZ"
             | "CU1"
+            | "CU3"
+            | "CP"
+            | "MS"
             | "NOISE_DEPOL"
             | "NOISE_AMP"
             | "NOISE_PHASE"
     )
 }
+
+/// How many parameters `gate_type` takes, or None for fixed gates.
+pub fn expected_param_count(gate_type: &str) -> Option<usize> {
+    match gate_type {
+        "U2" => Some(2),
+        "U3" | "CU3" => Some(3),
+        g if is_parameterized_gate(g) => Some(1),
+        _ => None,
+    }
+}
+
+/// Check `given` typed parameters against `expected_param_count`. None
+/// typed means the defaults and is always fine.
+pub fn validate_param_count(gate_type: &str, given: usize) -> Result<(), String> {
+    match expected_param_count(gate_type) {
+        Some(n) if given > 0 && given != n => {
+            let name = gate_name(gate_type).unwrap_or(gate_type);
+            let plural = if n == 1 { "" } else { "s" };
+            Err(format!("{name} takes {n} parameter{plural}, got {given}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Gates that act on the cursor qubit alone and can be laid down as a layer
+/// across every qubit. Barriers span the register and the X/Y-basis
+/// measurements open extra steps, so they are excluded.
+pub fn is_single_qubit_gate(gate_type: &str) -> bool {
+    find_menu_item(gate_type).is_some_and(|item| !item.needs_target)
+        && !matches!(gate_type, "BARRIER" | "MEASURE_X" | "MEASURE_Y")
+}
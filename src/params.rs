@@ -48,65 +48,70 @@ pub fn parse_param_expr(s: &str) -> Option<f64> {
     None
 }
 
+/// Largest denominator `format_param` tries when writing a value as a
+/// rational multiple of pi.
+const PI_MAX_DENOMINATOR: i64 = 12;
+
+/// Write `val` as `p*pi/q` in lowest terms when it is such a multiple
+/// (within 1e-10) for some q up to `PI_MAX_DENOMINATOR`, else as a decimal.
 pub fn format_param(val: f64) -> String {
-    struct PiForm {
-        value: f64,
-        display: &'static str,
-    }
+    format_pi_multiple(val).unwrap_or_else(|| format_decimal(val))
+}
 
-    let pi_forms = [
-        PiForm {
-            value: 2.0 * PI,
-            display: "2*pi",
-        },
-        PiForm {
-            value: PI,
-            display: "pi",
-        },
-        PiForm {
-            value: PI / 2.0,
-            display: "pi/2",
-        },
-        PiForm {
-            value: PI / 3.0,
-            display: "pi/3",
-        },
-        PiForm {
-            value: PI / 4.0,
-            display: "pi/4",
-        },
-        PiForm {
-            value: PI / 6.0,
-            display: "pi/6",
-        },
-        PiForm {
-            value: PI / 8.0,
-            display: "pi/8",
-        },
-        PiForm {
-            value: 3.0 * PI / 4.0,
-            display: "3*pi/4",
-        },
-        PiForm {
-            value: 3.0 * PI / 2.0,
-            display: "3*pi/2",
-        },
-        PiForm {
-            value: 2.0 * PI / 3.0,
-            display: "2*pi/3",
-        },
-    ];
-
-    for pf in &pi_forms {
-        if (val - pf.value).abs() < 1e-10 {
-            return pf.display.to_string();
-        }
-        if (val + pf.value).abs() < 1e-10 {
-            return format!("-{}", pf.display);
+fn format_pi_multiple(val: f64) -> Option<String> {
+    if val == 0.0 || !val.is_finite() {
+        return None;
+    }
+    // The smallest denominator that fits is already in lowest terms
+    (1..=PI_MAX_DENOMINATOR).find_map(|q| {
+        let p = (val / PI * q as f64).round() as i64;
+        if (p as f64 * PI / q as f64 - val).abs() >= 1e-10 {
+            return None;
         }
+        let sign = if p < 0 { "-" } else { "" };
+        let coeff = match p.abs() {
+            1 => String::new(),
+            n => format!("{n}*"),
+        };
+        let denom = if q == 1 {
+            String::new()
+        } else {
+            format!("/{q}")
+        };
+        Some(format!("{sign}{coeff}pi{denom}"))
+    })
+}
+
+/// Significant digits `format_param` keeps for values without a pi form.
+const PARAM_SIG_DIGITS: i32 = 6;
+
+/// `val` to `PARAM_SIG_DIGITS` significant digits without trailing zeros,
+/// so float noise such as 0.1 + 0.2 reads `0.3`. Magnitudes outside
+/// [1e-4, 1e6) use exponential notation (`1e-9`, `2.5e7`).
+fn format_decimal(val: f64) -> String {
+    if val == 0.0 {
+        return "0".to_string();
+    }
+    if !val.is_finite() {
+        return val.to_string();
+    }
+    let abs = val.abs();
+    if !(1e-4..1e6).contains(&abs) {
+        let s = format!("{:.*e}", (PARAM_SIG_DIGITS - 1) as usize, val);
+        let (mantissa, exp) = s.split_once('e').unwrap_or((&s, "0"));
+        return format!("{}e{exp}", trim_zeros(mantissa));
     }
+    let decimals = (PARAM_SIG_DIGITS - 1 - abs.log10().floor() as i32).max(0) as usize;
+    trim_zeros(&format!("{val:.decimals$}")).to_string()
+}
 
-    val.to_string()
+/// Drop trailing zeros after a decimal point, and the point if bare.
+fn trim_zeros(s: &str) -> &str {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        s
+    }
 }
 
 pub fn parse_params(input: &str) -> Option<Vec<f64>> {
@@ -124,3 +129,24 @@ pub fn parse_params(input: &str) -> Option<Vec<f64>> {
     }
     Some(params)
 }
+
+/// Live preview of a param input: the parsed values, "invalid", or a
+/// warning when an angle looks like a typo (|θ| > 4π).
+pub fn param_preview(input: &str) -> String {
+    if input.trim().is_empty() {
+        return String::new();
+    }
+    let Some(values) = parse_params(input) else {
+        return "invalid".to_string();
+    };
+    let shown = values
+        .iter()
+        .map(|v| format!("{v:.4}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if values.iter().any(|v| v.abs() > 4.0 * PI) {
+        format!("= {shown}  (warning: |θ| > 4π)")
+    } else {
+        format!("= {shown}")
+    }
+}
@@ -0,0 +1,97 @@
+//! Worker threads kept alive for the parallel gate kernels, so applying a
+//! gate hands work to threads that already exist instead of spawning new
+//! ones each time.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Jobs still running and whether any of them panicked.
+struct Pending {
+    state: Mutex<(usize, bool)>,
+    done: Condvar,
+}
+
+impl Pending {
+    fn finish(&self, panicked: bool) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.0 -= 1;
+        state.1 |= panicked;
+        if state.0 == 0 {
+            self.done.notify_all();
+        }
+    }
+}
+
+fn workers() -> &'static [Mutex<Sender<Job>>] {
+    static WORKERS: OnceLock<Vec<Mutex<Sender<Job>>>> = OnceLock::new();
+    WORKERS.get_or_init(|| {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        // The calling thread takes a share too
+        (1..cores)
+            .filter_map(|i| {
+                let (tx, rx) = mpsc::channel::<Job>();
+                std::thread::Builder::new()
+                    .name(format!("q-deck-worker-{i}"))
+                    .spawn(move || rx.into_iter().for_each(|job| job()))
+                    .ok()
+                    .map(|_| Mutex::new(tx))
+            })
+            .collect()
+    })
+}
+
+/// Threads a `run` call spreads its jobs over, the caller included.
+pub fn threads() -> usize {
+    workers().len() + 1
+}
+
+/// Run every job and return once all of them have finished: the first on
+/// the calling thread, the rest on the workers. A panic in any job is
+/// raised again here after the others are done.
+pub fn run<'a>(mut jobs: Vec<Box<dyn FnOnce() + Send + 'a>>) {
+    let Some(own) = (!jobs.is_empty()).then(|| jobs.remove(0)) else {
+        return;
+    };
+    let pending = Arc::new(Pending {
+        state: Mutex::new((jobs.len(), false)),
+        done: Condvar::new(),
+    });
+
+    for (i, job) in jobs.into_iter().enumerate() {
+        let pending_job = Arc::clone(&pending);
+        let wrapped: Box<dyn FnOnce() + Send + 'a> = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(job));
+            pending_job.finish(result.is_err());
+        });
+        // SAFETY: the job may borrow data that lives for 'a. `run` does not
+        // return until `pending` says every job has finished (panics are
+        // caught so a job always reports), so nothing outlives the borrow.
+        let wrapped: Job = unsafe { std::mem::transmute(wrapped) };
+        let workers = workers();
+        // With no worker to take it, the job runs here
+        match workers.get(i % workers.len().max(1)) {
+            Some(tx) => {
+                let sent = tx.lock().unwrap_or_else(|e| e.into_inner()).send(wrapped);
+                if let Err(mpsc::SendError(job)) = sent {
+                    job();
+                }
+            }
+            None => wrapped(),
+        }
+    }
+
+    let own_result = panic::catch_unwind(AssertUnwindSafe(own));
+    let mut state = pending.state.lock().unwrap_or_else(|e| e.into_inner());
+    while state.0 > 0 {
+        state = pending.done.wait(state).unwrap_or_else(|e| e.into_inner());
+    }
+    let worker_panicked = state.1;
+    drop(state);
+    if let Err(payload) = own_result {
+        panic::resume_unwind(payload);
+    }
+    assert!(!worker_panicked, "a parallel gate job panicked");
+}
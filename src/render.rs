@@ -1,16 +1,19 @@
 use ratatui::{
+    Frame,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
     widgets::{Block, Borders, Clear, Paragraph, Wrap},
-    Frame,
 };
 
-use crate::app::{App, Focus};
-use crate::circuit::{CellInfo, Circuit};
+use crate::app::{AmpFormat, App, Focus, Playback, WireStyle, display_row_qubit};
+use crate::circuit::{CellInfo, Circuit, Gate, gate_display_name};
+use crate::diff::{DiffLine, diff_counts};
+use crate::help::keybindings;
 use crate::matrix::{compute_circuit_unitary, format_complex};
-use crate::menu::GATE_MENU;
-use crate::quantum::simulate_circuit;
+use crate::menu::{GATE_MENU, find_menu_item, is_single_qubit_gate};
+use crate::params::{format_param, param_preview};
+use crate::quantum::{ComplexF64, MAX_DENSITY_QUBITS, QSphereState, StateVector};
 
 // ── Colors ─────────────────────────────────────────────────────────────────
 
@@ -23,12 +26,18 @@ const YELLOW: Color = Color::Rgb(224, 175, 104);
 const DIM: Color = Color::Rgb(86, 95, 137);
 const RED: Color = Color::Rgb(247, 118, 142);
 const DARK_BLUE: Color = Color::Rgb(192, 202, 245);
+const FRONTIER_BG: Color = Color::Rgb(41, 46, 66);
 
 // ── Layout constants ────────────────────────────────────────────────────────
 
 const CELL_W: usize = 11;
 const LABEL_W: usize = 7; // "q[N]  ──"
 const GATE_NAME_W: usize = 5;
+const QUBIT_PROB_W: usize = 9; // " P₁=0.50"
+
+/// Below this terminal size only a "too small" notice is drawn.
+const MIN_W: u16 = 20;
+const MIN_H: u16 = 8;
 
 // ── Main render entry point ─────────────────────────────────────────────────
 
@@ -37,13 +46,22 @@ pub fn render(f: &mut Frame, app: &mut App) {
     app.width = size.width;
     app.height = size.height;
 
+    if size.width < MIN_W || size.height < MIN_H {
+        let msg = format!("Terminal too small (need {MIN_W}×{MIN_H})");
+        let p = Paragraph::new(msg)
+            .style(Style::default().fg(YELLOW))
+            .wrap(Wrap { trim: true });
+        f.render_widget(p, size);
+        return;
+    }
+
     let ctrl_height = 3u16;
     let avail_h = size.height.saturating_sub(ctrl_height);
 
     // Left/Right split
     let qasm_w = ((size.width / 3) as usize)
         .max(30)
-        .min((size.width - 20) as usize) as u16;
+        .min(size.width.saturating_sub(20) as usize) as u16;
     let left_w = size.width.saturating_sub(qasm_w);
 
     let state_h = if avail_h < 20 { avail_h / 3 } else { 13 }
@@ -72,6 +90,10 @@ pub fn render(f: &mut Frame, app: &mut App) {
     render_circuit_panel(f, app, left_chunks[0]);
     if app.show_matrix {
         render_matrix_panel(f, app, left_chunks[1]);
+    } else if app.show_observables {
+        render_observables_panel(f, app, left_chunks[1]);
+    } else if app.show_listing {
+        render_listing_panel(f, app, left_chunks[1]);
     } else {
         render_state_panel(f, app, left_chunks[1]);
     }
@@ -81,8 +103,17 @@ pub fn render(f: &mut Frame, app: &mut App) {
     // Overlays
     match app.focus {
         Focus::Menu => render_menu_overlay(f, app),
-        Focus::InputParam | Focus::EditParam => render_param_input_overlay(f, app),
+        Focus::InputParam
+        | Focus::EditParam
+        | Focus::Command
+        | Focus::InputState
+        | Focus::InputLabel => render_param_input_overlay(f, app),
         Focus::EditGate => render_edit_gate_overlay(f, app),
+        Focus::Stats => render_stats_overlay(f, app),
+        Focus::Histogram => render_histogram_overlay(f, app),
+        Focus::Help => render_help_overlay(f, app),
+        Focus::Diff => render_diff_overlay(f, app),
+        Focus::InputPath => render_path_input_overlay(f, app),
         _ => {}
     }
 }
@@ -94,6 +125,9 @@ fn render_circuit_panel(f: &mut Frame, app: &mut App, area: Rect) {
         app.focus,
         Focus::Circuit
             | Focus::SelectTarget
+            | Focus::SelectSwap
+            | Focus::SelectRange
+            | Focus::SelectRegion
             | Focus::Menu
             | Focus::SelectControls
             | Focus::EditGate
@@ -127,147 +161,117 @@ fn build_circuit_lines(
     height: usize,
 ) -> Vec<Line<'static>> {
     let mut lines: Vec<Line<'static>> = Vec::new();
-    let wire_style = Style::default().fg(Color::White);
-
-    // Header line
-    let avail_w = width.saturating_sub(LABEL_W + 2);
-    let max_steps = (avail_w / CELL_W).max(1);
 
-    let start_step = if app.cursor_step >= max_steps as isize {
-        (app.cursor_step - max_steps as isize + 1) as usize
+    // Header line (the P₁ annotation column is reserved before fitting steps)
+    let annot_w = if app.show_qubit_probs {
+        QUBIT_PROB_W
     } else {
         0
     };
-
-    // Step numbers header
-    let mut step_hdr_spans = vec![Span::styled(" ".repeat(LABEL_W), wire_style)];
-    for step in start_step..start_step + max_steps {
-        step_hdr_spans.push(Span::styled(
-            pad_center(&format!("{step}"), CELL_W),
-            wire_style,
-        ));
-    }
-    lines.push(Line::from(step_hdr_spans));
+    let wrap_w = usize::from(app.wrap_circuit);
+    let avail_w = width.saturating_sub(LABEL_W + 2 + annot_w + wrap_w);
+    let max_steps = (avail_w / CELL_W).max(1);
 
     // Qubit rows (3 lines each)
     let num_cbits = circuit.num_cbits();
     let cbit_lines = if num_cbits > 0 { 2 } else { 0 };
     let status_lines = 1;
-    let header_lines = 1;
+    // Step numbers, plus a row of group brackets when any are defined
+    let header_lines =
+        1 + usize::from(!app.dag.groups.is_empty()) + usize::from(has_barrier_labels(circuit));
     let avail_h = height.saturating_sub(header_lines + cbit_lines + status_lines);
     let max_qubits = (avail_h / 3).max(1);
 
-    // Track which qubit is "active" for scrolling purposes
-    let active_qubit = if matches!(
-        app.focus,
-        Focus::SelectTarget | Focus::SelectControls | Focus::EditTarget | Focus::EditControl
-    ) {
-        app.target_qubit
-    } else {
-        app.cursor_qubit
+    let ctx = BlockContext {
+        // Simulation frontier: state panels reflect gates up to and including the cursor column
+        frontier: app.show_sim_frontier.then_some(app.cursor_step + 1),
+        wires: wire_glyphs(app.wire_style),
+        qubit_probs: if app.show_qubit_probs && app.simulation_limit_message().is_none() {
+            app.simulated_state()
+                .get_qubit_probabilities()
+                .iter()
+                .map(|p| p.prob1)
+                .collect()
+        } else {
+            Vec::new()
+        },
     };
 
-    // Keep active qubit in view
-    if active_qubit >= app.qubit_scroll + max_qubits {
-        app.qubit_scroll = active_qubit + 1 - max_qubits;
-    } else if active_qubit < app.qubit_scroll {
-        app.qubit_scroll = active_qubit;
-    }
-
-    let start_qubit = app.qubit_scroll;
-    let end_qubit = (start_qubit + max_qubits).min(circuit.num_qubits);
-
-    for qubit in start_qubit..end_qubit {
-        let mut top_line_spans = vec![Span::raw(" ".repeat(LABEL_W))];
-        let label = format!("q[{qubit}]");
-        let mut mid_line_spans = vec![
-            Span::styled(format!("{:<5}", label), wire_style),
-            Span::styled("──", wire_style),
-        ];
-        let mut bot_line_spans = vec![Span::raw(" ".repeat(LABEL_W))];
-
-        for step_idx in start_step..start_step + max_steps {
-            let step = step_idx as isize;
-            let info = circuit.get_cell_info(step, qubit);
-
-            let is_cursor = step == app.cursor_step
-                && qubit == app.cursor_qubit
-                && matches!(
-                    app.focus,
-                    Focus::Circuit
-                        | Focus::SelectTarget
-                        | Focus::Menu
-                        | Focus::SelectControls
-                        | Focus::EditGate
-                );
-
-            let current_step = if matches!(app.focus, Focus::EditTarget | Focus::EditControl) {
-                app.edit_orig_step
-            } else {
-                app.cursor_step
-            };
-            let is_target_sel = step == current_step
-                && qubit == app.target_qubit
-                && matches!(
-                    app.focus,
-                    Focus::SelectTarget
-                        | Focus::SelectControls
-                        | Focus::EditTarget
-                        | Focus::EditControl
-                );
-
-            let (top, mid, bot) = render_cell(&info, is_cursor, is_target_sel, qubit);
-            top_line_spans.extend(top);
-            mid_line_spans.extend(mid);
-            bot_line_spans.extend(bot);
+    // Wrapped layout: every block shows all qubits, so it needs them all to fit
+    let block_h = header_lines + 3 * circuit.num_qubits + cbit_lines;
+    let blocks_fit = height.saturating_sub(status_lines) / block_h.max(1);
+    let n = circuit.num_qubits;
+    let row_qubits = |rows: std::ops::Range<usize>| -> Vec<usize> {
+        rows.map(|row| display_row_qubit(row, n, app.reverse_qubits))
+            .collect()
+    };
+    let (start_row, end_row) = if app.wrap_circuit && blocks_fit > 0 {
+        let total_steps = circuit.max_steps + 1;
+        let blocks = visible_wrap_blocks(app.cursor_step, max_steps, blocks_fit, total_steps);
+        let last_block = total_steps
+            .div_ceil(max_steps)
+            .max(wrap_block(app.cursor_step, max_steps) + 1)
+            - 1;
+        for block in blocks {
+            let start = block * max_steps;
+            push_step_block(
+                &mut lines,
+                app,
+                circuit,
+                start..start + max_steps,
+                &row_qubits(0..n),
+                (block > 0, block < last_block),
+                &ctx,
+            );
         }
+        (0, n)
+    } else {
+        let start_step = if app.cursor_step >= max_steps as isize {
+            (app.cursor_step - max_steps as isize + 1) as usize
+        } else {
+            0
+        };
 
-        lines.push(Line::from(top_line_spans));
-        lines.push(Line::from(mid_line_spans));
-        lines.push(Line::from(bot_line_spans));
-    }
+        // Track which qubit is "active" for scrolling purposes
+        let active_qubit = if matches!(
+            app.focus,
+            Focus::SelectTarget
+                | Focus::SelectSwap
+                | Focus::SelectRange
+                | Focus::SelectControls
+                | Focus::EditTarget
+                | Focus::EditControl
+        ) {
+            app.target_qubit
+        } else {
+            app.cursor_qubit
+        };
 
-    // Classical bit wire
-    let num_cbits = circuit.num_cbits();
-    if num_cbits > 0 {
-        let mut sep_spans = vec![Span::raw(" ".repeat(LABEL_W))];
-        for step_idx in start_step..start_step + max_steps {
-            let mq = circuit.get_measure_at_step(step_idx as isize);
-            if mq >= 0 {
-                let half = CELL_W / 2;
-                sep_spans.push(Span::styled(" ".repeat(half), wire_style));
-                sep_spans.push(Span::styled("║", wire_style));
-                sep_spans.push(Span::styled(" ".repeat(CELL_W - half - 1), wire_style));
-            } else {
-                sep_spans.push(Span::styled(" ".repeat(CELL_W), wire_style));
-            }
+        // Keep active qubit in view (scrolling counts display rows)
+        let active_row = display_row_qubit(active_qubit, n, app.reverse_qubits);
+        if active_row >= app.qubit_scroll + max_qubits {
+            app.qubit_scroll = active_row + 1 - max_qubits;
+        } else if active_row < app.qubit_scroll {
+            app.qubit_scroll = active_row;
         }
-        lines.push(Line::from(sep_spans));
 
-        let cbit_label = format!("c{num_cbits}");
-        let mut cbit_line_spans = vec![
-            Span::styled(format!("{:<5}", cbit_label), wire_style),
-            Span::styled("══", wire_style),
-        ];
-        for step_idx in start_step..start_step + max_steps {
-            let mq = circuit.get_measure_at_step(step_idx as isize);
-            if mq >= 0 {
-                let bit_label = format!("{mq}");
-                let dash_l = (CELL_W - 1) / 2;
-                let dash_r = CELL_W.saturating_sub(dash_l + 1 + bit_label.len());
-                cbit_line_spans.push(Span::styled("═".repeat(dash_l), wire_style));
-                cbit_line_spans.push(Span::styled(format!("╩{bit_label}"), wire_style));
-                cbit_line_spans.push(Span::styled("═".repeat(dash_r), wire_style));
-            } else {
-                cbit_line_spans.push(Span::styled("═".repeat(CELL_W), wire_style));
-            }
-        }
-        lines.push(Line::from(cbit_line_spans));
-    }
+        let start_row = app.qubit_scroll;
+        let end_row = (start_row + max_qubits).min(n);
+        push_step_block(
+            &mut lines,
+            app,
+            circuit,
+            start_step..start_step + max_steps,
+            &row_qubits(start_row..end_row),
+            (false, false),
+            &ctx,
+        );
+        (start_row, end_row)
+    };
 
     // Status / position line
-    let more_above = start_qubit > 0;
-    let more_below = end_qubit < circuit.num_qubits;
+    let more_above = start_row > 0;
+    let more_below = end_row < n;
     let scroll_msg = if more_above && more_below {
         "  (↑↓ More qubits)"
     } else if more_above {
@@ -288,12 +292,66 @@ fn build_circuit_lines(
                     ),
                     Style::default().fg(YELLOW),
                 ),
+                Span::styled(
+                    format!(
+                        "  ↑↓ Move  Enter Confirm  n New ancilla  Esc Cancel{}",
+                        scroll_msg
+                    ),
+                    Style::default().fg(DIM),
+                ),
+            ]));
+        }
+        Focus::SelectSwap => {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!(
+                        "  Swap q[{}] with: q[{}]",
+                        app.cursor_qubit, app.target_qubit
+                    ),
+                    Style::default().fg(YELLOW),
+                ),
                 Span::styled(
                     format!("  ↑↓ Move  Enter Confirm  Esc Cancel{}", scroll_msg),
                     Style::default().fg(DIM),
                 ),
             ]));
         }
+        Focus::SelectRange => {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!(
+                        "  {} on q[{}]–q[{}]",
+                        app.pending_gate,
+                        app.cursor_qubit.min(app.target_qubit),
+                        app.cursor_qubit.max(app.target_qubit)
+                    ),
+                    Style::default().fg(YELLOW),
+                ),
+                Span::styled(
+                    format!("  ↑↓ Extend  Enter Place  Esc Cancel{}", scroll_msg),
+                    Style::default().fg(DIM),
+                ),
+            ]));
+        }
+        Focus::SelectRegion => {
+            let (steps, qubits) = app.region();
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!(
+                        "  Region: steps {}–{}, q[{}]–q[{}]",
+                        steps.start(),
+                        steps.end(),
+                        qubits.start(),
+                        qubits.end()
+                    ),
+                    Style::default().fg(YELLOW),
+                ),
+                Span::styled(
+                    format!("  Arrows Extend  d Delete  Esc Cancel{}", scroll_msg),
+                    Style::default().fg(DIM),
+                ),
+            ]));
+        }
         Focus::SelectControls => {
             lines.push(Line::from(vec![
                 Span::styled(
@@ -344,10 +402,29 @@ fn build_circuit_lines(
                 ),
                 Span::styled(scroll_msg, Style::default().fg(DIM)),
             ];
+            match app.playback {
+                Playback::Playing => {
+                    status_spans.push(Span::styled("  ▶ Playing", Style::default().fg(CYAN)))
+                }
+                Playback::Paused => {
+                    status_spans.push(Span::styled("  ⏸ Paused", Style::default().fg(CYAN)))
+                }
+                Playback::Off => {}
+            }
+            if app.input_state != 0 {
+                status_spans.push(Span::styled(
+                    format!(
+                        "  Input {}",
+                        format_basis_ket(app.input_state, app.dag.num_qubits)
+                    ),
+                    Style::default().fg(CYAN),
+                ));
+            }
             if !app.status_msg.is_empty() {
+                let color = if app.status_is_error { RED } else { YELLOW };
                 status_spans.push(Span::styled(
                     format!("  │  {}", app.status_msg),
-                    Style::default().fg(YELLOW),
+                    Style::default().fg(color),
                 ));
             }
             lines.push(Line::from(status_spans));
@@ -357,19 +434,354 @@ fn build_circuit_lines(
     lines
 }
 
+/// Per-frame drawing state shared by every block of steps.
+struct BlockContext {
+    frontier: Option<isize>,
+    wires: WireGlyphs,
+    qubit_probs: Vec<f64>,
+}
+
+/// Block of `per_block` steps that holds `step` in the wrapped layout.
+fn wrap_block(step: isize, per_block: usize) -> usize {
+    step.max(0) as usize / per_block.max(1)
+}
+
+/// Blocks shown in the wrapped layout: up to `fit` blocks, scrolled so the
+/// one holding `cursor_step` is on screen.
+fn visible_wrap_blocks(
+    cursor_step: isize,
+    per_block: usize,
+    fit: usize,
+    total_steps: usize,
+) -> std::ops::Range<usize> {
+    let cursor_block = wrap_block(cursor_step, per_block);
+    let total_blocks = total_steps.div_ceil(per_block.max(1)).max(cursor_block + 1);
+    let first = (cursor_block + 1).saturating_sub(fit.max(1));
+    first..(first + fit.max(1)).min(total_blocks)
+}
+
+/// Row of labelled brackets over the step columns each group spans, e.g.
+/// `┌─ QFT ──────┐`. Groups running off either edge have no corner there.
+fn group_bracket_line(
+    groups: &[(isize, isize, String)],
+    steps: std::ops::Range<usize>,
+) -> Line<'static> {
+    let mut row: Vec<char> = vec![' '; steps.len() * CELL_W];
+    let (first, last) = (steps.start as isize, steps.end as isize - 1);
+    for (start, end, label) in groups {
+        if *end < first || *start > last {
+            continue;
+        }
+        let from = ((*start).max(first) - first) as usize * CELL_W + 1;
+        let to = ((*end).min(last) - first + 1) as usize * CELL_W - 2;
+        for cell in &mut row[from..=to] {
+            *cell = '─';
+        }
+        if *start >= first {
+            row[from] = '┌';
+        }
+        if *end <= last {
+            row[to] = '┐';
+        }
+        let text: Vec<char> = format!(" {label} ").chars().collect();
+        let room = (to - from).saturating_sub(2);
+        for (i, ch) in text.into_iter().take(room).enumerate() {
+            row[from + 2 + i] = ch;
+        }
+    }
+    Line::from(vec![
+        Span::raw(" ".repeat(LABEL_W)),
+        Span::styled(
+            row.into_iter().collect::<String>(),
+            Style::default().fg(CYAN),
+        ),
+    ])
+}
+
+fn has_barrier_labels(circuit: &Circuit) -> bool {
+    circuit
+        .gates
+        .iter()
+        .any(|g| g.type_name == "BARRIER" && g.label.is_some())
+}
+
+/// Row of barrier captions, each centred over its barrier's column and cut
+/// to the column width.
+fn barrier_label_line(circuit: &Circuit, steps: std::ops::Range<usize>) -> Line<'static> {
+    let mut text = String::new();
+    for step in steps {
+        let label = circuit
+            .gates
+            .iter()
+            .find(|g| g.step == step as isize && g.type_name == "BARRIER")
+            .and_then(|g| g.label.as_deref())
+            .unwrap_or("");
+        let cut: String = label.chars().take(CELL_W - 1).collect();
+        text.push_str(&pad_center(&cut, CELL_W));
+    }
+    Line::from(vec![
+        Span::raw(" ".repeat(LABEL_W)),
+        Span::styled(
+            text,
+            Style::default().fg(DIM).add_modifier(Modifier::ITALIC),
+        ),
+    ])
+}
+
+/// Step header, qubit rows and classical wire for one run of steps. `cont`
+/// marks wires continued from the previous block (`«`) and onto the next (`»`).
+fn push_step_block(
+    lines: &mut Vec<Line<'static>>,
+    app: &App,
+    circuit: &Circuit,
+    steps: std::ops::Range<usize>,
+    qubits: &[usize],
+    cont: (bool, bool),
+    ctx: &BlockContext,
+) {
+    let wire_style = Style::default().fg(Color::White);
+    let (cont_before, cont_after) = cont;
+    let frontier = ctx.frontier;
+    let wires = ctx.wires;
+    let range_sel = (app.focus == Focus::SelectRange)
+        .then(|| app.cursor_qubit.min(app.target_qubit)..=app.cursor_qubit.max(app.target_qubit));
+    let region_sel = (app.focus == Focus::SelectRegion).then(|| app.region());
+    let lead = |h: &str| {
+        if cont_before {
+            format!("«{h}")
+        } else {
+            h.repeat(2)
+        }
+    };
+
+    let cursor_shown = matches!(
+        app.focus,
+        Focus::Circuit
+            | Focus::SelectTarget
+            | Focus::SelectSwap
+            | Focus::SelectRange
+            | Focus::SelectRegion
+            | Focus::Menu
+            | Focus::SelectControls
+            | Focus::EditGate
+    );
+    let cursor_style = Style::default().fg(ORANGE).add_modifier(Modifier::BOLD);
+
+    // Step numbers header, with the cursor's step picked out
+    let mut step_hdr_spans = vec![Span::styled(" ".repeat(LABEL_W), wire_style)];
+    for step in steps.clone() {
+        step_hdr_spans.push(Span::styled(
+            pad_center(&format!("{step}"), CELL_W),
+            if cursor_shown && step as isize == app.cursor_step {
+                cursor_style
+            } else {
+                wire_style
+            },
+        ));
+    }
+    lines.push(Line::from(step_hdr_spans));
+    if !app.dag.groups.is_empty() {
+        lines.push(group_bracket_line(&app.dag.groups, steps.clone()));
+    }
+    if has_barrier_labels(circuit) {
+        lines.push(barrier_label_line(circuit, steps.clone()));
+    }
+
+    for &qubit in qubits {
+        let mut top_line_spans = vec![Span::raw(" ".repeat(LABEL_W))];
+        // Custom names are cut to the label column
+        let label = match app.dag.qubit_labels.get(&qubit) {
+            Some(name) => name.chars().take(LABEL_W - 2).collect(),
+            None => format!("q[{qubit}]"),
+        };
+        let swapping = app.focus == Focus::SelectSwap
+            && (qubit == app.cursor_qubit || qubit == app.target_qubit);
+        let label_style = if swapping {
+            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+        } else if cursor_shown && qubit == app.cursor_qubit {
+            cursor_style
+        } else if app.dag.is_ancilla(qubit) {
+            Style::default().fg(DIM)
+        } else {
+            wire_style
+        };
+        let mut mid_line_spans = vec![
+            Span::styled(format!("{:<5}", label), label_style),
+            Span::styled(lead(wires.h), wire_style),
+        ];
+        let mut bot_line_spans = vec![Span::raw(" ".repeat(LABEL_W))];
+
+        for step_idx in steps.clone() {
+            let step = step_idx as isize;
+            let info = if app.reverse_qubits {
+                circuit.get_cell_info_reversed(step, qubit)
+            } else {
+                circuit.get_cell_info(step, qubit)
+            };
+
+            let is_cursor = cursor_shown && step == app.cursor_step && qubit == app.cursor_qubit;
+
+            let current_step = if matches!(app.focus, Focus::EditTarget | Focus::EditControl) {
+                app.edit_orig_step
+            } else {
+                app.cursor_step
+            };
+            let is_target_sel = step == current_step
+                && qubit == app.target_qubit
+                && matches!(
+                    app.focus,
+                    Focus::SelectTarget
+                        | Focus::SelectSwap
+                        | Focus::SelectControls
+                        | Focus::EditTarget
+                        | Focus::EditControl
+                )
+                || (step == app.cursor_step
+                    && range_sel.as_ref().is_some_and(|r| r.contains(&qubit)))
+                || region_sel
+                    .as_ref()
+                    .is_some_and(|(s, q)| s.contains(&step) && q.contains(&qubit));
+
+            let (mut top, mut mid, mut bot) =
+                render_cell(&info, is_cursor, is_target_sel, qubit, wires);
+            if frontier == Some(step) {
+                for row in [&mut top, &mut mid, &mut bot] {
+                    shade_leading_char(row);
+                }
+            }
+            top_line_spans.extend(top);
+            mid_line_spans.extend(mid);
+            bot_line_spans.extend(bot);
+        }
+
+        if cont_after {
+            mid_line_spans.push(Span::styled("»", wire_style));
+        } else if let Some(&p1) = ctx.qubit_probs.get(qubit) {
+            mid_line_spans.push(Span::styled(
+                format_qubit_prob(p1),
+                Style::default().fg(CYAN),
+            ));
+        }
+
+        lines.push(Line::from(top_line_spans));
+        lines.push(Line::from(mid_line_spans));
+        lines.push(Line::from(bot_line_spans));
+    }
+
+    // Classical bit wire
+    let num_cbits = circuit.num_cbits();
+    if num_cbits > 0 {
+        let mut sep_spans = vec![Span::raw(" ".repeat(LABEL_W))];
+        for step_idx in steps.clone() {
+            let mq = circuit.get_measure_at_step(step_idx as isize);
+            let mut cell = if mq >= 0 {
+                let half = CELL_W / 2;
+                vec![
+                    Span::styled(" ".repeat(half), wire_style),
+                    Span::styled("║", wire_style),
+                    Span::styled(" ".repeat(CELL_W - half - 1), wire_style),
+                ]
+            } else {
+                vec![Span::styled(" ".repeat(CELL_W), wire_style)]
+            };
+            if frontier == Some(step_idx as isize) {
+                shade_leading_char(&mut cell);
+            }
+            sep_spans.extend(cell);
+        }
+        lines.push(Line::from(sep_spans));
+
+        let cbit_label = format!("c{num_cbits}");
+        let mut cbit_line_spans = vec![
+            Span::styled(format!("{:<5}", cbit_label), wire_style),
+            Span::styled(lead("═"), wire_style),
+        ];
+        for step_idx in steps.clone() {
+            let mq = circuit.get_measure_at_step(step_idx as isize);
+            let mut cell = if mq >= 0 {
+                let bit_label = format!("{mq}");
+                let dash_l = (CELL_W - 1) / 2;
+                let dash_r = CELL_W.saturating_sub(dash_l + 1 + bit_label.len());
+                vec![
+                    Span::styled("═".repeat(dash_l), wire_style),
+                    Span::styled(format!("╩{bit_label}"), wire_style),
+                    Span::styled("═".repeat(dash_r), wire_style),
+                ]
+            } else {
+                vec![Span::styled("═".repeat(CELL_W), wire_style)]
+            };
+            if frontier == Some(step_idx as isize) {
+                shade_leading_char(&mut cell);
+            }
+            cbit_line_spans.extend(cell);
+        }
+        if cont_after {
+            cbit_line_spans.push(Span::styled("»", wire_style));
+        }
+        lines.push(Line::from(cbit_line_spans));
+    }
+}
+
+/// Glyphs used to draw a quantum wire and the joints that sit on it.
+#[derive(Clone, Copy)]
+struct WireGlyphs {
+    h: &'static str,
+    cross: &'static str,
+    meas_cross: &'static str,
+    box_l: &'static str,
+    box_r: &'static str,
+}
+
+fn wire_glyphs(style: WireStyle) -> WireGlyphs {
+    match style {
+        WireStyle::Single => WireGlyphs {
+            h: "─",
+            cross: "┼",
+            meas_cross: "╫",
+            box_l: "┤",
+            box_r: "├",
+        },
+        WireStyle::Double => WireGlyphs {
+            h: "═",
+            cross: "╪",
+            meas_cross: "╬",
+            box_l: "╡",
+            box_r: "╞",
+        },
+    }
+}
+
+/// Box colour by gate family: single-qubit blue, rotations purple,
+/// multi-qubit green, measurement red, noise orange.
+fn gate_color(type_name: &str) -> Color {
+    match type_name {
+        "MEASURE" | "MCX" => RED,
+        "NOISE" => ORANGE,
+        "RX" | "RY" | "RZ" | "P" | "U1" | "U2" | "U3" | "U" | "CRX" | "CRY" | "CRZ" | "CP"
+        | "CU1" | "CU3" | "MS" | "RXX" | "RZZ" => PURPLE,
+        "SWAP" => GREEN,
+        t if t.starts_with('C') => GREEN,
+        _ => BLUE,
+    }
+}
+
 fn render_cell(
     info: &CellInfo,
     is_cursor: bool,
     is_target_sel: bool,
     qubit: usize,
+    w: WireGlyphs,
 ) -> (Vec<Span<'static>>, Vec<Span<'static>>, Vec<Span<'static>>) {
     let half = CELL_W / 2;
     let dash_l_len = (CELL_W - 1) / 2;
     let dash_r_len = CELL_W - dash_l_len - 1;
 
     let wire_style = Style::default().fg(Color::White);
-    let gate_style = Style::default().fg(BLUE);
-    let measure_style = Style::default().fg(YELLOW);
+    let gate_style = Style::default().fg(info
+        .gate
+        .as_ref()
+        .map_or(BLUE, |g| gate_color(&g.type_name)));
+    let measure_style = Style::default().fg(gate_color("MEASURE"));
 
     let vert_row = vec![
         Span::styled(" ".repeat(half), wire_style),
@@ -393,9 +805,9 @@ fn render_cell(
         if info.is_barrier {
             let mid = vec![
                 Span::styled("║", sel_style),
-                Span::styled("─".repeat(dleft), wire_style),
+                Span::styled(w.h.repeat(dleft), wire_style),
                 Span::styled("│", wire_style),
-                Span::styled("─".repeat(dright), wire_style),
+                Span::styled(w.h.repeat(dright), wire_style),
                 Span::styled("║", sel_style),
             ];
             return (vert_row.clone(), mid, vert_row.clone());
@@ -416,41 +828,41 @@ fn render_cell(
         if let Some(gate) = &info.gate {
             if info.is_control {
                 let sym = control_symbol(&gate.type_name);
-                mid.push(Span::styled("─".repeat(dleft), wire_style));
+                mid.push(Span::styled(w.h.repeat(dleft), wire_style));
                 mid.push(Span::styled(sym, gate_style));
-                mid.push(Span::styled("─".repeat(dright), wire_style));
+                mid.push(Span::styled(w.h.repeat(dright), wire_style));
             } else if info.is_target && is_symbol_gate(&gate.type_name) {
                 let sym = target_symbol(&gate.type_name);
-                mid.push(Span::styled("─".repeat(dleft), wire_style));
+                mid.push(Span::styled(w.h.repeat(dleft), wire_style));
                 mid.push(Span::styled(sym, gate_style));
-                mid.push(Span::styled("─".repeat(dright), wire_style));
+                mid.push(Span::styled(w.h.repeat(dright), wire_style));
             } else if info.is_target
                 || (gate.measure_source < 0
                     && gate.type_name != "MEASURE"
                     && gate.type_name != "BARRIER")
             {
-                let name = pad_center(&gate_display_name(&gate.type_name), GATE_NAME_W);
-                mid.push(Span::styled("─", wire_style));
-                mid.push(Span::styled("┤", gate_style));
+                let name = pad_center(&gate_box_label(gate), GATE_NAME_W);
+                mid.push(Span::styled(w.h, wire_style));
+                mid.push(Span::styled(w.box_l, gate_style));
                 mid.push(Span::styled(name, gate_style));
-                mid.push(Span::styled("├", gate_style));
-                mid.push(Span::styled("─", wire_style));
+                mid.push(Span::styled(w.box_r, gate_style));
+                mid.push(Span::styled(w.h, wire_style));
             } else if gate.measure_source >= 0 {
                 let is_m = gate.measure_source as usize == qubit;
                 let sym = if is_m { "M" } else { "⊕" };
                 let style = if is_m { measure_style } else { gate_style };
-                mid.push(Span::styled("─".repeat(dleft), wire_style));
+                mid.push(Span::styled(w.h.repeat(dleft), wire_style));
                 mid.push(Span::styled(sym, style));
-                mid.push(Span::styled("─".repeat(dright), wire_style));
+                mid.push(Span::styled(w.h.repeat(dright), wire_style));
             } else {
-                mid.push(Span::styled("─".repeat(inner_w), wire_style));
+                mid.push(Span::styled(w.h.repeat(inner_w), wire_style));
             }
         } else if info.pass_through {
-            mid.push(Span::styled("─".repeat(dleft), wire_style));
-            mid.push(Span::styled("┼", wire_style));
-            mid.push(Span::styled("─".repeat(dright), wire_style));
+            mid.push(Span::styled(w.h.repeat(dleft), wire_style));
+            mid.push(Span::styled(w.cross, wire_style));
+            mid.push(Span::styled(w.h.repeat(dright), wire_style));
         } else {
-            mid.push(Span::styled("─".repeat(inner_w), wire_style));
+            mid.push(Span::styled(w.h.repeat(inner_w), wire_style));
         }
         mid.push(Span::styled("║", sel_style));
 
@@ -460,9 +872,9 @@ fn render_cell(
     // Normal cells
     if info.is_barrier {
         let mid = vec![
-            Span::styled("─".repeat(dash_l_len), wire_style),
+            Span::styled(w.h.repeat(dash_l_len), wire_style),
             Span::styled("│", wire_style),
-            Span::styled("─".repeat(dash_r_len), wire_style),
+            Span::styled(w.h.repeat(dash_r_len), wire_style),
         ];
         return (vert_row.clone(), mid, vert_row.clone());
     }
@@ -476,9 +888,9 @@ fn render_cell(
             };
             let sym = control_symbol(&gate.type_name);
             let mid = vec![
-                Span::styled("─".repeat(dash_l_len), wire_style),
+                Span::styled(w.h.repeat(dash_l_len), wire_style),
                 Span::styled(sym, gate_style),
-                Span::styled("─".repeat(dash_r_len), wire_style),
+                Span::styled(w.h.repeat(dash_r_len), wire_style),
             ];
             let bot = if info.measure_below {
                 dbl_vert_row.clone()
@@ -498,9 +910,9 @@ fn render_cell(
                 };
                 let sym = target_symbol(&gate.type_name);
                 let mid = vec![
-                    Span::styled("─".repeat(dash_l_len), wire_style),
+                    Span::styled(w.h.repeat(dash_l_len), wire_style),
                     Span::styled(sym, gate_style),
-                    Span::styled("─".repeat(dash_r_len), wire_style),
+                    Span::styled(w.h.repeat(dash_r_len), wire_style),
                 ];
                 let bot = if info.measure_below {
                     dbl_vert_row.clone()
@@ -514,7 +926,7 @@ fn render_cell(
                 // Controlled gate box
                 let margin = (CELL_W - GATE_NAME_W - 2) / 2;
                 let rmargin = CELL_W - margin - GATE_NAME_W - 2;
-                let name = pad_center(&gate_display_name(&gate.type_name), GATE_NAME_W);
+                let name = pad_center(&gate_box_label(gate), GATE_NAME_W);
                 let top = vec![
                     Span::styled(" ".repeat(margin), wire_style),
                     Span::styled(if info.vert_above { "┬" } else { "┌" }, gate_style),
@@ -523,11 +935,11 @@ fn render_cell(
                     Span::styled(" ".repeat(rmargin), wire_style),
                 ];
                 let mid = vec![
-                    Span::styled("─".repeat(margin), wire_style),
-                    Span::styled("┤", gate_style),
+                    Span::styled(w.h.repeat(margin), wire_style),
+                    Span::styled(w.box_l, gate_style),
                     Span::styled(name, gate_style),
-                    Span::styled("├", gate_style),
-                    Span::styled("─".repeat(rmargin), wire_style),
+                    Span::styled(w.box_r, gate_style),
+                    Span::styled(w.h.repeat(rmargin), wire_style),
                 ];
                 let bot = if info.measure_below {
                     dbl_vert_row.clone()
@@ -535,7 +947,7 @@ fn render_cell(
                     vec![
                         Span::styled(" ".repeat(margin), wire_style),
                         Span::styled(if info.vert_below { "┴" } else { "└" }, gate_style),
-                        Span::styled("─".repeat(GATE_NAME_W), gate_style),
+                        Span::styled(gate_box_floor(gate), gate_style),
                         Span::styled(if info.vert_below { "┴" } else { "┘" }, gate_style),
                         Span::styled(" ".repeat(rmargin), wire_style),
                     ]
@@ -555,11 +967,11 @@ fn render_cell(
                     Span::styled(" ".repeat(rmargin), wire_style),
                 ];
                 let mid = vec![
-                    Span::styled("─".repeat(margin), wire_style),
-                    Span::styled("┤", measure_style),
+                    Span::styled(w.h.repeat(margin), wire_style),
+                    Span::styled(w.box_l, measure_style),
                     Span::styled(pad_center("M", GATE_NAME_W), measure_style),
-                    Span::styled("├", measure_style),
-                    Span::styled("─".repeat(rmargin), wire_style),
+                    Span::styled(w.box_r, measure_style),
+                    Span::styled(w.h.repeat(rmargin), wire_style),
                 ];
                 let bot = if info.measure_below {
                     dbl_vert_row.clone()
@@ -580,9 +992,9 @@ fn render_cell(
                     empty_row.clone()
                 };
                 let mid = vec![
-                    Span::styled("─".repeat(dash_l_len), wire_style),
+                    Span::styled(w.h.repeat(dash_l_len), wire_style),
                     Span::styled("⊕", gate_style),
-                    Span::styled("─".repeat(dash_r_len), wire_style),
+                    Span::styled(w.h.repeat(dash_r_len), wire_style),
                 ];
                 let bot = if info.measure_below {
                     dbl_vert_row.clone()
@@ -605,11 +1017,11 @@ fn render_cell(
                 Span::styled(" ".repeat(rmargin), wire_style),
             ];
             let mid = vec![
-                Span::styled("─".repeat(margin), wire_style),
-                Span::styled("┤", measure_style),
+                Span::styled(w.h.repeat(margin), wire_style),
+                Span::styled(w.box_l, measure_style),
                 Span::styled(pad_center("M", GATE_NAME_W), measure_style),
-                Span::styled("├", measure_style),
-                Span::styled("─".repeat(rmargin), wire_style),
+                Span::styled(w.box_r, measure_style),
+                Span::styled(w.h.repeat(rmargin), wire_style),
             ];
             let bot = vec![
                 Span::styled(" ".repeat(margin), wire_style),
@@ -623,20 +1035,20 @@ fn render_cell(
         // Normal single-qubit gate box
         let margin = (CELL_W - GATE_NAME_W - 2) / 2;
         let rmargin = CELL_W - margin - GATE_NAME_W - 2;
-        let name = pad_center(&gate_display_name(&gate.type_name), GATE_NAME_W);
+        let name = pad_center(&gate_box_label(gate), GATE_NAME_W);
         let top = vec![
             Span::styled(" ".repeat(margin), wire_style),
             Span::styled("┌", gate_style),
-            Span::styled("─".repeat(GATE_NAME_W), gate_style),
+            Span::styled(gate_box_edge(gate).repeat(GATE_NAME_W), gate_style),
             Span::styled("┐", gate_style),
             Span::styled(" ".repeat(rmargin), wire_style),
         ];
         let mid = vec![
-            Span::styled("─".repeat(margin), wire_style),
-            Span::styled("┤", gate_style),
+            Span::styled(w.h.repeat(margin), wire_style),
+            Span::styled(w.box_l, gate_style),
             Span::styled(name, gate_style),
-            Span::styled("├", gate_style),
-            Span::styled("─".repeat(rmargin), wire_style),
+            Span::styled(w.box_r, gate_style),
+            Span::styled(w.h.repeat(rmargin), wire_style),
         ];
         let bot = if info.measure_below {
             dbl_vert_row.clone()
@@ -644,7 +1056,7 @@ fn render_cell(
             vec![
                 Span::styled(" ".repeat(margin), wire_style),
                 Span::styled("└", gate_style),
-                Span::styled("─".repeat(GATE_NAME_W), gate_style),
+                Span::styled(gate_box_floor(gate), gate_style),
                 Span::styled("┘", gate_style),
                 Span::styled(" ".repeat(rmargin), wire_style),
             ]
@@ -654,9 +1066,9 @@ fn render_cell(
 
     if info.pass_through {
         let mid = vec![
-            Span::styled("─".repeat(dash_l_len), wire_style),
-            Span::styled("┼", wire_style),
-            Span::styled("─".repeat(dash_r_len), wire_style),
+            Span::styled(w.h.repeat(dash_l_len), wire_style),
+            Span::styled(w.cross, wire_style),
+            Span::styled(w.h.repeat(dash_r_len), wire_style),
         ];
         let bot = if info.measure_below {
             dbl_vert_row.clone()
@@ -673,9 +1085,9 @@ fn render_cell(
             dbl_vert_row.clone()
         };
         let mid = vec![
-            Span::styled("─".repeat(dash_l_len), wire_style),
-            Span::styled("╫", wire_style),
-            Span::styled("─".repeat(dash_r_len), wire_style),
+            Span::styled(w.h.repeat(dash_l_len), wire_style),
+            Span::styled(w.meas_cross, wire_style),
+            Span::styled(w.h.repeat(dash_r_len), wire_style),
         ];
         return (top, mid, dbl_vert_row.clone());
     }
@@ -686,7 +1098,7 @@ fn render_cell(
     } else {
         empty_row.clone()
     };
-    let mid = vec![Span::styled("─".repeat(CELL_W), wire_style)];
+    let mid = vec![Span::styled(w.h.repeat(CELL_W), wire_style)];
     let bot = if info.vert_below {
         vert_row.clone()
     } else {
@@ -695,8 +1107,25 @@ fn render_cell(
     (top, mid, bot)
 }
 
-fn control_symbol(gate_type: &str) -> String {
-    if gate_type == "SWAP" {
+/// Tint the first character of a cell row to draw the simulation frontier.
+fn shade_leading_char(row: &mut Vec<Span<'static>>) {
+    let Some(first) = row.first() else {
+        return;
+    };
+    let content = first.content.to_string();
+    let Some(ch) = content.chars().next() else {
+        return;
+    };
+    let style = first.style;
+    let rest = content[ch.len_utf8()..].to_string();
+    row[0] = Span::styled(ch.to_string(), style.bg(FRONTIER_BG));
+    if !rest.is_empty() {
+        row.insert(1, Span::styled(rest, style));
+    }
+}
+
+fn control_symbol(gate_type: &str) -> String {
+    if gate_type == "SWAP" {
         "×".to_string()
     } else {
         "●".to_string()
@@ -716,26 +1145,102 @@ fn target_symbol(gate_type: &str) -> String {
     }
 }
 
-fn gate_display_name(gate_type: &str) -> String {
-    match gate_type {
-        "MEASURE" => "M".to_string(),
-        "CX" | "CCX" | "MCX" => "X".to_string(),
-        "CZ" => "Z".to_string(),
-        "CH" => "H".to_string(),
-        "CU1" | "CP" => "U1".to_string(),
-        "CRX" => "RX".to_string(),
-        "CRY" => "RY".to_string(),
-        "CRZ" => "RZ".to_string(),
-        other => {
-            if other.starts_with('C') && other.len() > 1 && other != "CONTROL" {
-                other[1..].to_string()
+/// The circuit view's symbols and what each means, listed after the
+/// keybindings in the help overlay. Control, target and swap glyphs come from
+/// `control_symbol`/`target_symbol` and wire joints from the current wire
+/// style, so the key matches what is on screen.
+pub fn symbol_legend(style: WireStyle) -> Vec<(String, &'static str)> {
+    let w = wire_glyphs(style);
+    vec![
+        (control_symbol("CX"), "Control qubit"),
+        (target_symbol("CX"), "X on the target of a CX, CCX or MCX"),
+        (control_symbol("SWAP"), "Each end of a SWAP"),
+        (w.h.to_string(), "Qubit wire"),
+        ("│".to_string(), "Joins a gate's qubits; a barrier"),
+        (w.cross.to_string(), "Gate line passing over a qubit"),
+        (
+            format!("{}H{}", w.box_l, w.box_r),
+            "Gate box (~ after the name: noise)",
+        ),
+        ("╌".to_string(), "Dashed box edge: u0 delay"),
+        ("M".to_string(), "Measurement"),
+        ("═".to_string(), "Classical register wire"),
+        ("║".to_string(), "Measurement result going to the register"),
+        (w.meas_cross.to_string(), "Result line passing over a qubit"),
+        ("╩k".to_string(), "Result stored in classical bit k"),
+        ("»".to_string(), "Circuit continues on the next row"),
+    ]
+}
+
+/// Compact parameter text for a gate box, e.g. "π/2" or "0.25".
+/// Returns None when the gate has no params or the text would not fit.
+fn gate_param_caption(gate: &Gate) -> Option<String> {
+    if gate.is_noise || gate.params.is_empty() {
+        return None;
+    }
+    let text = gate
+        .params
+        .iter()
+        .map(|&p| {
+            let formatted = format_param(p);
+            if formatted.parse::<f64>().is_ok() {
+                let rounded = format!("{p:.2}");
+                rounded
+                    .trim_end_matches('0')
+                    .trim_end_matches('.')
+                    .to_string()
             } else {
-                other.to_string()
+                formatted.replace('*', "").replace("pi", "π")
             }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    (text.chars().count() <= GATE_NAME_W).then_some(text)
+}
+
+/// Gate name for a box, marked with "~" when noise is attached to the gate.
+fn gate_box_name(gate: &Gate) -> String {
+    let name = gate_display_name(&gate.type_name);
+    if gate.noise.is_some() {
+        format!("{name}~")
+    } else {
+        name
+    }
+}
+
+/// Label for the middle row of a gate box: "P(π)" when the params fit
+/// alongside the name, otherwise just the name.
+fn gate_box_label(gate: &Gate) -> String {
+    let name = gate_box_name(gate);
+    if let Some(caption) = gate_param_caption(gate) {
+        let full = format!("{name}({caption})");
+        if full.chars().count() <= GATE_NAME_W {
+            return full;
+        }
+    }
+    name
+}
+
+/// Bottom edge of a gate box, carrying the param caption when the middle
+/// row only had room for the name.
+fn gate_box_floor(gate: &Gate) -> String {
+    let edge = gate_box_edge(gate);
+    match gate_param_caption(gate) {
+        Some(caption) if gate_box_label(gate) == gate_box_name(gate) => {
+            let total = GATE_NAME_W - caption.chars().count();
+            let left = total / 2;
+            edge.repeat(left) + &caption + &edge.repeat(total - left)
         }
+        _ => edge.repeat(GATE_NAME_W),
     }
 }
 
+/// Horizontal edge of a gate box; a `u0` delay is drawn dashed since it
+/// only idles the qubit.
+fn gate_box_edge(gate: &Gate) -> &'static str {
+    if gate.type_name == "U0" { "╌" } else { "─" }
+}
+
 fn pad_center(s: &str, width: usize) -> String {
     let len = s.chars().count();
     if len >= width {
@@ -749,13 +1254,26 @@ fn pad_center(s: &str, width: usize) -> String {
 
 // ── State / Probabilities Panel ───────────────────────────────────────────────
 
-fn render_state_panel(f: &mut Frame, app: &App, area: Rect) {
+fn render_state_panel(f: &mut Frame, app: &mut App, area: Rect) {
     let border_color = { RED };
-    let title = if app.show_statevector {
-        "Statevector"
+    let base_title = if app.noise_enabled {
+        "Probabilities (noisy)"
+    } else if app.show_statevector {
+        match app.amp_format {
+            AmpFormat::Rectangular => "Statevector",
+            AmpFormat::Polar => "Statevector (polar)",
+            AmpFormat::Magnitude => "Statevector (magnitude)",
+        }
     } else {
         "Probabilities"
     };
+    let title = if app.needs_recompute() {
+        format!("{base_title} [stale, . to refresh]")
+    } else if !app.sim_auto {
+        format!("{base_title} [frozen]")
+    } else {
+        base_title.to_string()
+    };
 
     let block = Block::default()
         .borders(Borders::ALL)
@@ -768,9 +1286,20 @@ fn render_state_panel(f: &mut Frame, app: &App, area: Rect) {
     let inner = block.inner(area);
     f.render_widget(block, area);
 
+    if let Some(msg) = app.simulation_limit_message() {
+        f.render_widget(
+            Paragraph::new(Line::styled(msg, Style::default().fg(DIM))),
+            inner,
+        );
+        return;
+    }
+
     let circuit = app.circuit();
-    let state = simulate_circuit(&circuit, app.cursor_step);
-    let mut qsphere = state.get_qsphere_states();
+    let mut state = app.simulated_state();
+    if app.canonical_phase {
+        state.canonicalize_phase();
+    }
+    let mut qsphere = state.get_qsphere_states_with_threshold(app.amp_threshold);
     qsphere.sort_by(|a, b| {
         b.prob
             .partial_cmp(&a.prob)
@@ -780,20 +1309,15 @@ fn render_state_panel(f: &mut Frame, app: &App, area: Rect) {
     let num_qubits = circuit.num_qubits.max(app.dag.num_qubits).max(1);
     let mut text_lines: Vec<Line> = Vec::new();
 
-    if app.show_statevector {
+    if app.show_statevector && !app.noise_enabled {
         // Statevector view: show complex amplitudes
         let display_count = qsphere.len().min(16);
         for s in qsphere.iter().take(display_count) {
             let state_str = format_basis_state(s.basis_state, num_qubits);
-            let re = s.amplitude.re;
-            let im = s.amplitude.im;
-            let sign = if im >= 0.0 { '+' } else { '-' };
             let line_str = format!(
-                "{}  α={:+.4}{}{:.4}i  P={:.4}  φ={:.4}",
+                "{}  α={}  P={:.4}  φ={:.4}",
                 state_str,
-                re,
-                sign,
-                im.abs(),
+                format_amplitude(s.amplitude, app.amp_format),
                 s.prob,
                 s.phase
             );
@@ -812,44 +1336,76 @@ fn render_state_panel(f: &mut Frame, app: &App, area: Rect) {
             text_lines.push(Line::default());
             text_lines.push(Line::styled(
                 format!(
-                    "Top: {} ({:.1}%)  {} non-zero",
+                    "Top: {} ({:.1}%)  {} with P>{:.0e}",
                     format_basis_state(top.basis_state, num_qubits),
                     top.prob * 100.0,
-                    qsphere.len()
+                    qsphere.len(),
+                    app.amp_threshold
                 ),
                 Style::default().fg(DIM),
             ));
         }
     } else {
-        // Probabilities view: show bar chart
-        let bar_width = (inner.width as usize).saturating_sub(30).max(10);
+        // Probabilities view: show bar chart, from ρ's diagonal when noise is on
+        let noisy = if app.noise_enabled {
+            app.noisy_probabilities()
+        } else {
+            None
+        };
+        if app.noise_enabled && noisy.is_none() {
+            text_lines.push(Line::styled(
+                format!("Noise needs ≤{} qubits; showing ideal", MAX_DENSITY_QUBITS),
+                Style::default().fg(DIM),
+            ));
+        }
+        // Mixed states from ρ carry no phase, so only pure states get a glyph
+        let mut probs: Vec<(usize, f64, Option<f64>)> = match noisy {
+            Some(diag) => diag
+                .into_iter()
+                .enumerate()
+                .filter(|(_, p)| *p > app.amp_threshold)
+                .map(|(i, p)| (i, p, None))
+                .collect(),
+            None => qsphere
+                .iter()
+                .map(|s| (s.basis_state, s.prob, Some(s.phase)))
+                .collect(),
+        };
+        probs.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
 
-        let display_count = qsphere.len().min(16);
-        for s in qsphere.iter().take(display_count) {
-            let fill = ((s.prob * bar_width as f64).round() as usize).min(bar_width);
+        let bar_width = (inner.width as usize).saturating_sub(32).max(10);
+
+        let display_count = probs.len().min(16);
+        for &(basis_state, prob, phase) in probs.iter().take(display_count) {
+            let fill = ((prob * bar_width as f64).round() as usize).min(bar_width);
             let empty = bar_width - fill;
             let bar = "█".repeat(fill) + &"░".repeat(empty);
-            let state_str = format_basis_state(s.basis_state, num_qubits);
-            let line_str = format!("{}: P={:.2} [{}]", state_str, s.prob, bar);
+            let state_str = format_basis_state(basis_state, num_qubits);
+            let mut line_str = format!("{}: P={:.2} [{}]", state_str, prob, bar);
+            if let Some(phase) = phase {
+                line_str.push(' ');
+                line_str.push(phase_glyph(phase));
+            }
             text_lines.push(Line::styled(line_str, Style::default().fg(YELLOW)));
         }
 
-        if qsphere.len() > 16 {
+        if probs.len() > 16 {
             text_lines.push(Line::styled(
-                format!("... and {} more states", qsphere.len() - 16),
+                format!("... and {} more states", probs.len() - 16),
                 Style::default().fg(DIM),
             ));
         }
 
         // Footer
-        if let Some(top) = qsphere.first() {
+        if let Some(&(basis_state, prob, _)) = probs.first() {
             text_lines.push(Line::default());
             text_lines.push(Line::styled(
                 format!(
-                    "Top: {} ({:.1}%)  {} non-zero",
-                    format_basis_state(top.basis_state, num_qubits),
-                    top.prob * 100.0,
-                    qsphere.len()
+                    "Top: {} ({:.1}%)  {} with P>{:.0e}",
+                    format_basis_state(basis_state, num_qubits),
+                    prob * 100.0,
+                    probs.len(),
+                    app.amp_threshold
                 ),
                 Style::default().fg(DIM),
             ));
@@ -860,6 +1416,14 @@ fn render_state_panel(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(p, inner);
 }
 
+/// Arrow pointing along a relative phase, snapped to the nearest 45°:
+/// 0 → `→`, π/2 → `↑`, π → `←`, −π/2 → `↓`.
+fn phase_glyph(phase: f64) -> char {
+    const ARROWS: [char; 8] = ['→', '↗', '↑', '↖', '←', '↙', '↓', '↘'];
+    let sector = (phase / std::f64::consts::FRAC_PI_4).round() as i64;
+    ARROWS[sector.rem_euclid(8) as usize]
+}
+
 fn format_basis_state(state: usize, num_qubits: usize) -> String {
     let mut s = String::from("|");
     for i in (0..num_qubits).rev() {
@@ -869,6 +1433,116 @@ fn format_basis_state(state: usize, num_qubits: usize) -> String {
     s
 }
 
+// ── Listing Panel ──────────────────────────────────────────────────────────────
+
+fn render_listing_panel(f: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(RED))
+        .title(Span::styled(
+            "Circuit Listing",
+            Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+        ));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let listing = app.dag.to_listing();
+    if listing.is_empty() {
+        let p = Paragraph::new(Line::styled("(empty circuit)", Style::default().fg(DIM)));
+        f.render_widget(p, inner);
+        return;
+    }
+
+    // Scroll so the lines for the cursor column stay in view
+    let height = inner.height as usize;
+    let first_at_cursor = listing
+        .iter()
+        .position(|(step, _)| *step >= app.cursor_step)
+        .unwrap_or(listing.len() - 1);
+    let scroll = first_at_cursor.saturating_sub(height.saturating_sub(1) / 2);
+
+    let text_lines: Vec<Line> = listing
+        .iter()
+        .skip(scroll)
+        .take(height)
+        .map(|(step, line)| {
+            let color = if *step == app.cursor_step { CYAN } else { DIM };
+            Line::styled(line.clone(), Style::default().fg(color))
+        })
+        .collect();
+
+    f.render_widget(Paragraph::new(Text::from(text_lines)), inner);
+}
+
+// ── Observables Panel ─────────────────────────────────────────────────────────
+
+fn render_observables_panel(f: &mut Frame, app: &mut App, area: Rect) {
+    let active = app.focus == Focus::Observables;
+    let border_color = if active { ORANGE } else { RED };
+    let title = if active {
+        "Observables [EDITING]"
+    } else {
+        "Observables"
+    };
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(border_color))
+        .title(Span::styled(
+            title,
+            Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+        ));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if let Some(msg) = app.simulation_limit_message() {
+        f.render_widget(
+            Paragraph::new(Line::styled(msg, Style::default().fg(DIM))),
+            inner,
+        );
+        return;
+    }
+
+    let state = app.simulated_state();
+    let observables: Vec<&str> = app.observables_input.split('\n').collect();
+    let values = state.expectation_values(&observables);
+
+    let obs_w = observables
+        .iter()
+        .map(|o| o.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(8);
+    let mut text_lines: Vec<Line> = Vec::new();
+    let last = observables.len() - 1;
+
+    for (i, (obs, val)) in observables.iter().zip(&values).enumerate() {
+        let cursor = if active && i == last { "_" } else { "" };
+        let label = pad_to_width(&format!("{obs}{cursor}"), obs_w + 1);
+        let value = match val {
+            Some(v) => Span::styled(format!("⟨·⟩ = {:+.4}", v), Style::default().fg(CYAN)),
+            None if obs.trim().is_empty() => Span::raw(""),
+            None => Span::styled("invalid", Style::default().fg(RED)),
+        };
+        text_lines.push(Line::from(vec![
+            Span::styled(label, Style::default().fg(DARK_BLUE)),
+            Span::raw("  "),
+            value,
+        ]));
+    }
+
+    text_lines.push(Line::default());
+    text_lines.push(Line::styled(
+        "One Pauli string per line (e.g. Z0, Z0Z1, X0 Y2)  Esc Done",
+        Style::default().fg(DIM),
+    ));
+
+    let p = Paragraph::new(Text::from(text_lines));
+    f.render_widget(p, inner);
+}
+
 // ── Matrix Panel ──────────────────────────────────────────────────────────────
 
 fn render_matrix_panel(f: &mut Frame, app: &mut App, area: Rect) {
@@ -1065,6 +1739,45 @@ fn format_basis_bra(state: usize, num_qubits: usize) -> String {
     s
 }
 
+/// An amplitude in the statevector view's format, to three decimals.
+/// Components that round to zero are dropped, so `0.707+0.000i` reads
+/// `0.707` and a zero phase leaves just the magnitude.
+fn format_amplitude(z: ComplexF64, fmt: AmpFormat) -> String {
+    let tol = 5e-4;
+    let component = |v: f64| if v.abs() < tol { 0.0 } else { v };
+    match fmt {
+        AmpFormat::Rectangular => {
+            let (re, im) = (component(z.re), component(z.im));
+            match (re == 0.0, im == 0.0) {
+                (true, true) => "0".to_string(),
+                (false, true) => format!("{re:.3}"),
+                (true, false) => format!("{im:.3}i"),
+                (false, false) => format!("{re:.3}{im:+.3}i"),
+            }
+        }
+        AmpFormat::Polar => {
+            let r = component(z.norm());
+            let theta = component(z.arg().to_degrees());
+            if r == 0.0 {
+                "0".to_string()
+            } else if theta.abs() < 0.05 {
+                format!("{r:.3}")
+            } else {
+                format!("{r:.3}∠{theta:.1}°")
+            }
+        }
+        AmpFormat::Magnitude => match component(z.norm()) {
+            0.0 => "0".to_string(),
+            r => format!("{r:.3}"),
+        },
+    }
+}
+
+/// Wire-end annotation with a qubit's marginal P(|1⟩).
+fn format_qubit_prob(p1: f64) -> String {
+    format!(" P₁={:.2}", p1.clamp(0.0, 1.0))
+}
+
 fn pad_to_width(s: &str, width: usize) -> String {
     let len = s.chars().count();
     if len >= width {
@@ -1125,10 +1838,19 @@ fn render_qasm_panel(f: &mut Frame, app: &mut App, area: Rect) {
         for (i, line_str) in text_lines.iter().enumerate().skip(scroll).take(inner_h) {
             let is_error = app.qasm_errors.iter().any(|(line_idx, _)| *line_idx == i);
             let base_style = if is_error {
-                Style::default().fg(Color::Red)
+                Style::default().fg(Color::White).bg(Color::Red)
             } else {
                 Style::default().fg(DARK_BLUE)
             };
+            // Gutter: a marker beside lines that failed to parse
+            let gutter = if is_error {
+                Span::styled(
+                    "✗ ",
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Span::raw("  ")
+            };
 
             if i == cursor_row {
                 let safe_col = cursor_col.min(line_str.len());
@@ -1141,12 +1863,16 @@ fn render_qasm_panel(f: &mut Frame, app: &mut App, area: Rect) {
                     (" ", "")
                 };
                 lines.push(Line::from(vec![
+                    gutter,
                     Span::styled(before, base_style),
                     Span::styled(cur_ch, Style::default().fg(Color::Black).bg(ORANGE)),
                     Span::styled(after, base_style),
                 ]));
             } else {
-                lines.push(Line::styled(*line_str, base_style));
+                lines.push(Line::from(vec![
+                    gutter,
+                    Span::styled(*line_str, base_style),
+                ]));
             }
         }
 
@@ -1162,7 +1888,7 @@ fn render_qasm_panel(f: &mut Frame, app: &mut App, area: Rect) {
 
 // ── Controls Panel ─────────────────────────────────────────────────────────────
 
-fn render_controls_panel(f: &mut Frame, app: &App, area: Rect) {
+fn render_controls_panel(f: &mut Frame, app: &mut App, area: Rect) {
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(GREEN));
@@ -1171,8 +1897,8 @@ fn render_controls_panel(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(block, area);
 
     let mut help = match app.focus {
-        Focus::Qasm => "QASM:  Tab Exit editor  Type to edit  q Quit".to_string(),
-        _ => "Nav: ↑↓/jk Qubit  ←→/hl Step  +/- Qubits  a Add gate  Tab Focus  Bksp Del  e Edit  v Statevec  m Matrix  Ctrl+S Save  q Quit".to_string(),
+        Focus::Qasm => "QASM:  Tab Complete gate / Exit editor  Type to edit  Ctrl+F Reformat  q Quit".to_string(),
+        _ => "Nav: ↑↓/jk Qubit  ←→/hl Step  +/- Qubits  a Add gate  : Command  Tab Focus  Bksp Del  e Edit  v Statevec  m Matrix  Ctrl+S Save  ? Help  q Quit".to_string(),
     };
 
     if app.focus == Focus::Qasm {
@@ -1182,21 +1908,63 @@ fn render_controls_panel(f: &mut Frame, app: &App, area: Rect) {
         }
     }
 
-    let p = Paragraph::new(Span::styled(help, Style::default().fg(YELLOW)));
+    let mut spans = Vec::new();
+    if app.show_summary {
+        let gate_count = app.circuit().gate_count();
+        let summary = if app.simulation_limit_message().is_some() {
+            format!("{gate_count} gates")
+        } else {
+            state_summary(&app.simulated_state(), gate_count)
+        };
+        spans.push(Span::styled(summary, Style::default().fg(CYAN)));
+        spans.push(Span::styled(" │ ", Style::default().fg(DIM)));
+    }
+    spans.push(Span::styled(help, Style::default().fg(YELLOW)));
+    let p = Paragraph::new(Line::from(spans));
     f.render_widget(p, inner);
 }
 
+/// Controls-row summary: the most likely basis state (the lowest on a tie)
+/// and its probability, the Shannon entropy of a full measurement in bits,
+/// and the gate count.
+fn state_summary(state: &StateVector, gate_count: usize) -> String {
+    let states = state.get_qsphere_states();
+    let top = states
+        .iter()
+        .fold(None::<&QSphereState>, |best, s| match best {
+            Some(b) if b.prob >= s.prob => Some(b),
+            _ => Some(s),
+        });
+    let entropy = 0.0 - states.iter().map(|s| s.prob * s.prob.log2()).sum::<f64>();
+    let dominant = top.map_or_else(String::new, |s| {
+        format!(
+            "{} {:.1}%  ",
+            format_basis_state(s.basis_state, state.num_qubits),
+            100.0 * s.prob
+        )
+    });
+    format!("{dominant}H={entropy:.2} bits  {gate_count} gates")
+}
+
 // ── Menu Overlay ──────────────────────────────────────────────────────────────
 
 fn render_menu_overlay(f: &mut Frame, app: &App) {
     let area = overlay_rect(f.area(), 75, 20);
     f.render_widget(Clear, area);
 
+    let mut title = if app.menu_filter.is_empty() {
+        "Add Gate".to_string()
+    } else {
+        format!("Add Gate [filter: {}_]", app.menu_filter)
+    };
+    if app.apply_all_qubits {
+        title.push_str(" [all qubits]");
+    }
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(ORANGE))
         .title(Span::styled(
-            "Add Gate",
+            title,
             Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
         ));
 
@@ -1205,11 +1973,28 @@ fn render_menu_overlay(f: &mut Frame, app: &App) {
 
     let mut lines: Vec<Line> = Vec::new();
 
+    // Recently placed gates, picked with 1-5
+    if !app.recent_gates.is_empty() {
+        let mut recent_line = vec![Span::styled(" Recent:", Style::default().fg(DIM))];
+        for (i, gate) in app.recent_gates.iter().enumerate() {
+            let symbol = find_menu_item(gate).map_or(gate.as_str(), |item| item.symbol);
+            recent_line.push(Span::styled(
+                format!("  {}", i + 1),
+                Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+            ));
+            recent_line.push(Span::styled(
+                format!(" {symbol}"),
+                Style::default().fg(CYAN),
+            ));
+        }
+        lines.push(Line::from(recent_line));
+    }
+
     // Category tabs
     let mut cat_line: Vec<Span> = Vec::new();
     for (i, cat) in GATE_MENU.iter().enumerate() {
         let name = format!(" {} ", cat.name);
-        if i == app.menu_cat {
+        if i == app.menu_cat && app.menu_filter.is_empty() {
             cat_line.push(Span::styled(
                 name,
                 Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
@@ -1224,9 +2009,17 @@ fn render_menu_overlay(f: &mut Frame, app: &App) {
     lines.push(Line::from(cat_line));
     lines.push(Line::styled("─".repeat(42), Style::default().fg(DIM)));
 
-    // Items
-    let cat = &GATE_MENU[app.menu_cat];
-    for (i, item) in cat.items.iter().enumerate() {
+    // Items, scrolled to keep the selection visible
+    let items = app.menu_items();
+    let visible = 11;
+    let offset = app.menu_item.saturating_sub(visible - 1);
+    if items.is_empty() {
+        lines.push(Line::styled(
+            "   (no matching gates)",
+            Style::default().fg(DIM),
+        ));
+    }
+    for (i, item) in items.iter().enumerate().skip(offset).take(visible) {
         let mut spans: Vec<Span> = Vec::new();
         if i == app.menu_item {
             spans.push(Span::styled(
@@ -1252,19 +2045,38 @@ fn render_menu_overlay(f: &mut Frame, app: &App) {
         if item.needs_target {
             spans.push(Span::styled(" →target", Style::default().fg(DIM)));
         }
-        if item.needs_params {
-            if let Some(hint) = &item.param_hint {
-                spans.push(Span::styled(
-                    format!(" ({})", hint.example),
-                    Style::default().fg(DIM),
-                ));
-            }
+        if item.needs_params
+            && let Some(hint) = &item.param_hint
+        {
+            spans.push(Span::styled(
+                format!(" ({})", hint.example),
+                Style::default().fg(DIM),
+            ));
         }
         lines.push(Line::from(spans));
     }
 
+    // Description of the highlighted gate, cut to fit the overlay
+    if let Some(item) = items.get(app.menu_item)
+        && !item.description.is_empty()
+    {
+        let width = inner.width as usize;
+        let desc = if item.description.chars().count() > width {
+            let cut: String = item
+                .description
+                .chars()
+                .take(width.saturating_sub(1))
+                .collect();
+            format!("{cut}…")
+        } else {
+            item.description.to_string()
+        };
+        lines.push(Line::default());
+        lines.push(Line::styled(desc, Style::default().fg(CYAN)));
+    }
+
     lines.push(Line::styled(
-        "↑↓ Select  ←→ Cat  ⏎ Ok  Esc ✕",
+        "↑↓ Select  ←→ Cat  1-5 Recent  Tab All qubits  Type filter  ⏎ Ok  Esc ✕",
         Style::default().fg(DIM),
     ));
 
@@ -1278,26 +2090,136 @@ fn render_param_input_overlay(f: &mut Frame, app: &App) {
     let area = overlay_rect(f.area(), 40, 7);
     f.render_widget(Clear, area);
 
+    let command = app.focus == Focus::Command;
+    let input_state = app.focus == Focus::InputState;
+    let input_label = app.focus == Focus::InputLabel;
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(ORANGE))
         .title(Span::styled(
-            "Enter Parameter",
+            if command {
+                "Command"
+            } else if input_state {
+                "Input State"
+            } else if input_label {
+                "Barrier Label"
+            } else {
+                "Enter Parameter"
+            },
             Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
         ));
 
     let inner = block.inner(area);
     f.render_widget(block, area);
 
-    let lines = vec![
+    if command {
+        let lines = vec![
+            Line::default(),
+            Line::styled(
+                format!(":{}_", app.param_input),
+                Style::default().fg(DARK_BLUE),
+            ),
+            Line::default(),
+            Line::styled(
+                "Examples: rx(pi/2) 0, cx 1 0, qft 3",
+                Style::default().fg(DIM),
+            ),
+        ];
+        f.render_widget(Paragraph::new(Text::from(lines)), inner);
+        return;
+    }
+
+    if input_state {
+        let lines = vec![
+            Line::default(),
+            Line::styled(
+                format!("|{}_⟩", app.param_input),
+                Style::default().fg(DARK_BLUE),
+            ),
+            Line::default(),
+            Line::styled(
+                "Examples: 101, 0x5 (empty for |0…0⟩)",
+                Style::default().fg(DIM),
+            ),
+        ];
+        f.render_widget(Paragraph::new(Text::from(lines)), inner);
+        return;
+    }
+
+    if input_label {
+        let lines = vec![
+            Line::default(),
+            Line::styled(
+                format!("Label: {}_", app.param_input),
+                Style::default().fg(DARK_BLUE),
+            ),
+            Line::default(),
+            Line::styled(
+                "⏎ Place  Esc Place without a label",
+                Style::default().fg(DIM),
+            ),
+        ];
+        f.render_widget(Paragraph::new(Text::from(lines)), inner);
+        return;
+    }
+
+    let preview = param_preview(&app.param_input);
+    let preview_color = if preview == "invalid" {
+        RED
+    } else if preview.contains("warning") {
+        YELLOW
+    } else {
+        GREEN
+    };
+    let mut lines = vec![
         Line::default(),
         Line::styled(
             format!("Value: {}_", app.param_input),
             Style::default().fg(DARK_BLUE),
         ),
-        Line::default(),
+        Line::styled(
+            format!("       {preview}"),
+            Style::default().fg(preview_color),
+        ),
         Line::styled("Examples: pi/2, 3*pi/4, 1.57", Style::default().fg(DIM)),
     ];
+    if app.focus == Focus::InputParam
+        && is_single_qubit_gate(&app.pending_gate)
+        && app.dag.num_qubits > 1
+    {
+        lines.push(Line::styled(
+            "Tab Place on a qubit range",
+            Style::default().fg(DIM),
+        ));
+    }
+
+    let p = Paragraph::new(Text::from(lines));
+    f.render_widget(p, inner);
+}
+
+fn render_path_input_overlay(f: &mut Frame, app: &App) {
+    let area = overlay_rect(f.area(), 50, 6);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(ORANGE))
+        .title(Span::styled(
+            "Append Circuit",
+            Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+        ));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let lines = vec![
+        Line::default(),
+        Line::styled(
+            format!("File: {}_", app.path_input),
+            Style::default().fg(DARK_BLUE),
+        ),
+        Line::styled("Enter Append  Esc Cancel", Style::default().fg(DIM)),
+    ];
 
     let p = Paragraph::new(Text::from(lines));
     f.render_widget(p, inner);
@@ -1347,6 +2269,295 @@ fn render_edit_gate_overlay(f: &mut Frame, app: &App) {
     f.render_widget(p, inner);
 }
 
+// ── Stats Overlay ──────────────────────────────────────────────────────────────
+
+fn render_stats_overlay(f: &mut Frame, app: &App) {
+    let area = overlay_rect(f.area(), 50, 26);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(ORANGE))
+        .title(Span::styled(
+            "Circuit Stats",
+            Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+        ));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let circuit = app.circuit();
+    let gate_count = circuit.gate_count();
+    let pairs = circuit.coupling_pairs();
+
+    let mut lines: Vec<Line> = vec![
+        Line::styled(
+            format!(
+                "Qubits: {}   Gates: {}   Depth: {}",
+                circuit.num_qubits,
+                gate_count,
+                if circuit.gates.is_empty() {
+                    0
+                } else {
+                    circuit.max_steps + 1
+                }
+            ),
+            Style::default().fg(CYAN),
+        ),
+        Line::default(),
+        Line::styled(
+            format!("Coupling ({} pairs):", pairs.len()),
+            Style::default().fg(ORANGE),
+        ),
+    ];
+
+    if pairs.is_empty() {
+        lines.push(Line::styled(
+            "  no multi-qubit gates",
+            Style::default().fg(DIM),
+        ));
+    }
+    for q in 0..circuit.num_qubits {
+        let neighbours: Vec<String> = pairs
+            .iter()
+            .filter_map(|&(a, b)| {
+                if a == q {
+                    Some(format!("q{b}"))
+                } else if b == q {
+                    Some(format!("q{a}"))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        if !neighbours.is_empty() {
+            lines.push(Line::styled(
+                format!("  q{q}: {}", neighbours.join(", ")),
+                Style::default().fg(DARK_BLUE),
+            ));
+        }
+    }
+
+    let busy = circuit.schedule();
+    let critical = busy.iter().copied().fold(0.0, f64::max);
+    lines.push(Line::default());
+    lines.push(Line::styled(
+        format!("Critical path: {critical}"),
+        Style::default().fg(CYAN),
+    ));
+    lines.push(Line::styled(
+        "Busy time per qubit (1q 1, 2q 3, measure 10):",
+        Style::default().fg(ORANGE),
+    ));
+    for (row, times) in busy.chunks(6).enumerate() {
+        let cells: Vec<String> = times
+            .iter()
+            .enumerate()
+            .map(|(i, t)| format!("q{}: {t}", row * 6 + i))
+            .collect();
+        lines.push(Line::styled(
+            format!("  {}", cells.join("  ")),
+            Style::default().fg(DARK_BLUE),
+        ));
+    }
+
+    let linear = pairs.iter().all(|&(a, b)| b == a + 1);
+    lines.push(Line::default());
+    lines.push(Line::styled(
+        format!(
+            "Nearest-neighbour on a line: {}",
+            if linear { "yes" } else { "no" }
+        ),
+        Style::default().fg(if linear { GREEN } else { YELLOW }),
+    ));
+    lines.push(Line::styled("Esc Close", Style::default().fg(DIM)));
+
+    let p = Paragraph::new(Text::from(lines));
+    f.render_widget(p, inner);
+}
+
+// ── Histogram Overlay ──────────────────────────────────────────────────────────
+
+/// Most outcomes the histogram lists; the rest are counted in a footer.
+const HISTOGRAM_ROWS: usize = 16;
+const HISTOGRAM_BAR_W: usize = 24;
+
+fn render_histogram_overlay(f: &mut Frame, app: &App) {
+    let Some(hist) = &app.histogram else {
+        return;
+    };
+    let measured: Vec<String> = hist.qubits.iter().rev().map(|q| format!("q{q}")).collect();
+    let mut lines = vec![Line::styled(
+        format!("Measured: {}", measured.join(" ")),
+        Style::default().fg(DIM),
+    )];
+
+    // The most frequent outcomes, listed in outcome order
+    let mut shown = hist.counts.clone();
+    shown.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    shown.truncate(HISTOGRAM_ROWS);
+    shown.sort_unstable();
+    let max = shown.iter().map(|&(_, c)| c).max().unwrap_or(1);
+    for &(outcome, count) in &shown {
+        let bar = (count * HISTOGRAM_BAR_W).div_ceil(max);
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{} ", format_basis_state(outcome, hist.qubits.len())),
+                Style::default().fg(DARK_BLUE),
+            ),
+            Span::styled(
+                format!("{:<w$}", "█".repeat(bar), w = HISTOGRAM_BAR_W),
+                Style::default().fg(CYAN),
+            ),
+            Span::styled(
+                format!(
+                    " {count:>5}  {:>5.1}%",
+                    100.0 * count as f64 / hist.shots as f64
+                ),
+                Style::default().fg(DARK_BLUE),
+            ),
+        ]));
+    }
+    if hist.counts.len() > shown.len() {
+        lines.push(Line::styled(
+            format!("... and {} more outcomes", hist.counts.len() - shown.len()),
+            Style::default().fg(DIM),
+        ));
+    }
+
+    lines.push(Line::default());
+    let footer = if hist.undo.is_some() {
+        "Measurements were added: k Keep  Esc Discard"
+    } else {
+        "Esc Close"
+    };
+    lines.push(Line::styled(footer, Style::default().fg(DIM)));
+
+    let area = overlay_rect(f.area(), 60, lines.len() as u16 + 2);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(ORANGE))
+        .title(Span::styled(
+            format!("Measurement Counts ({} shots)", hist.shots),
+            Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+        ));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let p = Paragraph::new(Text::from(lines));
+    f.render_widget(p, inner);
+}
+
+// ── Diff Overlay ───────────────────────────────────────────────────────────────
+
+fn render_diff_overlay(f: &mut Frame, app: &App) {
+    let Some(diff) = &app.diff else {
+        return;
+    };
+    let area = overlay_rect(f.area(), 72, diff.len() as u16 + 2);
+    f.render_widget(Clear, area);
+
+    let (added, removed) = diff_counts(diff);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(ORANGE))
+        .title(Span::styled(
+            format!("Changes since the last save (+{added} -{removed})"),
+            Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Span::styled(
+            " ↑↓ Scroll  Esc Close ",
+            Style::default().fg(DIM),
+        ));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let lines: Vec<Line> = diff
+        .iter()
+        .map(|line| match line {
+            DiffLine::Same(text) => Line::styled(format!("  {text}"), Style::default().fg(DIM)),
+            DiffLine::Added(text) => Line::styled(format!("+ {text}"), Style::default().fg(GREEN)),
+            DiffLine::Removed(text) => Line::styled(format!("- {text}"), Style::default().fg(RED)),
+        })
+        .collect();
+    let max_scroll = lines.len().saturating_sub(inner.height as usize);
+    let scroll = app.diff_scroll.min(max_scroll) as u16;
+    let p = Paragraph::new(Text::from(lines)).scroll((scroll, 0));
+    f.render_widget(p, inner);
+}
+
+// ── Help Overlay ───────────────────────────────────────────────────────────────
+
+fn render_help_overlay(f: &mut Frame, app: &App) {
+    let area = overlay_rect(f.area(), 72, f.area().height);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(ORANGE))
+        .title(Span::styled(
+            "Keybindings",
+            Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Span::styled(
+            " ↑↓ Scroll  Esc Close ",
+            Style::default().fg(DIM),
+        ));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let keys_width = keybindings()
+        .iter()
+        .map(|b| b.keys.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut lines: Vec<Line> = Vec::new();
+    let mut mode = "";
+    for binding in keybindings() {
+        if binding.mode != mode {
+            if !mode.is_empty() {
+                lines.push(Line::default());
+            }
+            mode = binding.mode;
+            lines.push(Line::styled(
+                mode,
+                Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+            ));
+        }
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("  {:<keys_width$}  ", binding.keys),
+                Style::default().fg(CYAN),
+            ),
+            Span::raw(binding.action),
+        ]));
+    }
+    lines.push(Line::default());
+    lines.push(Line::styled(
+        "Symbols",
+        Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+    ));
+    for (symbol, meaning) in symbol_legend(app.wire_style) {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("  {symbol:<keys_width$}  "),
+                Style::default().fg(CYAN),
+            ),
+            Span::raw(meaning),
+        ]));
+    }
+
+    let max_scroll = lines.len().saturating_sub(inner.height as usize);
+    let scroll = app.help_scroll.min(max_scroll) as u16;
+    let p = Paragraph::new(Text::from(lines)).scroll((scroll, 0));
+    f.render_widget(p, inner);
+}
+
 // ── Overlay rect helper ────────────────────────────────────────────────────────
 
 fn overlay_rect(screen: Rect, min_w: u16, min_h: u16) -> Rect {
@@ -1359,3 +2570,217 @@ fn overlay_rect(screen: Rect, min_w: u16, min_h: u16) -> Rect {
         height: h,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    fn circuit_text(app: &mut App, width: usize, height: usize) -> Vec<String> {
+        let circuit = app.circuit();
+        build_circuit_lines(app, &circuit, width, height)
+            .iter()
+            .map(text)
+            .collect()
+    }
+
+    fn app_with(qasm_body: &str) -> App {
+        let mut app = App::new();
+        app.apply_settings(&Settings::default());
+        app.dag.parse_qasm(&format!(
+            "OPENQASM 2.0;\ninclude \"qelib1.inc\";\n{qasm_body}"
+        ));
+        app.sync_from_dag();
+        app
+    }
+
+    #[test]
+    fn wire_style_sets_the_wire_glyphs() {
+        let mut app = app_with("qreg q[2];\nh q[0];\n");
+        let lines = circuit_text(&mut app, 60, 20);
+        assert!(lines[2].contains("─┤  H  ├─"), "{lines:#?}");
+        assert!(lines[5].starts_with("q[1] ───"));
+
+        app.wire_style = WireStyle::Double;
+        let lines = circuit_text(&mut app, 60, 20);
+        assert!(lines[2].contains("═╡  H  ╞═"), "{lines:#?}");
+        assert!(lines[5].starts_with("q[1] ═══"));
+        assert!(!lines[5].contains('─'));
+    }
+
+    fn gate(type_name: &str, params: &[f64]) -> Gate {
+        Gate {
+            type_name: type_name.to_string(),
+            params: params.to_vec(),
+            ..Gate::default()
+        }
+    }
+
+    #[test]
+    fn rotation_boxes_show_their_params() {
+        use std::f64::consts::PI;
+
+        let p = gate("P", &[PI]);
+        assert_eq!(gate_box_label(&p), "P(π)");
+        assert_eq!(gate_box_floor(&p), "─────");
+
+        // Too wide for the middle row, so the caption drops to the edge
+        let rx = gate("RX", &[PI / 2.0]);
+        assert_eq!(gate_box_label(&rx), "RX");
+        assert_eq!(gate_box_floor(&rx), "─π/2─");
+        assert_eq!(gate_box_floor(&gate("RZ", &[1.23456])), "1.23─");
+
+        // Too wide for either, so only the name
+        let u2 = gate("U2", &[PI / 2.0, PI / 4.0]);
+        assert_eq!(gate_box_label(&u2), "U2");
+        assert_eq!(gate_box_floor(&u2), "─────");
+        assert_eq!(gate_box_label(&gate("H", &[])), "H");
+    }
+
+    #[test]
+    fn qubit_prob_annotations_fit_their_column() {
+        assert_eq!(format_qubit_prob(0.5), " P₁=0.50");
+        assert_eq!(format_qubit_prob(1.0000001), " P₁=1.00");
+        assert!(format_qubit_prob(0.123).chars().count() <= QUBIT_PROB_W);
+
+        let mut app = app_with("qreg q[2];\nh q[0];\n");
+        app.show_qubit_probs = true;
+        let lines = circuit_text(&mut app, 60, 20);
+        assert!(lines[2].trim_end().ends_with(" P₁=0.50"), "{lines:#?}");
+        assert!(lines[5].trim_end().ends_with(" P₁=0.00"), "{lines:#?}");
+        assert!(lines.iter().all(|l| l.chars().count() <= 60), "{lines:#?}");
+    }
+
+    #[test]
+    fn wrapped_layout_maps_steps_to_blocks() {
+        assert_eq!(wrap_block(0, 4), 0);
+        assert_eq!(wrap_block(3, 4), 0);
+        assert_eq!(wrap_block(4, 4), 1);
+        assert_eq!(wrap_block(9, 4), 2);
+        assert_eq!(wrap_block(-1, 4), 0);
+
+        // 10 steps of 4 make 3 blocks; 2 fit, so the cursor's block is last shown
+        assert_eq!(visible_wrap_blocks(1, 4, 2, 10), 0..2);
+        assert_eq!(visible_wrap_blocks(9, 4, 2, 10), 1..3);
+        assert_eq!(visible_wrap_blocks(9, 4, 5, 10), 0..3);
+        // The cursor may sit one past the last step, opening a new block
+        assert_eq!(visible_wrap_blocks(12, 4, 2, 10), 2..4);
+    }
+
+    #[test]
+    fn tiny_widths_render_without_panicking() {
+        let mut app = app_with("qreg q[3];\nh q[0];\ncx q[0], q[2];\nrx(pi/3) q[1];\n");
+        for (width, height) in [(10, 8), (1, 1), (0, 0)] {
+            let lines = circuit_text(&mut app, width, height);
+            // Diagram rows keep at least one step; the status line is text
+            // the paragraph clips
+            let (status, rows) = lines.split_last().unwrap();
+            assert!(status.contains("Position"));
+            let bound = LABEL_W + 2 + CELL_W;
+            assert!(
+                rows.iter().all(|l| l.chars().count() <= bound),
+                "{lines:#?}"
+            );
+        }
+    }
+
+    #[test]
+    fn gate_colors_follow_the_gate_family() {
+        for (gate, color) in [
+            ("H", BLUE),
+            ("SX", BLUE),
+            ("RX", PURPLE),
+            ("CU3", PURPLE),
+            ("CX", GREEN),
+            ("CCX", GREEN),
+            ("SWAP", GREEN),
+            ("MEASURE", RED),
+            ("MCX", RED),
+            ("NOISE", ORANGE),
+        ] {
+            assert_eq!(gate_color(gate), color, "{gate}");
+        }
+    }
+
+    #[test]
+    fn phase_glyphs_point_along_the_phase() {
+        use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+        assert_eq!(phase_glyph(0.0), '→');
+        assert_eq!(phase_glyph(FRAC_PI_2), '↑');
+        assert_eq!(phase_glyph(PI), '←');
+        assert_eq!(phase_glyph(-PI), '←');
+        assert_eq!(phase_glyph(-FRAC_PI_2), '↓');
+        assert_eq!(phase_glyph(FRAC_PI_4), '↗');
+        // Snapped to the nearest 45°
+        assert_eq!(phase_glyph(0.3), '→');
+        assert_eq!(phase_glyph(2.0 * PI - 0.1), '→');
+    }
+
+    #[test]
+    fn header_emphasizes_the_cursor_step() {
+        let mut app = app_with("qreg q[2];\nh q[0];\nx q[0];\nz q[0];\n");
+        app.focus = Focus::Circuit;
+        app.cursor_step = 1;
+        app.cursor_qubit = 1;
+        let circuit = app.circuit();
+        let lines = build_circuit_lines(&mut app, &circuit, 60, 20);
+        let bold: Vec<&str> = lines[0]
+            .spans
+            .iter()
+            .filter(|s| s.style.add_modifier.contains(Modifier::BOLD))
+            .map(|s| s.content.trim())
+            .collect();
+        assert_eq!(bold, vec!["1"]);
+        assert_eq!(lines[5].spans[0].style.fg, Some(ORANGE));
+        assert_ne!(lines[2].spans[0].style.fg, Some(ORANGE));
+    }
+
+    #[test]
+    fn amplitudes_format_in_each_style() {
+        let z = ComplexF64::new(1.0, 1.0) / 2f64.sqrt();
+        assert_eq!(format_amplitude(z, AmpFormat::Rectangular), "0.707+0.707i");
+        assert_eq!(format_amplitude(z, AmpFormat::Polar), "1.000∠45.0°");
+        assert_eq!(format_amplitude(z, AmpFormat::Magnitude), "1.000");
+
+        // Components too small to show are dropped
+        let real = ComplexF64::new(std::f64::consts::FRAC_1_SQRT_2, 1e-6);
+        assert_eq!(format_amplitude(real, AmpFormat::Rectangular), "0.707");
+        assert_eq!(format_amplitude(real, AmpFormat::Polar), "0.707");
+        let tiny = ComplexF64::new(1e-5, -1e-5);
+        for fmt in [
+            AmpFormat::Rectangular,
+            AmpFormat::Polar,
+            AmpFormat::Magnitude,
+        ] {
+            assert_eq!(format_amplitude(tiny, fmt), "0");
+        }
+    }
+
+    #[test]
+    fn legend_covers_every_connection_symbol() {
+        for style in [WireStyle::Single, WireStyle::Double] {
+            let legend = symbol_legend(style);
+            let has = |glyph: &str| legend.iter().any(|(g, _)| g == glyph);
+            for item in GATE_MENU.iter().flat_map(|c| c.items) {
+                let gate = item.gate_type;
+                assert!(has(&target_symbol(gate)), "{gate} target");
+                assert!(has(&control_symbol(gate)), "{gate} control");
+            }
+            assert!(has("M") && has("│"), "measurement and barrier");
+        }
+    }
+
+    #[test]
+    fn state_summary_names_the_dominant_state_and_entropy() {
+        let app = app_with("qreg q[2];\nh q[0];\ncx q[0], q[1];\n");
+        let bell = crate::quantum::simulate_circuit(&app.circuit(), -1);
+        assert_eq!(state_summary(&bell, 2), "|00⟩ 50.0%  H=1.00 bits  2 gates");
+        let one = StateVector::from_basis(2, 0b10);
+        assert_eq!(state_summary(&one, 0), "|10⟩ 100.0%  H=0.00 bits  0 gates");
+    }
+}
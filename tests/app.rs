@@ -0,0 +1,401 @@
+use std::time::{Duration, SystemTime};
+
+use q_deck_rs::app::{
+    App, Playback, display_row_qubit, needs_measurements, should_recover, sim_limit_message,
+};
+use q_deck_rs::config::{DEFAULT_MAX_SIM_QUBITS, Settings};
+use q_deck_rs::simulate_circuit;
+
+const HEADER: &str = "OPENQASM 2.0;\ninclude \"qelib1.inc\";\n";
+
+/// An app with default settings, whatever the user has saved, holding the
+/// circuit in `body`.
+fn app_with(body: &str) -> App {
+    let mut app = App::new();
+    app.apply_settings(&Settings::default());
+    app.qasm_text = format!("{HEADER}{body}");
+    app.parse_qasm_input();
+    assert!(app.qasm_errors.is_empty(), "{:?}", app.qasm_errors);
+    app
+}
+
+#[test]
+fn reformat_canonicalizes_messy_qasm() {
+    let mut app = app_with("qreg   q[2];\n\n  h   q[0];\ncx q[0],q[1];\n");
+    assert!(app.reformat_qasm());
+    let clean = app_with("qreg q[2];\nh q[0];\ncx q[0], q[1];\n");
+    assert_eq!(app.qasm_text, clean.dag.to_qasm());
+
+    app.qasm_text.push_str("bogus;\n");
+    let before = app.qasm_text.clone();
+    assert!(!app.reformat_qasm());
+    assert_eq!(app.qasm_text, before);
+}
+
+#[test]
+fn noise_toggle_without_noise_nodes_changes_nothing() {
+    let mut app = app_with("qreg q[2];\nh q[0];\ncx q[0], q[1];\nt q[1];\n");
+    app.cursor_step = app.dag.max_step();
+    let ideal: Vec<f64> = app
+        .simulated_state()
+        .amplitudes
+        .iter()
+        .map(|a| a.norm_sqr())
+        .collect();
+    app.noise_enabled = true;
+    let noisy = app
+        .noisy_probabilities()
+        .expect("two qubits fit a density matrix");
+    assert_eq!(noisy.len(), ideal.len());
+    for (i, (n, p)) in noisy.iter().zip(&ideal).enumerate() {
+        assert!((n - p).abs() < 1e-12, "|{i}⟩: {n} vs {p}");
+    }
+}
+
+#[test]
+fn recover_only_an_autosave_newer_than_the_save() {
+    let saved = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let before = saved - Duration::from_secs(10);
+    let after = saved + Duration::from_secs(10);
+    assert!(should_recover(Some(after), Some(saved)));
+    assert!(!should_recover(Some(before), Some(saved)));
+    assert!(!should_recover(Some(saved), Some(saved)));
+    assert!(should_recover(Some(before), None));
+    assert!(!should_recover(None, Some(saved)));
+    assert!(!should_recover(None, None));
+}
+
+#[test]
+fn cached_state_matches_a_fresh_simulation() {
+    let mut app =
+        app_with("qreg q[3];\nh q[0];\ncx q[0], q[1];\nt q[1];\nh q[2];\ncx q[1], q[2];\n");
+    let check = |app: &mut App| {
+        let circuit = app.circuit();
+        for step in -1..=app.dag.max_step() {
+            let fresh = simulate_circuit(&circuit, step);
+            let cached = app.cached_state(step);
+            for (i, (a, b)) in cached.amplitudes.iter().zip(&fresh.amplitudes).enumerate() {
+                assert!((a - b).norm() < 1e-12, "step {step}, |{i}⟩: {a} vs {b}");
+            }
+        }
+    };
+    check(&mut app);
+    // An edit in the middle invalidates only the later checkpoints
+    app.dag.add_gate("X", 2, 1, None);
+    app.sync_from_dag();
+    check(&mut app);
+}
+
+#[test]
+fn fresh_and_bell_states_report_normalized() {
+    let mut app = app_with("qreg q[2];\n");
+    app.check_normalization();
+    assert_eq!(app.status_msg, "Total probability 1.000000000000");
+    assert!(!app.status_is_error);
+
+    let mut app = app_with("qreg q[2];\nh q[0];\ncx q[0], q[1];\n");
+    app.cursor_step = app.dag.max_step();
+    app.check_normalization();
+    assert!(
+        app.status_msg.starts_with("Total probability 1.0000000"),
+        "{}",
+        app.status_msg
+    );
+    assert!(!app.status_is_error);
+}
+
+#[test]
+fn basis_measurements_are_deterministic_on_their_eigenstates() {
+    // |+⟩ measured in X, and |+i⟩ = S·H|0⟩ measured in Y, always give 0
+    for (prep, gate) in [
+        ("h q[0];\n", "MEASURE_X"),
+        ("h q[0];\ns q[0];\n", "MEASURE_Y"),
+    ] {
+        let mut app = app_with(&format!("qreg q[1];\n{prep}"));
+        app.cursor_step = app.dag.max_step() + 1;
+        assert!(app.place_gate(gate, -1));
+        assert!(
+            app.dag
+                .to_qasm()
+                .ends_with("h q[0];\nmeasure q[0] -> c[0];\n")
+        );
+        let state = simulate_circuit(&app.circuit(), -1);
+        assert_eq!(
+            state.sample_measurements(&[0], 500, 7),
+            vec![(0, 500)],
+            "{gate}"
+        );
+    }
+}
+
+#[test]
+fn playback_advances_and_pauses_at_the_end() {
+    let mut app = app_with("qreg q[1];\nh q[0];\nx q[0];\nh q[0];\n");
+    app.cursor_step = 2;
+    assert!(!app.playback_tick(), "nothing happens while off");
+
+    app.toggle_playback();
+    assert_eq!((app.playback, app.cursor_step), (Playback::Playing, 0));
+    assert!(app.playback_tick());
+    assert_eq!((app.playback, app.cursor_step), (Playback::Playing, 1));
+    assert!(app.playback_tick());
+    assert_eq!((app.playback, app.cursor_step), (Playback::Paused, 2));
+    assert!(!app.playback_tick());
+    assert_eq!(app.cursor_step, 2);
+
+    // Resuming at the end starts over
+    app.pause_playback();
+    assert_eq!((app.playback, app.cursor_step), (Playback::Playing, 0));
+    app.pause_playback();
+    assert_eq!(app.playback, Playback::Paused);
+    app.toggle_playback();
+    assert_eq!(app.playback, Playback::Off);
+}
+
+#[test]
+fn recent_gates_list_each_gate_once_newest_first() {
+    let mut app = app_with("qreg q[1];\n");
+    for gate in ["H", "X", "H"] {
+        assert!(app.place_gate(gate, -1));
+        app.cursor_step += 1;
+    }
+    assert_eq!(app.recent_gates, vec!["H", "X"]);
+}
+
+#[test]
+fn seeding_basis_state_five_starts_from_101() {
+    for input in ["101", "0x5"] {
+        let mut app = app_with("qreg q[3];\n");
+        app.param_input = input.to_string();
+        app.set_input_state().unwrap();
+        let probs: Vec<f64> = app
+            .simulated_state()
+            .amplitudes
+            .iter()
+            .map(|a| a.norm_sqr())
+            .collect();
+        assert_eq!(probs[0b101], 1.0, "{input}");
+        assert_eq!(probs.iter().sum::<f64>(), 1.0);
+        // The circuit itself is untouched
+        assert!(app.dag.nodes.is_empty());
+    }
+
+    let mut app = app_with("qreg q[3];\n");
+    app.param_input = "1000".to_string();
+    assert!(app.set_input_state().is_err());
+    app.param_input = "12".to_string();
+    assert!(app.set_input_state().is_err());
+}
+
+#[test]
+fn regenerating_qasm_keeps_the_cursor_row() {
+    let mut app = app_with("qreg q[2];\nh        q[0];\ncx   q[0],   q[1];\n\n\n\n");
+    // Past the end of most canonical lines, on row 4
+    app.qasm_cursor = app.qasm_offset(4, 18);
+    assert_eq!(app.qasm_cursor_row_col(), (4, 18));
+    app.sync_from_dag();
+    // Same row, column clamped to the now shorter line
+    let row_len = app.qasm_text.lines().nth(4).unwrap().len();
+    assert!(row_len < 18);
+    assert_eq!(app.qasm_cursor_row_col(), (4, row_len));
+
+    // Past the new end of the text, the cursor moves to the last line
+    app.qasm_text.push_str("\n\n\n\n\n");
+    app.qasm_cursor = app.qasm_text.len();
+    app.sync_from_dag();
+    assert_eq!(app.qasm_cursor, app.qasm_text.len());
+}
+
+#[test]
+fn unparseable_lines_are_recorded_by_index() {
+    let mut app = app_with("qreg q[1];\nh q[0];\n");
+    app.qasm_text = format!("{HEADER}qreg q[1];\nh q[0\nx q[0];\n???\n");
+    app.parse_qasm_input();
+    let lines: Vec<usize> = app.qasm_errors.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, vec![3, 5]);
+
+    app.qasm_text = format!("{HEADER}qreg q[1];\nx q[0];\n");
+    app.parse_qasm_input();
+    assert!(app.qasm_errors.is_empty());
+}
+
+#[test]
+fn apply_all_places_one_gate_per_qubit_in_one_step() {
+    let mut app = app_with("qreg q[4];\n");
+    app.apply_all_qubits = true;
+    app.cursor_step = 0;
+    assert!(app.place_gate("H", -1));
+    let mut placed: Vec<(String, usize, isize)> = app
+        .dag
+        .nodes
+        .values()
+        .map(|n| (n.type_name.clone(), n.target as usize, n.step))
+        .collect();
+    placed.sort();
+    let expected: Vec<_> = (0..4).map(|q| ("H".to_string(), q, 0)).collect();
+    assert_eq!(placed, expected);
+    assert_eq!(app.status_msg, "Placed Hadamard on all 4 qubits");
+}
+
+#[test]
+fn swap_ignores_a_leftover_param_buffer() {
+    let mut app = app_with("qreg q[2];\n");
+    app.param_input = "pi/2".to_string();
+    assert!(app.place_gate("SWAP", 1));
+    let node = app.dag.get_node_at(0, 0).unwrap();
+    assert_eq!(node.type_name, "SWAP");
+    assert!(node.params.is_empty());
+    assert!(app.dag.to_qasm().ends_with("swap q[0], q[1];\n"));
+}
+
+#[test]
+fn range_placement_copies_the_gate_and_its_param() {
+    let mut app = app_with("qreg q[4];\n");
+    app.cursor_qubit = 1;
+    app.target_qubit = 3;
+    app.param_input = "pi".to_string();
+    assert!(app.place_gate_on_range("RY"));
+    let mut placed: Vec<(usize, Vec<f64>)> = app
+        .dag
+        .nodes
+        .values()
+        .map(|n| {
+            assert_eq!((n.type_name.as_str(), n.step), ("RY", 0));
+            (n.target as usize, n.params.clone())
+        })
+        .collect();
+    placed.sort_by_key(|(q, _)| *q);
+    let pi = std::f64::consts::PI;
+    assert_eq!(placed, vec![(1, vec![pi]), (2, vec![pi]), (3, vec![pi])]);
+    assert_eq!(app.cursor_qubit, 1);
+}
+
+#[test]
+fn placing_with_the_wrong_param_count_is_refused() {
+    let mut app = app_with("qreg q[1];\n");
+    for (gate, params) in [("RX", "pi, pi/2"), ("U2", "pi"), ("U3", "pi, 0")] {
+        app.param_input = params.to_string();
+        assert!(!app.place_gate(gate, -1), "{gate}({params})");
+        assert!(app.status_msg.contains("parameter"), "{}", app.status_msg);
+    }
+    assert!(app.dag.nodes.is_empty());
+    app.param_input = "pi, 0, pi".to_string();
+    assert!(app.place_gate("U3", -1));
+    assert_eq!(app.dag.get_node_at(0, 0).unwrap().params.len(), 3);
+}
+
+#[test]
+fn simulation_stops_just_above_the_qubit_limit() {
+    assert_eq!(sim_limit_message(20, 20), None);
+    assert_eq!(
+        sim_limit_message(21, 20).as_deref(),
+        Some("State too large to simulate (21 qubits)")
+    );
+
+    let mut app = app_with("qreg q[3];\n");
+    assert_eq!(app.max_sim_qubits, DEFAULT_MAX_SIM_QUBITS);
+    assert_eq!(app.simulation_limit_message(), None);
+    app.max_sim_qubits = 2;
+    assert!(app.simulation_limit_message().is_some());
+}
+
+#[test]
+fn frozen_simulation_needs_recompute_only_after_changes() {
+    let mut app = app_with("qreg q[2];\nh q[0];\n");
+    assert!(app.sim_auto);
+    assert!(!app.needs_recompute());
+
+    app.toggle_sim_auto();
+    assert!(!app.needs_recompute());
+    let frozen = app.simulated_state();
+
+    // An edit leaves the snapshot stale until it is refreshed
+    app.cursor_qubit = 1;
+    app.cursor_step = 0;
+    assert!(app.place_gate("X", -1));
+    assert!(app.needs_recompute());
+    assert_eq!(app.simulated_state().amplitudes, frozen.amplitudes);
+    app.refresh_simulation();
+    assert!(!app.needs_recompute());
+
+    // So does moving the cursor
+    app.cursor_step += 1;
+    assert!(app.needs_recompute());
+
+    app.toggle_sim_auto();
+    assert!(!app.needs_recompute());
+}
+
+#[test]
+fn sampling_adds_measurements_only_when_none_exist() {
+    assert!(needs_measurements(&app_with("qreg q[2];\nh q[0];\n").dag));
+    assert!(!needs_measurements(
+        &app_with("qreg q[2];\nh q[0];\nmeasure q[1] -> c[0];\n").dag
+    ));
+
+    let mut app = app_with("qreg q[2];\nh q[0];\n");
+    app.measure_and_sample();
+    assert!(!needs_measurements(&app.dag));
+    app.close_histogram(false);
+    assert!(needs_measurements(&app.dag));
+    assert_eq!(app.dag.nodes.len(), 1);
+}
+
+#[test]
+fn reversed_display_maps_rows_back_to_qubits() {
+    let rows: Vec<usize> = (0..4).map(|r| display_row_qubit(r, 4, true)).collect();
+    assert_eq!(rows, vec![3, 2, 1, 0]);
+    for q in 0..4 {
+        assert_eq!(display_row_qubit(q, 4, false), q);
+        assert_eq!(display_row_qubit(display_row_qubit(q, 4, true), 4, true), q);
+    }
+
+    // Moving down the screen walks toward q[0] once the order is reversed
+    let mut app = app_with("qreg q[4];\n");
+    assert_eq!(app.qubit_after_rows(1, 1), 2);
+    app.reverse_qubits = true;
+    assert_eq!(app.qubit_after_rows(1, 1), 0);
+    assert_eq!(app.qubit_after_rows(0, 1), 0);
+    assert_eq!(app.qubit_after_rows(3, -1), 3);
+}
+
+#[test]
+fn cursor_clamps_into_a_shrunken_circuit() {
+    let mut app = app_with("qreg q[4];\nh q[0];\nh q[3];\nx q[3];\nz q[3];\n");
+    app.cursor_qubit = 3;
+    app.cursor_step = 2;
+    app.qasm_text = format!("{HEADER}qreg q[2];\nh q[0];\nx q[1];\n");
+    app.parse_qasm_input();
+    app.clamp_cursor();
+    // Clamped to q[1] at step 1, then snapped to the X beside it
+    assert_eq!((app.cursor_step, app.cursor_qubit), (0, 1));
+
+    // A cursor that is still inside stays put, even on an empty cell
+    app.cursor_step = 1;
+    app.clamp_cursor();
+    assert_eq!((app.cursor_step, app.cursor_qubit), (1, 1));
+}
+
+#[test]
+fn apply_all_with_bad_params_places_nothing() {
+    let mut app = app_with("qreg q[3];\n");
+    for params in ["pi, pi", "pi/"] {
+        app.apply_all_qubits = true;
+        app.param_input = params.to_string();
+        assert!(!app.place_gate("RX", -1), "{params}");
+        assert!(app.dag.nodes.is_empty(), "{params}");
+        assert!(!app.status_msg.starts_with("Placed"), "{}", app.status_msg);
+    }
+}
+
+#[test]
+fn range_placement_with_the_wrong_param_count_changes_nothing() {
+    let mut app = app_with("qreg q[4];\nh q[0];\n");
+    let before = app.dag.to_qasm();
+    app.cursor_qubit = 1;
+    app.target_qubit = 3;
+    app.param_input = "pi, pi/2".to_string();
+    assert!(!app.place_gate_on_range("RY"));
+    assert_eq!(app.dag.to_qasm(), before);
+    assert_eq!(app.status_msg, "Rotate Y takes 1 parameter, got 2");
+}
@@ -0,0 +1,97 @@
+use q_deck_rs::{Circuit, CircuitDAG};
+
+const HEADER: &str = "OPENQASM 2.0;\ninclude \"qelib1.inc\";\n";
+
+fn circuit(body: &str) -> Circuit {
+    let mut dag = CircuitDAG::new();
+    let errors = dag.parse_qasm(&format!("{HEADER}{body}"));
+    assert!(errors.is_empty(), "{errors:?}");
+    dag.to_circuit()
+}
+
+#[test]
+fn next_gate_step_scans_one_qubit_without_wrapping() {
+    let mut dag = CircuitDAG::new();
+    dag.num_qubits = 2;
+    dag.add_gate("H", 0, 0, None);
+    dag.add_gate("X", 1, 2, None);
+    dag.add_gate("Z", 0, 4, None);
+    let c = dag.to_circuit();
+    assert_eq!(c.next_gate_step(0, 0, true), Some(4));
+    assert_eq!(c.next_gate_step(0, 2, true), Some(4));
+    assert_eq!(c.next_gate_step(0, 4, true), None);
+    assert_eq!(c.next_gate_step(0, 4, false), Some(0));
+    assert_eq!(c.next_gate_step(0, 3, false), Some(0));
+    assert_eq!(c.next_gate_step(0, 0, false), None);
+}
+
+#[test]
+fn coupling_pairs_list_each_interacting_pair_once() {
+    let c = circuit("qreg q[3];\ncx q[0], q[1];\ncz q[1], q[2];\ncx q[1], q[0];\n");
+    assert_eq!(c.coupling_pairs(), vec![(0, 1), (1, 2)]);
+
+    let c = circuit("qreg q[3];\nccx q[0], q[1], q[2];\nh q[0];\n");
+    assert_eq!(c.coupling_pairs(), vec![(0, 1), (0, 2), (1, 2)]);
+}
+
+#[test]
+fn svg_draws_a_box_per_boxed_gate() {
+    let svg = circuit("qreg q[2];\nh q[0];\nt q[1];\nrx(pi/2) q[0];\n").to_svg();
+    assert!(svg.starts_with("<svg ") && svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<rect").count(), 3);
+
+    // A CNOT target is ⊕ rather than a box
+    let svg = circuit("qreg q[2];\nh q[0];\ncx q[0], q[1];\nt q[1];\n").to_svg();
+    assert_eq!(svg.matches("<rect").count(), 2);
+    assert_eq!(svg.matches("<circle").count(), 2);
+}
+
+#[test]
+fn scattered_controls_draw_dots_only_on_their_rows() {
+    let mut dag = CircuitDAG::new();
+    dag.num_qubits = 4;
+    dag.add_multi_control_gate("CCX", 1, 0, vec![0, 3]);
+    let c = dag.to_circuit();
+
+    let cells: Vec<_> = (0..4).map(|q| c.get_cell_info(0, q)).collect();
+    assert!(cells[0].is_control && cells[3].is_control);
+    assert!(cells[1].is_target && !cells[1].is_control);
+    assert!(cells[2].pass_through && !cells[2].is_control);
+    // The connector runs from the top control to the bottom one
+    assert!(!cells[0].vert_above && cells[0].vert_below);
+    assert!(cells[1].vert_above && cells[1].vert_below);
+    assert!(cells[3].vert_above && !cells[3].vert_below);
+}
+
+#[test]
+fn a_two_gate_chain_sets_the_critical_path() {
+    // H (1) then CX (3) on q0, with q2 idle but for one X
+    let c = circuit("qreg q[3];\nh q[0];\ncx q[0], q[1];\nx q[2];\n");
+    assert_eq!(c.schedule(), vec![4.0, 4.0, 1.0]);
+    let c = circuit("qreg q[1];\nh q[0];\nmeasure q[0] -> c[0];\n");
+    assert_eq!(c.schedule(), vec![11.0]);
+}
+
+#[test]
+fn only_savable_controlled_gates_can_gain_a_control() {
+    let c = circuit(
+        "qreg q[4];\nh q[0];\nry(pi) q[1];\ns q[2];\ncx q[0], q[1];\ncz q[2], q[3];\nccx q[0], q[1], q[2];\n",
+    );
+    let mut by_type: Vec<(&str, Option<&str>)> = c
+        .gates
+        .iter()
+        .map(|g| (g.type_name.as_str(), g.controlled_type()))
+        .collect();
+    by_type.sort();
+    assert_eq!(
+        by_type,
+        vec![
+            ("CCX", Some("CCX")),
+            ("CX", Some("CCX")),
+            ("CZ", None),
+            ("H", Some("CH")),
+            ("RY", Some("CRY")),
+            ("S", None),
+        ]
+    );
+}
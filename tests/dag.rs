@@ -0,0 +1,268 @@
+use q_deck_rs::CircuitDAG;
+use q_deck_rs::dag::{complete_gate_token, parse_command};
+
+fn steps(dag: &CircuitDAG) -> Vec<(String, usize, isize)> {
+    let mut nodes: Vec<_> = dag.nodes.values().collect();
+    nodes.sort_by_key(|n| (n.step, n.seq));
+    nodes
+        .iter()
+        .map(|n| (n.type_name.clone(), n.target as usize, n.step))
+        .collect()
+}
+
+fn dag_with_qubits(n: usize) -> CircuitDAG {
+    let mut dag = CircuitDAG::new();
+    dag.num_qubits = n;
+    dag
+}
+
+#[test]
+fn compacting_closes_a_gap() {
+    let mut dag = dag_with_qubits(1);
+    dag.add_gate("H", 0, 0, None);
+    dag.add_gate("X", 0, 5, None);
+    dag.compact_steps();
+    assert_eq!(
+        steps(&dag),
+        vec![("H".to_string(), 0, 0), ("X".to_string(), 0, 1)]
+    );
+}
+
+#[test]
+fn rescheduling_merges_independent_gates() {
+    let mut dag = dag_with_qubits(2);
+    dag.add_gate("H", 0, 0, None);
+    dag.add_gate("X", 1, 1, None);
+    dag.reschedule_asap();
+    assert_eq!(dag.max_step(), 0);
+}
+
+#[test]
+fn rescheduling_keeps_a_multi_qubit_span_to_itself() {
+    let mut dag = dag_with_qubits(3);
+    dag.add_gate("CX", 2, 0, Some(0));
+    dag.add_gate("H", 1, 1, None);
+    dag.reschedule_asap();
+    assert_eq!(
+        steps(&dag),
+        vec![("CX".to_string(), 2, 0), ("H".to_string(), 1, 1)]
+    );
+}
+
+#[test]
+fn rescheduling_follows_insertion_order_within_a_step() {
+    // The conditioned H sorts before MEASURE by name but reads its bit
+    let mut dag = dag_with_qubits(2);
+    dag.add_gate("MEASURE", 0, 2, None);
+    dag.add_classical_control_gate("H", 1, 2, 0);
+    dag.reschedule_asap();
+    assert_eq!(
+        steps(&dag),
+        vec![("MEASURE".to_string(), 0, 0), ("H".to_string(), 1, 1)]
+    );
+}
+
+#[test]
+fn allocating_an_ancilla_grows_the_register() {
+    let mut dag = dag_with_qubits(2);
+    assert_eq!(dag.allocate_ancilla(), 2);
+    assert_eq!(dag.num_qubits, 3);
+    assert_eq!(dag.ancillas, vec![2]);
+    assert!(dag.is_ancilla(2) && !dag.is_ancilla(1));
+}
+
+#[test]
+fn same_type_target_and_step_keep_distinct_ids() {
+    let mut dag = dag_with_qubits(3);
+    dag.add_gate("RX", 2, 5, None);
+    dag.add_parameterized_gate("RX", 2, 5, vec![1.0], Some(0));
+    assert_eq!(dag.nodes.len(), 2);
+    let mut ids: Vec<_> = dag.nodes.keys().cloned().collect();
+    ids.sort();
+    assert_eq!(ids, vec!["RX_q2_s5", "RX_q2_s5_1"]);
+}
+
+#[test]
+fn gate_commands_parse_controls_first() {
+    let node = parse_command("cx 1 0").unwrap();
+    assert_eq!(node.type_name, "CX");
+    assert_eq!((node.control, node.target), (1, 0));
+
+    let node = parse_command("RX(pi/2) 2").unwrap();
+    assert_eq!(node.type_name, "RX");
+    assert_eq!(node.target, 2);
+    assert!((node.params[0] - std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+
+    assert!(parse_command("cx 1").is_err());
+    assert!(parse_command("cx 1 1").is_err());
+    assert!(parse_command("frobnicate 0").is_err());
+    assert!(parse_command("h").is_err());
+}
+
+#[test]
+fn gate_tokens_complete_from_a_prefix() {
+    assert_eq!(complete_gate_token("cr"), vec!["crx", "cry", "crz"]);
+    assert_eq!(complete_gate_token("SWA"), vec!["swap"]);
+    assert_eq!(complete_gate_token("sx"), vec!["sx", "sxdg"]);
+    assert!(complete_gate_token("qq").is_empty());
+    assert!(complete_gate_token("").len() > 30);
+}
+
+#[test]
+fn random_circuits_are_reproducible_and_placeable() {
+    let a = CircuitDAG::random(5, 12, 42);
+    assert_eq!(a.to_qasm(), CircuitDAG::random(5, 12, 42).to_qasm());
+    assert_ne!(a.to_qasm(), CircuitDAG::random(5, 12, 43).to_qasm());
+    assert_eq!(a.num_qubits, 5);
+    assert!(!a.nodes.is_empty() && a.max_step() < 12);
+
+    // Every gate could have been placed where it is
+    for (id, node) in &a.nodes {
+        let mut without = a.clone();
+        without.remove_node(id);
+        assert!(
+            without.can_place_gate_at(node.step, &node.qubits()),
+            "{id} does not fit"
+        );
+    }
+}
+
+#[test]
+fn same_step_gates_keep_insertion_order() {
+    let mut dag = dag_with_qubits(2);
+    dag.add_gate("Z", 0, 0, None);
+    dag.add_gate("CX", 1, 0, Some(0));
+    let order: Vec<_> = dag
+        .topological_sort()
+        .iter()
+        .map(|n| n.type_name.clone())
+        .collect();
+    assert_eq!(order, vec!["Z", "CX"]);
+    let cx = dag.get_node_at(0, 1).unwrap();
+    let z = dag.get_node_at(0, 0).unwrap();
+    assert!(cx.dependencies.contains(&z.id));
+}
+
+#[test]
+fn overlapping_nodes_resolve_deterministically() {
+    // Each fresh DAG hashes differently, so HashMap order would show up
+    for _ in 0..20 {
+        let mut dag = dag_with_qubits(3);
+        // Overlaps the placement rules forbid but an edit can leave behind
+        dag.add_gate("CX", 1, 0, Some(0));
+        dag.add_gate("CZ", 2, 0, Some(1));
+        dag.add_gate("X", 0, 0, None);
+        assert_eq!(dag.get_node_at(0, 1).unwrap().type_name, "CX");
+        assert_eq!(dag.get_node_at(0, 0).unwrap().type_name, "X");
+        assert_eq!(dag.get_node_at(0, 2).unwrap().type_name, "CZ");
+    }
+}
+
+#[test]
+fn region_holds_only_nodes_wholly_inside_it() {
+    let mut dag = dag_with_qubits(4);
+    dag.add_gate("H", 1, 1, None);
+    dag.add_gate("CX", 2, 2, Some(1));
+    dag.add_gate("CX", 3, 2, Some(0));
+    dag.add_barrier(3);
+    let inside = |step: isize, qubit: usize, steps, qubits| {
+        dag.get_node_at(step, qubit)
+            .unwrap()
+            .inside_region(&steps, &qubits, 4)
+    };
+    assert!(inside(1, 1, 0..=2, 1..=2));
+    assert!(inside(2, 1, 0..=2, 1..=2));
+    assert!(!inside(2, 0, 0..=2, 1..=3), "control outside");
+    assert!(!inside(1, 1, 2..=3, 0..=3), "step outside");
+    assert!(!inside(3, 0, 3..=3, 0..=2), "barrier spans every wire");
+    assert!(inside(3, 0, 3..=3, 0..=3));
+
+    assert_eq!(dag.remove_region(0..=2, 1..=2), 2);
+    assert_eq!(steps(&dag).len(), 2);
+}
+
+#[test]
+fn compacting_keeps_barriers_as_separators() {
+    let mut dag = dag_with_qubits(2);
+    dag.add_gate("H", 0, 0, None);
+    dag.add_barrier(3);
+    dag.add_gate("X", 1, 6, None);
+    dag.compact_steps();
+    assert_eq!(
+        steps(&dag)
+            .into_iter()
+            .map(|(t, _, s)| (t, s))
+            .collect::<Vec<_>>(),
+        vec![
+            ("H".to_string(), 0),
+            ("BARRIER".to_string(), 1),
+            ("X".to_string(), 2)
+        ]
+    );
+}
+
+#[test]
+fn rescheduling_fills_a_gap_that_compacting_skips() {
+    // The X on q1 depends on nothing, so it can run before the CX that
+    // spans its row; greedy compaction keeps it after
+    let build = || {
+        let mut dag = dag_with_qubits(3);
+        dag.add_gate("H", 0, 0, None);
+        dag.add_gate("CX", 2, 1, Some(0));
+        dag.add_gate("X", 1, 2, None);
+        dag
+    };
+    let mut compacted = build();
+    compacted.compact_steps();
+    assert_eq!(compacted.max_step(), 2);
+
+    let mut rescheduled = build();
+    rescheduled.reschedule_asap();
+    assert_eq!(
+        steps(&rescheduled),
+        vec![
+            ("H".to_string(), 0, 0),
+            ("X".to_string(), 1, 0),
+            ("CX".to_string(), 2, 1)
+        ]
+    );
+}
+
+#[test]
+fn rescheduling_keeps_barriers_between_their_sides() {
+    let mut dag = dag_with_qubits(2);
+    dag.add_gate("H", 0, 0, None);
+    dag.add_gate("H", 0, 1, None);
+    dag.add_barrier(2);
+    dag.add_gate("X", 1, 3, None);
+    dag.reschedule_asap();
+    // The X would fit at step 0 without the barrier
+    assert_eq!(
+        steps(&dag)[2..],
+        [
+            ("BARRIER".to_string(), usize::MAX, 2),
+            ("X".to_string(), 1, 3)
+        ]
+    );
+}
+
+#[test]
+fn releasing_an_ancilla_undoes_its_allocation() {
+    let mut dag = dag_with_qubits(2);
+    let q = dag.allocate_ancilla();
+    dag.qubit_labels.insert(q, "scratch".to_string());
+    dag.release_ancilla(q);
+    assert_eq!(dag.num_qubits, 2);
+    assert!(dag.ancillas.is_empty());
+    assert!(dag.qubit_labels.is_empty());
+}
+
+#[test]
+fn qelib1_spellings_complete_like_menu_gates() {
+    assert_eq!(complete_gate_token("u"), vec!["u", "u0", "u1", "u2", "u3"]);
+    assert_eq!(complete_gate_token("cn"), vec!["cnot"]);
+    assert_eq!(complete_gate_token("cph"), vec!["cphase"]);
+    assert_eq!(complete_gate_token("i"), vec!["id"]);
+    assert!(complete_gate_token("m").contains(&"measure"));
+    assert!(complete_gate_token("mcx").is_empty());
+}
@@ -0,0 +1,44 @@
+//! The crate root's re-exports are enough to build, parse and simulate a
+//! circuit without reaching into the modules.
+
+use q_deck_rs::{
+    Circuit, CircuitDAG, Gate, StateVector, format_param, parse_param_expr, parse_params,
+    simulate_circuit,
+};
+
+#[test]
+fn parse_and_simulate_through_the_public_api() {
+    let mut dag = CircuitDAG::new();
+    let errors = dag.parse_qasm(
+        "OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[2];\nry(pi/2) q[0];\ncx q[0], q[1];\n",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    let circuit: Circuit = dag.to_circuit();
+    assert_eq!(circuit.gate_count(), 2);
+
+    let state: StateVector = simulate_circuit(&circuit, -1);
+    let probs: Vec<f64> = state.amplitudes.iter().map(|a| a.norm_sqr()).collect();
+    for (i, expected) in [0.5, 0.0, 0.0, 0.5].into_iter().enumerate() {
+        assert!((probs[i] - expected).abs() < 1e-12, "|{i}⟩: {}", probs[i]);
+    }
+}
+
+#[test]
+fn hand_built_circuits_simulate_too() {
+    let theta = parse_param_expr("pi").unwrap();
+    let circuit = Circuit {
+        num_qubits: 1,
+        gates: vec![Gate {
+            type_name: "RX".to_string(),
+            params: parse_params("pi").unwrap(),
+            control: -1,
+            measure_source: -1,
+            classical_control: -1,
+            ..Gate::default()
+        }],
+        ..Circuit::default()
+    };
+    assert_eq!(format_param(theta), "pi");
+    let state = simulate_circuit(&circuit, -1);
+    assert!((state.amplitudes[1].norm_sqr() - 1.0).abs() < 1e-12);
+}
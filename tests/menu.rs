@@ -0,0 +1,40 @@
+use q_deck_rs::menu::{
+    expected_param_count, find_menu_item, menu_item_matches, step_index, validate_param_count,
+};
+
+#[test]
+fn menu_filter_matches_name_or_type_ignoring_case() {
+    let h = find_menu_item("H").unwrap();
+    let cx = find_menu_item("CX").unwrap();
+    assert!(menu_item_matches(h, ""));
+    assert!(menu_item_matches(h, "hada"));
+    assert!(menu_item_matches(h, "H"));
+    assert!(menu_item_matches(cx, "cnot"));
+    assert!(menu_item_matches(cx, "cx"));
+    assert!(!menu_item_matches(cx, "hada"));
+}
+
+#[test]
+fn param_counts_are_checked_per_gate() {
+    assert_eq!(expected_param_count("RX"), Some(1));
+    assert_eq!(expected_param_count("U2"), Some(2));
+    assert_eq!(expected_param_count("U3"), Some(3));
+    assert_eq!(expected_param_count("H"), None);
+    assert!(validate_param_count("RX", 1).is_ok());
+    assert!(validate_param_count("RX", 0).is_ok());
+    assert!(validate_param_count("RX", 2).is_err());
+    assert!(validate_param_count("U2", 1).is_err());
+    assert!(validate_param_count("U3", 2).is_err());
+    assert!(validate_param_count("U3", 4).is_err());
+}
+
+#[test]
+fn menu_steps_wrap_only_when_asked() {
+    assert_eq!(step_index(1, 3, true, true), 2);
+    assert_eq!(step_index(2, 3, true, true), 0);
+    assert_eq!(step_index(0, 3, false, true), 2);
+    assert_eq!(step_index(2, 3, true, false), 2);
+    assert_eq!(step_index(0, 3, false, false), 0);
+    assert_eq!(step_index(0, 1, true, true), 0);
+    assert_eq!(step_index(0, 0, false, true), 0);
+}
@@ -0,0 +1,31 @@
+use q_deck_rs::format_param;
+use q_deck_rs::params::param_preview;
+
+#[test]
+fn param_preview_shows_values_and_warnings() {
+    assert_eq!(param_preview("  "), "");
+    assert_eq!(param_preview("pi/2"), "= 1.5708");
+    assert_eq!(param_preview("-pi, 0.5"), "= -3.1416, 0.5000");
+    assert_eq!(param_preview("pi/"), "invalid");
+    assert_eq!(param_preview("13"), "= 13.0000  (warning: |θ| > 4π)");
+}
+
+#[test]
+fn params_format_to_six_significant_digits() {
+    assert_eq!(format_param(0.1 + 0.2), "0.3");
+    assert_eq!(format_param(1.0 / 3.0), "0.333333");
+    assert_eq!(format_param(0.000004), "4e-6");
+    assert_eq!(format_param(12345678.9), "1.23457e7");
+}
+
+#[test]
+fn rational_multiples_of_pi_format_as_fractions() {
+    use std::f64::consts::PI;
+
+    assert_eq!(format_param(5.0 * PI / 6.0), "5*pi/6");
+    assert_eq!(format_param(PI / 12.0), "pi/12");
+    assert_eq!(format_param(7.0 * PI / 4.0), "7*pi/4");
+    assert_eq!(format_param(-PI / 12.0), "-pi/12");
+    assert_eq!(format_param(PI / 5.0), "pi/5");
+    assert_eq!(format_param(1.234), "1.234");
+}
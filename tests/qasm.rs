@@ -0,0 +1,418 @@
+use q_deck_rs::CircuitDAG;
+use q_deck_rs::circuit::invert_gate;
+
+const HEADER: &str = "OPENQASM 2.0;\ninclude \"qelib1.inc\";\n";
+
+fn parse(body: &str) -> CircuitDAG {
+    let mut dag = CircuitDAG::new();
+    let errors = dag.parse_qasm(&format!("{HEADER}{body}"));
+    assert!(errors.is_empty(), "{errors:?}");
+    dag
+}
+
+fn round_trip(dag: &CircuitDAG) -> CircuitDAG {
+    let mut again = CircuitDAG::new();
+    let errors = again.parse_qasm(&dag.to_qasm());
+    assert!(errors.is_empty(), "{errors:?}");
+    again
+}
+
+#[test]
+fn group_comment_round_trips() {
+    let dag = parse("qreg q[2];\n// group 0 1 bell\nh q[0];\ncx q[0], q[1];\n");
+    assert_eq!(dag.groups, vec![(0, 1, "bell".to_string())]);
+    assert!(dag.to_qasm().contains("// group 0 1 bell\n"));
+    assert_eq!(round_trip(&dag).groups, dag.groups);
+}
+
+#[test]
+fn reparsing_drops_old_groups() {
+    let mut dag = parse("qreg q[1];\n// group 0 0 prep\nh q[0];\n");
+    dag.parse_qasm(&format!("{HEADER}qreg q[1];\nx q[0];\n"));
+    assert!(dag.groups.is_empty());
+}
+
+/// Node types in step order, same-step nodes in insertion order.
+fn types(dag: &CircuitDAG) -> Vec<String> {
+    let mut nodes: Vec<_> = dag.nodes.values().collect();
+    nodes.sort_by_key(|n| (n.step, n.seq));
+    nodes.iter().map(|n| n.type_name.clone()).collect()
+}
+
+#[test]
+fn measure_then_conditioned_x_fuses() {
+    let dag = parse("qreg q[2];\ncreg c[1];\nmeasure q[0] -> c[0];\nif (c[0]==1) x q[1];\n");
+    assert_eq!(types(&dag), vec!["MCX"]);
+}
+
+#[test]
+fn comment_between_measure_and_conditional_still_fuses() {
+    let dag = parse(
+        "qreg q[2];\ncreg c[1];\nmeasure q[0] -> c[0];\n// correct q[1]\nif (c[0]==1) x q[1];\n",
+    );
+    assert_eq!(types(&dag), vec!["MCX"]);
+}
+
+#[test]
+fn conditioned_z_after_measure_stays_z() {
+    let dag = parse("qreg q[2];\ncreg c[1];\nmeasure q[0] -> c[0];\nif (c[0]==1) z q[1];\n");
+    assert_eq!(types(&dag), vec!["MEASURE", "Z"]);
+    assert!(dag.to_qasm().contains("if (c[0]==1) z q[1];"));
+    assert_eq!(types(&round_trip(&dag)), vec!["MEASURE", "Z"]);
+}
+
+#[test]
+fn conditional_on_another_bit_does_not_fuse() {
+    let dag = parse("qreg q[2];\ncreg c[2];\nmeasure q[0] -> c[0];\nif (c[1]==1) x q[1];\n");
+    assert_eq!(types(&dag), vec!["MEASURE", "X"]);
+}
+
+#[test]
+fn conditional_x_on_the_measured_qubit_does_not_fuse() {
+    let dag = parse("qreg q[2];\ncreg c[1];\nmeasure q[0] -> c[0];\nif (c[0]==1) x q[0];\n");
+    assert_eq!(types(&dag), vec!["MEASURE", "X"]);
+}
+
+#[test]
+fn negctrl_wraps_the_gate_in_x_on_that_control() {
+    let dag = parse("qreg q[3];\nx q[0];\nctrl @ negctrl @ x q[0], q[1], q[2];\n");
+    assert_eq!(types(&dag), vec!["X", "X", "CCX", "X"]);
+    let probs = q_deck_rs::run_qasm(&dag.to_qasm()).unwrap();
+    assert_eq!(probs.len(), 1);
+    assert_eq!(probs[0].0, 0b101);
+    assert_eq!(types(&round_trip(&dag)), types(&dag));
+}
+
+#[test]
+fn ctrl_modifier_makes_a_controlled_node() {
+    let dag = parse("qreg q[2];\nctrl @ x q[0], q[1];\n");
+    let node = dag.nodes.values().next().unwrap();
+    assert_eq!(
+        (node.type_name.as_str(), node.control, node.target),
+        ("CX", 0, 1)
+    );
+}
+
+#[test]
+fn three_control_x_round_trips_with_every_control() {
+    let dag = parse("qreg q[4];\nctrl @ ctrl @ ctrl @ x q[0],q[1],q[2],q[3];\n");
+    let node = dag.nodes.values().next().unwrap();
+    assert_eq!((node.type_name.as_str(), node.target), ("CCX", 3));
+    assert_eq!(node.controls, vec![0, 1, 2]);
+    assert!(
+        dag.to_qasm()
+            .ends_with("ctrl @ ctrl @ ctrl @ x q[0], q[1], q[2], q[3];\n")
+    );
+    let again = round_trip(&dag);
+    assert_eq!(again.to_qasm(), dag.to_qasm());
+
+    // Two controls still write as the qelib1 ccx
+    let dag = parse("qreg q[3];\nctrl @ ctrl @ x q[0], q[1], q[2];\n");
+    assert!(dag.to_qasm().ends_with("ccx q[0], q[1], q[2];\n"));
+}
+
+#[test]
+fn ctrl_modifiers_on_unsupported_bases_are_errors() {
+    for line in [
+        "ctrl @ ctrl @ z q[0], q[1], q[2];",
+        "ctrl @ ctrl @ h q[0], q[1], q[2];",
+        "ctrl @ s q[0], q[1];",
+        "ctrl @ x q[0], q[1], q[2];",
+    ] {
+        let mut dag = CircuitDAG::new();
+        let errors = dag.parse_qasm(&format!("{HEADER}qreg q[3];\n{line}\n"));
+        assert_eq!(errors.len(), 1, "{line}");
+        assert!(dag.nodes.is_empty(), "{line}");
+    }
+    let dag = parse("qreg q[2];\nctrl @ h q[0], q[1];\nctrl @ rx(pi) q[0], q[1];\n");
+    assert_eq!(types(&dag), vec!["CH", "CRX"]);
+}
+
+#[test]
+fn register_reset_resets_every_qubit() {
+    let dag = parse("qreg q[3];\nreset q;\n");
+    assert_eq!(types(&dag), vec!["RESET"; 3]);
+    let dag = parse("qreg r[3];\nreset r;\n");
+    assert_eq!(types(&dag), vec!["RESET"; 3]);
+
+    let mut dag = CircuitDAG::new();
+    let errors = dag.parse_qasm(&format!("{HEADER}qreg q[2];\nreset r;\n"));
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn measure_all_measures_every_qubit() {
+    let mut dag = parse("qreg q[3];\nh q[0];\n");
+    assert_eq!(dag.measure_all(), 1);
+    let qasm = dag.to_qasm();
+    for q in 0..3 {
+        assert_eq!(
+            qasm.matches(&format!("measure q[{q}] -> c[{q}];")).count(),
+            1
+        );
+    }
+    assert_eq!(qasm.matches("measure").count(), 3);
+    assert!(qasm.contains("creg c[3];"));
+}
+
+#[test]
+fn cp_and_cu1_keep_their_names() {
+    let cp = parse("qreg q[2];\ncp(pi/2) q[0], q[1];\n");
+    assert_eq!(types(&cp), vec!["CP"]);
+    assert!(round_trip(&cp).to_qasm().contains("cp(pi/2) q[0], q[1];"));
+
+    let cu1 = parse("qreg q[2];\ncu1(pi/2) q[0], q[1];\n");
+    assert_eq!(types(&cu1), vec!["CU1"]);
+    assert!(round_trip(&cu1).to_qasm().contains("cu1(pi/2) q[0], q[1];"));
+}
+
+#[test]
+fn listing_describes_each_step() {
+    let dag = parse(
+        "qreg q[2];\ncreg c[2];\nh q[0];\ncx q[0], q[1];\nmeasure q[0] -> c[0];\nmeasure q[1] -> c[1];\n",
+    );
+    let listing: Vec<String> = dag.to_listing().into_iter().map(|(_, l)| l).collect();
+    assert_eq!(
+        listing,
+        vec![
+            "Step 0: Hadamard on q0",
+            "Step 1: CNOT q0→q1",
+            "Step 2: Measure q0, q1"
+        ]
+    );
+}
+
+#[test]
+fn swapping_qubits_relabels_gates_and_bits() {
+    let mut dag = parse("qreg q[3];\ncreg c[3];\nh q[0];\ncx q[0], q[1];\nmeasure q[0] -> c[0];\n");
+    dag.swap_qubits(0, 2);
+    let qasm = dag.to_qasm();
+    assert!(qasm.contains("h q[2];"), "{qasm}");
+    assert!(qasm.contains("cx q[2], q[1];"), "{qasm}");
+    assert!(qasm.contains("measure q[2] -> c[2];"), "{qasm}");
+    assert!(!qasm.contains("q[0]"), "{qasm}");
+}
+
+#[test]
+fn identity_gates_parse_and_can_be_removed() {
+    let mut dag = parse("qreg q[2];\nid q[0];\ni q[1];\nh q[1];\n");
+    assert_eq!(types(&dag), vec!["I", "I", "H"]);
+    assert!(dag.to_qasm().contains("id q[0];"));
+    assert_eq!(dag.remove_identities(), 2);
+    assert_eq!(types(&dag), vec!["H"]);
+}
+
+#[test]
+fn comments_stay_above_their_gate() {
+    let dag = parse("qreg q[2];\n// prepare bell\nh q[0];\ncx q[0], q[1];\n// done\n");
+    let qasm = dag.to_qasm();
+    assert!(qasm.contains("// prepare bell\nh q[0];\n"), "{qasm}");
+    assert!(qasm.ends_with("// done\n"), "{qasm}");
+    assert_eq!(round_trip(&dag).to_qasm(), qasm);
+}
+
+#[test]
+fn appending_a_circuit_adds_its_steps_after_ours() {
+    let mut dag = parse("qreg q[2];\nh q[0];\ncx q[0], q[1];\n");
+    assert_eq!(dag.max_step(), 1);
+    dag.append_qasm(&format!("{HEADER}qreg q[3];\nh q[2];\n"))
+        .unwrap();
+    assert_eq!(dag.max_step(), 2);
+    assert_eq!(dag.num_qubits, 3);
+    assert_eq!(types(&dag), vec!["H", "CX", "H"]);
+
+    let before = dag.to_qasm();
+    assert!(dag.append_qasm("qreg q[1];\nh q[0\n").is_err());
+    assert_eq!(dag.to_qasm(), before);
+}
+
+#[test]
+fn inverted_s_is_written_as_sdg() {
+    let mut is_dagger = false;
+    assert!(invert_gate("S", &mut [], &mut is_dagger));
+    assert!(is_dagger);
+    let mut dag = CircuitDAG::new();
+    dag.num_qubits = 1;
+    dag.add_dagger_gate("S", 0, 0);
+    assert!(dag.to_qasm().contains("sdg q[0];"));
+
+    let dag = parse("qreg q[1];\ninv @ s q[0];\n");
+    assert!(
+        dag.nodes
+            .values()
+            .all(|n| n.type_name == "S" && n.is_dagger)
+    );
+    assert!(dag.to_qasm().contains("sdg q[0];"));
+
+    // Inverting again gives back S
+    assert!(invert_gate("S", &mut [], &mut is_dagger));
+    assert!(!is_dagger);
+}
+
+#[test]
+fn out_of_range_qubit_warns_once_and_keeps_parsing() {
+    let dag = parse("qreg q[2];\nh q[0];\nx q[5];\ncx q[0], q[1];\n");
+    assert_eq!(dag.warnings.len(), 1);
+    assert_eq!(dag.warnings[0].line, 4);
+    assert_eq!(dag.warnings[0].message, "q[5] is outside qreg q[2]");
+    assert_eq!(types(&dag), vec!["H", "X", "CX"]);
+    assert!(parse("qreg q[2];\nh q[1];\n").warnings.is_empty());
+}
+
+#[test]
+fn u_gate_round_trips_as_u3() {
+    let dag = parse("qreg q[1];\nU(pi,0,pi) q[0];\n");
+    assert_eq!(types(&dag), vec!["U3"]);
+    let qasm = dag.to_qasm();
+    assert!(qasm.contains("u3(pi, 0, pi) q[0];"), "{qasm}");
+    let again = round_trip(&dag);
+    assert_eq!(again.to_qasm(), qasm);
+
+    let mut dag3 = dag.clone();
+    dag3.openqasm3 = true;
+    assert!(
+        dag3.to_qasm().contains("U(pi, 0, pi) q[0];"),
+        "{}",
+        dag3.to_qasm()
+    );
+}
+
+#[test]
+fn gates_on_disjoint_qubits_share_a_step() {
+    let dag = parse("qreg q[3];\ncx q[0],q[1];\nh q[2];\n");
+    assert_eq!(dag.max_step(), 0);
+    assert_eq!(types(&dag), vec!["CX", "H"]);
+}
+
+fn barrier_qubits(body: &str) -> Vec<usize> {
+    let dag = parse(&format!("qreg q[3];\n{body}"));
+    let barriers: Vec<_> = dag
+        .nodes
+        .values()
+        .filter(|n| n.type_name == "BARRIER")
+        .collect();
+    assert_eq!(barriers.len(), 1, "{body}");
+    barriers[0].controls.clone()
+}
+
+#[test]
+fn barriers_parse_with_and_without_qubits() {
+    assert!(barrier_qubits("barrier;\n").is_empty());
+    assert!(barrier_qubits("barrier q[0], q[1], q[2];\n").is_empty());
+    assert_eq!(barrier_qubits("barrier q[1];\n"), vec![1]);
+    assert_eq!(barrier_qubits("barrier q[0], q[2];\n"), vec![0, 2]);
+}
+
+#[test]
+fn layer_barriers_separate_each_pair_of_steps() {
+    let dag = parse("qreg q[2];\nh q[0];\ncx q[0], q[1];\n");
+    assert!(!dag.to_qasm().contains("barrier"));
+    let qasm = dag.to_qasm_with(true);
+    assert_eq!(qasm.matches("barrier").count(), 1, "{qasm}");
+    assert!(
+        qasm.ends_with("h q[0];\nbarrier q[0], q[1];\ncx q[0], q[1];\n"),
+        "{qasm}"
+    );
+}
+
+#[test]
+fn qubit_labels_round_trip() {
+    let mut dag = parse("qreg q[3];\nh q[0];\ncx q[0], q[2];\n");
+    dag.set_qubit_label(0, "ctrl");
+    dag.set_qubit_label(2, " data ");
+    dag.set_qubit_label(1, "tmp");
+    dag.set_qubit_label(1, "");
+    assert!(dag.to_qasm().contains("// qubit 0 ctrl\n// qubit 2 data\n"));
+    let again = round_trip(&dag);
+    assert_eq!(again.qubit_labels, dag.qubit_labels);
+    assert_eq!(again.qubit_labels.len(), 2);
+    assert_eq!(types(&again), types(&dag));
+}
+
+#[test]
+fn qelib1_cu3_imports_as_a_controlled_node() {
+    let dag = parse("qreg q[2];\ncu3(pi,0,0) q[0],q[1];\n");
+    assert_eq!(types(&dag), vec!["CU3"]);
+    let node = dag.get_node_at(0, 1).unwrap();
+    assert_eq!((node.control, node.target), (0, 1));
+    assert_eq!(node.params, vec![std::f64::consts::PI, 0.0, 0.0]);
+    assert!(dag.to_qasm().ends_with("cu3(pi, 0, 0) q[0], q[1];\n"));
+    assert_eq!(round_trip(&dag).to_qasm(), dag.to_qasm());
+}
+
+#[test]
+fn inline_noise_attaches_to_its_gate_and_round_trips() {
+    let mut dag = parse("qreg q[2];\nh q[0]; // noise depolarizing 0.01\nx q[1];\n");
+    assert_eq!(types(&dag), vec!["H", "X"]);
+    let h = dag.get_node_at(0, 0).unwrap();
+    assert_eq!(h.noise, Some(("depolarizing".to_string(), 0.01)));
+    assert_eq!(dag.get_node_at(0, 1).unwrap().noise, None);
+    assert!(
+        dag.to_qasm()
+            .contains("h q[0]; // noise depolarizing 0.01\nx q[1];\n")
+    );
+    assert_eq!(round_trip(&dag).to_qasm(), dag.to_qasm());
+
+    assert!(dag.set_node_noise(0, 1, Some(("bitflip".to_string(), 0.2))));
+    assert!(!dag.set_node_noise(3, 1, None));
+    assert!(dag.to_qasm().contains("x q[1]; // noise bitflip 0.2\n"));
+}
+
+#[test]
+fn barrier_labels_parse_and_serialize() {
+    let mut dag = parse(
+        "qreg q[2];\nh q[0];\nbarrier q[0], q[1]; // label: pre-measure\nmeasure q[0] -> c[0];\n",
+    );
+    let barrier = dag
+        .nodes
+        .values()
+        .find(|n| n.type_name == "BARRIER")
+        .unwrap();
+    assert_eq!(barrier.label.as_deref(), Some("pre-measure"));
+    let step = barrier.step;
+    assert!(
+        dag.to_qasm()
+            .contains("barrier q[0], q[1]; // label: pre-measure\n")
+    );
+    assert_eq!(round_trip(&dag).to_qasm(), dag.to_qasm());
+
+    dag.set_barrier_label(step, "  ");
+    assert!(!dag.to_qasm().contains("label"));
+}
+
+#[test]
+fn declared_creg_width_survives_a_round_trip() {
+    let dag = parse("qreg q[1];\ncreg c[8];\nh q[0];\nmeasure q[0] -> c[0];\n");
+    assert_eq!(dag.num_cbits, 8);
+    assert!(dag.to_qasm().contains("creg c[8];\n"));
+    assert_eq!(round_trip(&dag).num_cbits, 8);
+    assert_eq!(dag.to_circuit().num_cbits(), 8);
+}
+
+#[test]
+fn controlled_rotations_keep_their_type_without_params() {
+    for gate in ["CRX", "CRY", "CRZ"] {
+        let mut dag = CircuitDAG::new();
+        dag.num_qubits = 2;
+        dag.add_gate(gate, 1, 0, Some(0));
+        let name = gate.to_lowercase();
+        assert!(
+            dag.to_qasm().ends_with(&format!("{name}(0) q[0], q[1];\n")),
+            "{gate}"
+        );
+        assert_eq!(types(&round_trip(&dag)), vec![gate]);
+    }
+    let dag = parse("qreg q[2];\ncrx(0) q[0], q[1];\n");
+    assert_eq!(round_trip(&dag).to_qasm(), dag.to_qasm());
+}
+
+#[test]
+fn creg_is_written_only_when_declared_or_used() {
+    let dag = parse("qreg q[1];\nh q[0];\n");
+    assert!(!dag.to_qasm().contains("creg"), "{}", dag.to_qasm());
+
+    let dag = parse("qreg q[1];\ncreg c[1];\nh q[0];\n");
+    assert_eq!(dag.num_cbits, 1);
+    assert!(dag.to_qasm().contains("creg c[1];\n"));
+    assert_eq!(round_trip(&dag).num_cbits, 1);
+}
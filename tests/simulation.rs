@@ -0,0 +1,405 @@
+use std::f64::consts::FRAC_PI_4;
+
+use num_complex::Complex64;
+use q_deck_rs::quantum::{
+    IncrementalSimulator, QSphereState, simulate_circuit_from, simulate_density,
+};
+use q_deck_rs::{CircuitDAG, StateVector, run_qasm, simulate_circuit};
+
+const HEADER: &str = "OPENQASM 2.0;\ninclude \"qelib1.inc\";\n";
+
+fn parse(body: &str) -> CircuitDAG {
+    let mut dag = CircuitDAG::new();
+    let errors = dag.parse_qasm(&format!("{HEADER}{body}"));
+    assert!(errors.is_empty(), "{errors:?}");
+    dag
+}
+
+fn state(body: &str) -> StateVector {
+    simulate_circuit(&parse(body).to_circuit(), -1)
+}
+
+const BELL: &str = "qreg q[2];\nh q[0];\ncx q[0], q[1];\n";
+
+/// Basis-state probabilities from the statevector and from the density
+/// matrix, which must agree when the circuit has no noise.
+fn ideal_and_density(dag: &CircuitDAG) -> (Vec<f64>, Vec<f64>) {
+    let circuit = dag.to_circuit();
+    let ideal = simulate_circuit(&circuit, -1)
+        .amplitudes
+        .iter()
+        .map(|a| a.norm_sqr())
+        .collect();
+    let rho = simulate_density(&circuit, 0, -1).expect("small enough for a density matrix");
+    (ideal, rho.probabilities())
+}
+
+fn assert_close(a: &[f64], b: &[f64]) {
+    assert_eq!(a.len(), b.len());
+    for (i, (x, y)) in a.iter().zip(b).enumerate() {
+        assert!((x - y).abs() < 1e-9, "|{i}⟩: {x} vs {y}");
+    }
+}
+
+#[test]
+fn density_ccx_matches_statevector() {
+    let dag = parse("qreg q[3];\nx q[0];\nx q[1];\nccx q[0], q[1], q[2];\n");
+    let (ideal, noisy) = ideal_and_density(&dag);
+    assert!((ideal[0b111] - 1.0).abs() < 1e-9);
+    assert_close(&ideal, &noisy);
+}
+
+#[test]
+fn density_diffusion_matches_statevector() {
+    let mut dag = parse("qreg q[4];\nh q[0];\nh q[1];\nh q[2];\nh q[3];\nz q[3];\n");
+    dag.add_diffusion(&[0, 1, 2, 3], dag.max_step() + 1);
+    let (ideal, noisy) = ideal_and_density(&dag);
+    assert_close(&ideal, &noisy);
+}
+
+#[test]
+fn three_control_x_needs_every_control() {
+    let dag =
+        parse("qreg q[4];\nx q[0];\nx q[2];\nctrl @ ctrl @ ctrl @ x q[0], q[1], q[2], q[3];\n");
+    let (ideal, noisy) = ideal_and_density(&dag);
+    assert!((ideal[0b0101] - 1.0).abs() < 1e-9);
+    assert_close(&ideal, &noisy);
+}
+
+#[test]
+fn from_amplitudes_validates_length_and_norm() {
+    let half = Complex64::new(0.5f64.sqrt(), 0.0);
+    assert!(StateVector::from_amplitudes(vec![half, half]).is_ok());
+    assert!(StateVector::from_amplitudes(vec![half; 3]).is_err());
+    assert!(StateVector::from_amplitudes(vec![Complex64::new(1.0, 0.0); 2]).is_err());
+
+    let (state, warning) = StateVector::from_amplitudes_with_warning(vec![
+        Complex64::new(1.0 + 1e-9, 0.0),
+        half * 0.0,
+    ])
+    .unwrap();
+    assert!(warning.is_some());
+    assert!((state.total_probability() - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn simulate_from_rejects_a_state_of_the_wrong_width() {
+    let circuit = parse("qreg q[2];\nx q[1];\n").to_circuit();
+    assert!(simulate_circuit_from(StateVector::new(3), &circuit, -1).is_err());
+    let state = simulate_circuit_from(StateVector::from_basis(2, 0b01), &circuit, -1).unwrap();
+    assert!((state.amplitudes[0b11].norm_sqr() - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn bell_state_expectation_values() {
+    let values = state(BELL).expectation_values(&["Z0", "Z1", "Z0Z1", "Z5", "Q0"]);
+    let expected = [Some(0.0), Some(0.0), Some(1.0), None, None];
+    for (value, expected) in values.iter().zip(expected) {
+        match (value, expected) {
+            (Some(v), Some(e)) => assert!((v - e).abs() < 1e-9, "{v} vs {e}"),
+            _ => assert_eq!(*value, expected),
+        }
+    }
+}
+
+#[test]
+fn molmer_sorensen_entangles_two_qubits() {
+    // MS(π/2)|00⟩ = (|00⟩ − i|11⟩)/√2
+    let amps = state("qreg q[2];\nms(pi/2) q[0], q[1];\n").amplitudes;
+    let h = std::f64::consts::FRAC_1_SQRT_2;
+    let expected = [
+        Complex64::new(h, 0.0),
+        Complex64::new(0.0, 0.0),
+        Complex64::new(0.0, 0.0),
+        Complex64::new(0.0, -h),
+    ];
+    for (i, (a, e)) in amps.iter().zip(expected).enumerate() {
+        assert!((a - e).norm() < 1e-9, "|{i:02b}⟩: {a} vs {e}");
+    }
+}
+
+fn assert_same_state(a: &StateVector, b: &StateVector) {
+    assert_eq!(a.amplitudes.len(), b.amplitudes.len());
+    for (i, (x, y)) in a.amplitudes.iter().zip(&b.amplitudes).enumerate() {
+        assert!((x - y).norm() < 1e-12, "|{i}⟩: {x} vs {y}");
+    }
+}
+
+#[test]
+fn incremental_simulation_matches_full_after_appends() {
+    let mut dag = parse("qreg q[3];\n");
+    let mut sim = IncrementalSimulator::new();
+    let appends: [(&str, usize, Option<usize>); 5] = [
+        ("H", 0, None),
+        ("CX", 1, Some(0)),
+        ("T", 2, None),
+        ("H", 2, None),
+        ("CX", 2, Some(1)),
+    ];
+    for (step, (gate, target, control)) in appends.into_iter().enumerate() {
+        dag.add_gate(gate, target, step as isize, control);
+        let circuit = dag.to_circuit();
+        assert_same_state(sim.simulate(&circuit, -1), &simulate_circuit(&circuit, -1));
+    }
+}
+
+#[test]
+fn raising_the_threshold_hides_unlikely_states() {
+    // P(|1⟩) = sin²(0.1) ≈ 0.00997
+    let sv = state("qreg q[1];\nry(0.2) q[0];\n");
+    let shown = |threshold| -> Vec<usize> {
+        sv.get_qsphere_states_with_threshold(threshold)
+            .iter()
+            .map(|s| s.basis_state)
+            .collect()
+    };
+    assert_eq!(shown(1e-6), vec![0, 1]);
+    assert_eq!(shown(1e-2), vec![0]);
+    assert_eq!(shown(0.999), Vec::<usize>::new());
+}
+
+#[test]
+fn qft_maps_basis_states_to_fourier_phases() {
+    // QFT|x⟩ = Σₖ e^{2πi·xk/8} |k⟩ / √8; |000⟩ gives the uniform superposition
+    let mut dag = parse("qreg q[3];\n");
+    dag.add_qft(0, 3, 0);
+    let circuit = dag.to_circuit();
+    for x in 0..8 {
+        let out = simulate_circuit_from(StateVector::from_basis(3, x), &circuit, -1).unwrap();
+        for (k, a) in out.amplitudes.iter().enumerate() {
+            let angle = 2.0 * std::f64::consts::PI * (x * k) as f64 / 8.0;
+            let expected = Complex64::from_polar(1.0 / 8f64.sqrt(), angle);
+            assert!(
+                (a - expected).norm() < 1e-9,
+                "x={x}, |{k}⟩: {a} vs {expected}"
+            );
+        }
+    }
+}
+
+#[test]
+fn transpiled_gates_act_like_the_originals() {
+    for gate in ["h", "x", "y", "z", "s", "t"] {
+        let dag = parse(&format!("qreg q[1];\n{gate} q[0];\n"));
+        let basis = dag.transpile_to_basis(&["RZ", "SX", "CX"]);
+        assert!(
+            basis
+                .nodes
+                .values()
+                .all(|n| ["RZ", "SX"].contains(&n.type_name.as_str())),
+            "{gate}"
+        );
+        for input in 0..2 {
+            let run = |d: &CircuitDAG| {
+                let mut s =
+                    simulate_circuit_from(StateVector::from_basis(1, input), &d.to_circuit(), -1)
+                        .unwrap();
+                s.canonicalize_phase();
+                s
+            };
+            assert_same_state(&run(&dag), &run(&basis));
+        }
+    }
+}
+
+#[test]
+fn id_and_u0_parse_and_leave_the_state_alone() {
+    let dag = parse("qreg q[1];\nh q[0];\nid q[0];\nu0(1) q[0];\n");
+    let kinds: Vec<(String, Vec<f64>)> = (0..3)
+        .map(|step| {
+            let n = dag.get_node_at(step, 0).unwrap();
+            (n.type_name.clone(), n.params.clone())
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ("H".to_string(), vec![]),
+            ("I".to_string(), vec![]),
+            ("U0".to_string(), vec![1.0]),
+        ]
+    );
+    assert!(dag.to_qasm().ends_with("h q[0];\nid q[0];\nu0(1) q[0];\n"));
+    assert_same_state(
+        &simulate_circuit(&dag.to_circuit(), -1),
+        &state("qreg q[1];\nh q[0];\n"),
+    );
+}
+
+#[test]
+fn cancelling_pairs_simplify_away() {
+    let mut dag = parse("qreg q[1];\nh q[0];\nh q[0];\n");
+    assert_eq!(dag.simplify_cancellations(), 1);
+    assert!(dag.nodes.is_empty());
+
+    // Removing X·X brings the two H together
+    let body = "qreg q[2];\nh q[0];\nx q[0];\nx q[0];\nh q[0];\ns q[1];\nsdg q[1];\nz q[1];\n";
+    let mut dag = parse(body);
+    assert_eq!(dag.simplify_cancellations(), 3);
+    assert_eq!(dag.nodes.len(), 1);
+    assert_same_state(&simulate_circuit(&dag.to_circuit(), -1), &state(body));
+}
+
+#[test]
+fn run_qasm_gives_bell_probabilities() {
+    let probs = run_qasm(&format!("{HEADER}{BELL}")).unwrap();
+    assert_eq!(
+        probs.iter().map(|&(i, _)| i).collect::<Vec<_>>(),
+        vec![0b00, 0b11]
+    );
+    for (_, p) in probs {
+        assert!((p - 0.5).abs() < 1e-12);
+    }
+    let err = run_qasm(&format!("{HEADER}qreg q[1];\nh q[0\n")).unwrap_err();
+    assert!(err.starts_with("line 4:"), "{err}");
+}
+
+#[test]
+fn csv_rows_put_the_highest_qubit_first() {
+    let row = QSphereState {
+        basis_state: 0b001,
+        amplitude: Complex64::new(0.0, -0.5),
+        prob: 0.25,
+        phase: -std::f64::consts::FRAC_PI_2,
+        hamming: 1,
+    };
+    assert_eq!(row.csv_row(3), "1,001,0.25,0,-0.5,-1.5707963267948966");
+
+    let csv = StateVector::from_basis(2, 0b10).to_csv();
+    assert_eq!(csv, "basis,binary,prob,real,imag,phase\n2,10,1,1,0,0\n");
+}
+
+#[test]
+fn cu3_applies_u3_only_when_the_control_is_set() {
+    let cu3 = parse("qreg q[2];\ncu3(pi/3, pi/4, pi/5) q[0], q[1];\n").to_circuit();
+    let u3 = parse("qreg q[2];\nu3(pi/3, pi/4, pi/5) q[1];\n").to_circuit();
+    for input in 0..4 {
+        let got = simulate_circuit_from(StateVector::from_basis(2, input), &cu3, -1).unwrap();
+        let expected = if input & 1 == 1 {
+            simulate_circuit_from(StateVector::from_basis(2, input), &u3, -1).unwrap()
+        } else {
+            StateVector::from_basis(2, input)
+        };
+        assert_same_state(&got, &expected);
+    }
+}
+
+#[test]
+fn controlled_h_and_rotations_act_only_under_their_control() {
+    let probs = |body: &str| -> Vec<f64> {
+        state(body)
+            .amplitudes
+            .iter()
+            .map(|a| a.norm_sqr())
+            .collect()
+    };
+    assert_close(
+        &probs("qreg q[2];\nch q[0], q[1];\n"),
+        &[1.0, 0.0, 0.0, 0.0],
+    );
+    assert_close(
+        &probs("qreg q[2];\nx q[0];\nctrl @ h q[0], q[1];\n"),
+        &[0.0, 0.5, 0.0, 0.5],
+    );
+    for gate in ["crx(pi)", "cry(pi)"] {
+        let body = format!("qreg q[2];\nx q[0];\n{gate} q[0], q[1];\n");
+        assert_close(&probs(&body), &[0.0, 0.0, 0.0, 1.0]);
+    }
+    // CRZ(θ) only changes phases: e^{∓iθ/2} where the control is set
+    let crz = state("qreg q[2];\nh q[0];\nh q[1];\ncrz(pi/2) q[0], q[1];\n");
+    let turn = |angle: f64| Complex64::from_polar(0.5, angle);
+    let expected = StateVector::from_amplitudes(vec![
+        turn(0.0),
+        turn(-FRAC_PI_4),
+        turn(0.0),
+        turn(FRAC_PI_4),
+    ])
+    .unwrap();
+    assert_same_state(&crz, &expected);
+
+    // Three controls flip the target only when all are set
+    let body = "qreg q[4];\nx q[0];\nx q[1];\nctrl @ ctrl @ ctrl @ x q[0], q[1], q[2], q[3];\n";
+    assert!((probs(body)[0b0011] - 1.0).abs() < 1e-12);
+    let body =
+        "qreg q[4];\nx q[0];\nx q[1];\nx q[2];\nctrl @ ctrl @ ctrl @ x q[0], q[1], q[2], q[3];\n";
+    assert!((probs(body)[0b1111] - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn transpiling_keeps_a_gates_noise_and_duration() {
+    let mut dag = parse("qreg q[1];\nx q[0]; // noise amplitude_damping 0.5\n");
+    let id = dag.get_node_at_mut(0, 0).unwrap();
+    dag.nodes.get_mut(&id).unwrap().duration = Some(6.0);
+    let basis = dag.transpile_to_basis(&["RZ", "SX", "CX"]);
+    assert!(basis.nodes.len() > 1);
+
+    let noisy = |d: &CircuitDAG| {
+        simulate_density(&d.to_circuit(), 0, -1)
+            .unwrap()
+            .probabilities()
+    };
+    let (before, after) = (noisy(&dag), noisy(&basis));
+    for (a, b) in before.iter().zip(&after) {
+        assert!((a - b).abs() < 1e-9, "{before:?} vs {after:?}");
+    }
+    assert!((before[0] - 0.5).abs() < 1e-9);
+
+    let total: f64 = basis.nodes.values().map(|n| n.duration.unwrap()).sum();
+    assert!((total - 6.0).abs() < 1e-9);
+}
+
+#[cfg(feature = "parallel")]
+mod parallel {
+    use num_complex::Complex64;
+    use q_deck_rs::quantum::{update_pairs_parallel, update_pairs_serial};
+    use std::time::Instant;
+
+    fn hadamard(a0: Complex64, a1: Complex64) -> (Complex64, Complex64) {
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        ((a0 + a1) * s, (a0 - a1) * s)
+    }
+
+    /// A normalized state with distinct amplitudes everywhere.
+    fn spread_state(num_qubits: usize) -> Vec<Complex64> {
+        let n = 1 << num_qubits;
+        let amps: Vec<Complex64> = (0..n)
+            .map(|i| Complex64::from_polar(1.0 + i as f64 / n as f64, i as f64 * 0.37))
+            .collect();
+        let norm = amps.iter().map(|a| a.norm_sqr()).sum::<f64>().sqrt();
+        amps.into_iter().map(|a| a / norm).collect()
+    }
+
+    #[test]
+    fn parallel_h_matches_serial_on_14_qubits() {
+        let mut serial = spread_state(14);
+        let mut parallel = serial.clone();
+        for q in 0..14 {
+            update_pairs_serial(&mut serial, 1 << q, &hadamard);
+            update_pairs_parallel(&mut parallel, 1 << q, &hadamard);
+        }
+        assert_eq!(serial, parallel);
+    }
+
+    /// `cargo test --release --features parallel -- --ignored --nocapture`
+    /// prints serial and parallel time per H on registers around the
+    /// threshold.
+    #[test]
+    #[ignore]
+    fn parallel_speedup() {
+        for num_qubits in [12, 14, 16, 18, 20, 22] {
+            let mut amps = spread_state(num_qubits);
+            let reps = 40;
+            let start = Instant::now();
+            for r in 0..reps {
+                update_pairs_serial(&mut amps, 1 << (r % num_qubits), &hadamard);
+            }
+            let serial = start.elapsed() / reps as u32;
+            let start = Instant::now();
+            for r in 0..reps {
+                update_pairs_parallel(&mut amps, 1 << (r % num_qubits), &hadamard);
+            }
+            let parallel = start.elapsed() / reps as u32;
+            println!("{num_qubits} qubits: serial {serial:?}, parallel {parallel:?} per H");
+        }
+    }
+}